@@ -0,0 +1,211 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+/// rough time it takes to drag one screenshot into the right folder by hand
+const SECONDS_PER_MANUAL_MOVE: u64 = 5;
+
+/// id of the session this process is journaling to, unset for subcommands that don't organize
+static SESSION: OnceLock<String> = OnceLock::new();
+
+/// what this process did so far, for `ctl status`
+static ORGANIZED: AtomicU64 = AtomicU64::new(0);
+static FAILED: AtomicU64 = AtomicU64::new(0);
+
+/// One line of the journal
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Record {
+    Start {
+        session: String,
+        at: DateTime<Local>,
+        path: PathBuf,
+        watch: bool,
+    },
+    Organized {
+        session: String,
+        at: DateTime<Local>,
+        channel: String,
+    },
+    Failed {
+        session: String,
+        at: DateTime<Local>,
+    },
+}
+
+/// Start journaling a new organizer session
+pub fn start(path: &Path, watch: bool) {
+    let at = Local::now();
+    let session = format!("{}-{}", at.timestamp(), std::process::id());
+    if SESSION.set(session.clone()).is_err() {
+        return;
+    }
+    append(&Record::Start {
+        session,
+        at,
+        path: path.canonicalize().unwrap_or_else(|_| path.to_path_buf()),
+        watch,
+    });
+}
+
+pub fn organized(channel: &str) {
+    ORGANIZED.fetch_add(1, Ordering::Relaxed);
+    if let Some(session) = SESSION.get() {
+        append(&Record::Organized {
+            session: session.clone(),
+            at: Local::now(),
+            channel: channel.to_string(),
+        });
+    }
+}
+
+pub fn failed() {
+    FAILED.fetch_add(1, Ordering::Relaxed);
+    if let Some(session) = SESSION.get() {
+        append(&Record::Failed {
+            session: session.clone(),
+            at: Local::now(),
+        });
+    }
+}
+
+/// Screenshots organized and failed moves of this process so far
+pub fn counts() -> (u64, u64) {
+    (
+        ORGANIZED.load(Ordering::Relaxed),
+        FAILED.load(Ordering::Relaxed),
+    )
+}
+
+/// Print the last `limit` sessions with what they did and the time that saved
+pub fn report(limit: usize) -> io::Result<()> {
+    let file = match fs::File::open(journal_path()) {
+        Ok(file) => file,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            println!("No activity recorded yet");
+            return Ok(());
+        }
+        Err(error) => return Err(error),
+    };
+
+    // keyed by start time so sessions come out in order
+    let mut sessions: BTreeMap<(DateTime<Local>, String), Summary> = BTreeMap::new();
+    let mut starts: BTreeMap<String, (DateTime<Local>, String)> = BTreeMap::new();
+
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        let record = match serde_json::from_str::<Record>(&line) {
+            Ok(record) => record,
+            Err(error) => {
+                log::debug!("Skipping unreadable journal line: {error}");
+                continue;
+            }
+        };
+        match record {
+            Record::Start {
+                session,
+                at,
+                path,
+                watch,
+            } => {
+                let key = (at, session.clone());
+                starts.insert(session, key.clone());
+                sessions.insert(
+                    key,
+                    Summary {
+                        path,
+                        watch,
+                        last_at: at,
+                        ..Summary::default()
+                    },
+                );
+            }
+            Record::Organized {
+                session,
+                at,
+                channel,
+            } => {
+                if let Some(summary) = starts.get(&session).and_then(|key| sessions.get_mut(key)) {
+                    summary.organized += 1;
+                    summary.channels.insert(channel);
+                    summary.last_at = at;
+                }
+            }
+            Record::Failed { session, at } => {
+                if let Some(summary) = starts.get(&session).and_then(|key| sessions.get_mut(key)) {
+                    summary.failed += 1;
+                    summary.last_at = at;
+                }
+            }
+        }
+    }
+
+    let skip = sessions.len().saturating_sub(limit);
+    for ((started, _), summary) in sessions.iter().skip(skip) {
+        println!(
+            "{} -> {}  {} {}",
+            started.format("%Y-%m-%d %H:%M"),
+            summary.last_at.format("%Y-%m-%d %H:%M"),
+            if summary.watch { "watch" } else { "batch" },
+            summary.path.display()
+        );
+        println!(
+            "    organized {} screenshot(s) across {} channel(s), {} failed",
+            summary.organized,
+            summary.channels.len(),
+            summary.failed
+        );
+    }
+
+    let total = sessions
+        .values()
+        .map(|summary| summary.organized)
+        .sum::<u64>();
+    let saved = total * SECONDS_PER_MANUAL_MOVE;
+    println!(
+        "\n{total} screenshot(s) organized over {} session(s), that's {total} manual moves \
+         avoided or about {}h {}m of dragging files around",
+        sessions.len(),
+        saved / 3600,
+        saved / 60 % 60
+    );
+    Ok(())
+}
+
+#[derive(Default)]
+struct Summary {
+    path: PathBuf,
+    watch: bool,
+    last_at: DateTime<Local>,
+    organized: u64,
+    failed: u64,
+    channels: HashSet<String>,
+}
+
+/// the journal is append only, one json record per line
+fn append(record: &Record) {
+    let result = (|| {
+        let path = journal_path();
+        fs::create_dir_all(path.parent().expect("journal has a parent directory"))?;
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        // a single write per record so concurrent moves don't interleave lines
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(line.as_bytes())
+    })();
+    if let Err(error) = result {
+        log::warn!("Failed to write activity journal: {error}");
+    }
+}
+
+pub fn journal_path() -> PathBuf {
+    crate::state_dir().join("activity.jsonl")
+}
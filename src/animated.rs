@@ -0,0 +1,66 @@
+use image::DynamicImage;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::process::Command;
+
+const PNG_SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+/// Short clips saved with the same name a screenshot gets, only decoded through ffmpeg
+pub fn is_video(path: &Path) -> bool {
+    extension(path) == Some("webm")
+}
+
+/// gif, webm or apng, anything where re-encoding to a still format would lose frames
+pub fn is_animated(path: &Path) -> bool {
+    match extension(path) {
+        Some("gif") | Some("webm") => true,
+        Some("png") => is_apng(path).unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Decode `path` like [image::open], for animated files that's their first frame
+pub fn open(path: &Path) -> io::Result<DynamicImage> {
+    if !is_video(path) {
+        return image::open(path).map_err(io::Error::other);
+    }
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-i"])
+        .arg(path)
+        .args(["-frames:v", "1", "-f", "image2pipe", "-c:v", "png", "-"])
+        .output()
+        .map_err(|error| io::Error::new(error.kind(), format!("can't run ffmpeg: {error}")))?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "ffmpeg failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    image::load_from_memory(&output.stdout).map_err(io::Error::other)
+}
+
+fn extension(path: &Path) -> Option<&str> {
+    path.extension().and_then(|extension| extension.to_str())
+}
+
+/// an apng has an acTL chunk somewhere before the image data
+fn is_apng(path: &Path) -> io::Result<bool> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut signature = [0; 8];
+    file.read_exact(&mut signature)?;
+    if &signature != PNG_SIGNATURE {
+        return Ok(false);
+    }
+    loop {
+        let mut header = [0; 8];
+        file.read_exact(&mut header)?;
+        let length = u32::from_be_bytes(header[..4].try_into().unwrap());
+        match &header[4..] {
+            b"acTL" => return Ok(true),
+            b"IDAT" | b"IEND" => return Ok(false),
+            // skip the data and the crc
+            _ => file.seek(SeekFrom::Current(i64::from(length) + 4))?,
+        };
+    }
+}
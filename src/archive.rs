@@ -0,0 +1,161 @@
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::collection::{self, Screenshot};
+use crate::index::Index;
+use crate::retention::ARCHIVE;
+use crate::stats::human_size;
+use crate::{glob, removal, sidecar, sync_tools};
+
+/// Pack the screenshots under `path` of channels matching `channel` that were captured before
+/// `before` into one <channel>_<year>-<month>.zip per month in `out` ( twitch-screenshots/.archive/
+/// by default ), adding to zips that are already there. With `remove` the screenshots are
+/// deleted once they're in a finished zip.
+pub fn run(
+    path: &Path,
+    channel: Option<&str>,
+    before: Option<NaiveDate>,
+    out: Option<&Path>,
+    remove: bool,
+) -> io::Result<()> {
+    let save_dir = collection::save_dir(path);
+    let out = out.map_or_else(|| save_dir.join(ARCHIVE), Path::to_path_buf);
+    let index = Index::open_existing(&save_dir)?;
+
+    let mut zips: BTreeMap<PathBuf, Vec<Screenshot>> = BTreeMap::new();
+    for screenshot in collection::walk(&save_dir) {
+        // there's no month to put it in
+        let Some(captured_at) = screenshot.captured_at else {
+            continue;
+        };
+        if channel.is_some_and(|pattern| !glob::matches(pattern, &screenshot.channel))
+            || before.is_some_and(|before| captured_at.date() >= before)
+        {
+            continue;
+        }
+        let name = format!("{}_{}.zip", screenshot.channel, captured_at.format("%Y-%m"));
+        zips.entry(out.join(name)).or_default().push(screenshot);
+    }
+    if zips.is_empty() {
+        println!("Nothing to archive");
+        return Ok(());
+    }
+    fs::create_dir_all(&out)?;
+
+    let mut archived = 0;
+    for (zip, screenshots) in &zips {
+        let packed = pack(zip, screenshots)?;
+        println!(
+            "{}: {} screenshot(s), {}",
+            zip.display(),
+            packed.len(),
+            human_size(zip.metadata()?.len())
+        );
+        archived += packed.len();
+        if !remove {
+            continue;
+        }
+        for screenshot in packed {
+            if let Err(error) = removal::discard(&save_dir, &screenshot.path, index.as_ref(), false)
+            {
+                log::error!("Failed to remove {}: {error:?}", screenshot.path.display());
+            }
+        }
+    }
+    println!(
+        "Archived {archived} screenshot(s) into {} zip(s){}",
+        zips.len(),
+        if remove {
+            ", the originals are removed"
+        } else {
+            ""
+        }
+    );
+    Ok(())
+}
+
+/// Write `zip` with what it had before plus `screenshots` ( and their sidecars ), through a temp
+/// file so an interrupted run doesn't break it. Returns the screenshots that are in it now,
+/// ones it already had count too.
+fn pack<'a>(zip: &Path, screenshots: &'a [Screenshot]) -> io::Result<Vec<&'a Screenshot>> {
+    let temp = sync_tools::temp_path(zip);
+    let result = write_zip(zip, &temp, screenshots);
+    match result {
+        Ok(packed) => fs::rename(&temp, zip).map(|()| packed),
+        Err(error) => {
+            let _ = fs::remove_file(&temp);
+            Err(error)
+        }
+    }
+}
+
+fn write_zip<'a>(
+    zip: &Path,
+    temp: &Path,
+    screenshots: &'a [Screenshot],
+) -> io::Result<Vec<&'a Screenshot>> {
+    let mut writer = ZipWriter::new(BufWriter::new(File::create(temp)?));
+
+    // name -> size of what's in there already
+    let mut existing = HashMap::new();
+    if zip.exists() {
+        let mut archive = ZipArchive::new(File::open(zip)?)?;
+        for i in 0..archive.len() {
+            let file = archive.by_index_raw(i)?;
+            existing.insert(file.name()?.into_owned(), file.size());
+        }
+        writer.merge_archive(archive)?;
+    }
+
+    let mut packed = Vec::new();
+    for screenshot in screenshots {
+        let name = screenshot.file_name();
+        let size = screenshot.path.metadata()?.len();
+        match existing.get(&name) {
+            Some(&archived) if archived == size => {
+                packed.push(screenshot);
+                continue;
+            }
+            Some(_) => {
+                log::warn!(
+                    "{} already has a different {name}, leaving {}",
+                    zip.display(),
+                    screenshot.path.display()
+                );
+                continue;
+            }
+            None => {}
+        }
+
+        // pngs and what --convert makes are compressed already
+        let mut options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .large_file(size > u32::MAX as u64);
+        if let Some(time) = screenshot
+            .captured_at
+            .and_then(|captured_at| captured_at.try_into().ok())
+        {
+            options = options.last_modified_time(time);
+        }
+        writer.start_file(&name, options)?;
+        io::copy(&mut File::open(&screenshot.path)?, &mut writer)?;
+
+        let sidecar = sidecar::path_for(&screenshot.path);
+        if sidecar.exists() {
+            writer.start_file(format!("{name}.json"), options.large_file(false))?;
+            io::copy(&mut File::open(&sidecar)?, &mut writer)?;
+        }
+        existing.insert(name, size);
+        packed.push(screenshot);
+    }
+
+    let mut file = writer.finish()?;
+    file.flush()?;
+    file.get_ref().sync_all()?;
+    Ok(packed)
+}
@@ -0,0 +1,151 @@
+use chrono::NaiveDateTime;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::plan::SAVE_TO;
+use crate::{
+    capture_time, channel_name, is_screenshot, manifest, same_channel, sharding, strip_extension,
+    sync_tools,
+};
+
+/// A screenshot that already sits in the organized tree
+#[derive(Debug, Clone)]
+pub struct Screenshot {
+    pub path: PathBuf,
+    pub channel: String,
+    pub captured_at: Option<NaiveDateTime>,
+}
+
+impl Screenshot {
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// Commands accept either the folder that was organized or the twitch-screenshots folder itself
+pub fn save_dir(path: &Path) -> PathBuf {
+    let nested = path.join(SAVE_TO);
+    if nested.is_dir() {
+        nested
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Every screenshot under the save dir, sorted by capture time. Only files inside their own
+/// channel folder count, fan-out links elsewhere would show up twice otherwise.
+pub fn walk(save_dir: &Path) -> Vec<Screenshot> {
+    screenshots(save_dir, save_dir)
+}
+
+/// The screenshots in one channel folder of the save dir, sorted by capture time
+pub fn channel(save_dir: &Path, channel_dir: &Path) -> Vec<Screenshot> {
+    screenshots(save_dir, channel_dir)
+}
+
+/// Channel folders in the save dir with nothing left in them but empty folders, and the marker
+/// if it was split, or the manifest
+pub fn empty_channel_dirs(save_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(save_dir) else {
+        return Vec::new();
+    };
+    let mut dirs = entries
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry.file_type().is_ok_and(|ft| ft.is_dir())
+                && !entry.file_name().to_string_lossy().starts_with('.')
+        })
+        .map(|entry| entry.path())
+        .filter(|dir| {
+            WalkDir::new(dir)
+                .into_iter()
+                .filter_map(Result::ok)
+                .all(|entry| {
+                    entry.file_type().is_dir()
+                        || entry.file_name() == sharding::MARKER
+                        || entry.file_name() == manifest::FILE
+                })
+        })
+        .collect::<Vec<_>>();
+    dirs.sort();
+    dirs
+}
+
+/// Remove an empty channel folder, see [empty_channel_dirs]
+pub fn remove_channel_dir(dir: &Path) -> io::Result<()> {
+    // deepest first, a folder only goes once what's in it is gone
+    for entry in WalkDir::new(dir).contents_first(true) {
+        let entry = entry?;
+        if entry.file_type().is_dir() {
+            fs::remove_dir(entry.path())?;
+        } else {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove every channel folder in the save dir that was left empty, returns how many went
+pub fn remove_empty_channels(save_dir: &Path) -> usize {
+    let mut removed = 0;
+    for dir in empty_channel_dirs(save_dir) {
+        match remove_channel_dir(&dir) {
+            Ok(()) => {
+                log::info!("Removed empty channel folder {}", dir.display());
+                removed += 1;
+            }
+            Err(error) => log::warn!("Failed to remove empty folder {}: {error}", dir.display()),
+        }
+    }
+    removed
+}
+
+fn screenshots(save_dir: &Path, dir: &Path) -> Vec<Screenshot> {
+    let mut screenshots = WalkDir::new(dir)
+        .min_depth(1)
+        .into_iter()
+        // thumbnails, archived and original screenshots are kept in hidden folders, unfinished
+        // files are hidden too
+        .filter_entry(|entry| {
+            entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.')
+        })
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy();
+            if strip_extension(&file_name).is_none() || sync_tools::is_temp(&file_name) {
+                return None;
+            }
+            let mut components = entry.path().strip_prefix(save_dir).ok()?.components();
+            let Some(Component::Normal(top)) = components.next() else {
+                return None;
+            };
+            // left in the save dir, not in a channel folder
+            components.next()?;
+            // the folder may be named the way the streamer capitalizes their name
+            let top = top.to_string_lossy();
+            let named = channel_name(&file_name);
+            let channel = if same_channel(&top, &named) {
+                named
+            } else if !is_screenshot(entry.path()) {
+                // organized by OCR or a decision, it's the folder that's named after the channel
+                top.into_owned()
+            } else {
+                // a fan-out link of another channel's screenshot
+                return None;
+            };
+            Some(Screenshot {
+                captured_at: capture_time(&file_name),
+                channel,
+                path: entry.into_path(),
+            })
+        })
+        .collect::<Vec<_>>();
+    screenshots.sort_by(|a, b| (a.captured_at, &a.path).cmp(&(b.captured_at, &b.path)));
+    screenshots
+}
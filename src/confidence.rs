@@ -0,0 +1,193 @@
+use chrono::{NaiveDate, NaiveTime};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use crate::{iso_parts, strip_duplicate, strip_extension};
+
+/// how much each part of a name adds to the score, they add up to 1
+const EXTENSION: f32 = 0.3;
+const STRUCTURE: f32 = 0.2;
+const DATE: f32 = 0.3;
+const TIME: f32 = 0.1;
+const CHANNEL: f32 = 0.1;
+
+/// Default for --min-confidence, a name with an image extension, a channel and the three parts
+/// of a time after it and a date that parses. The time or channel alone don't make up for a
+/// missing date, together they're worth less than it.
+pub const DEFAULT_MIN: f32 = EXTENSION + STRUCTURE + DATE;
+
+/// set by --min-confidence, as the bits of an f32
+static MIN: AtomicU32 = AtomicU32::new(DEFAULT_MIN.to_bits());
+
+/// lowercase names of channels that have a folder already
+static KNOWN: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// How sure it is that a file is a twitch screenshot, going by the parts of its name
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Score {
+    /// png, or what --convert makes of one
+    pub extension: f32,
+    /// a channel followed by four `_` separated parts, the date and time
+    pub structure: f32,
+    /// the date part is like Sat-Jan-18-2025
+    pub date: f32,
+    /// the time parts are like 1_06_05-PM
+    pub time: f32,
+    /// there's a folder of the channel already
+    pub channel: f32,
+}
+
+impl Score {
+    pub fn total(&self) -> f32 {
+        self.extension + self.structure + self.date + self.time + self.channel
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.2} ( extension {:.2}, structure {:.2}, date {:.2}, time {:.2}, channel {:.2} )",
+            self.total(),
+            self.extension,
+            self.structure,
+            self.date,
+            self.time,
+            self.channel
+        )
+    }
+}
+
+/// Score how much `filename` looks like the name twitch gives a screenshot
+pub fn score(filename: &str) -> Score {
+    let Some(stem) = strip_extension(filename) else {
+        // the rest doesn't matter for what isn't an image
+        return Score::default();
+    };
+    let mut score = Score {
+        extension: EXTENSION,
+        ..Score::default()
+    };
+
+    // renamed by --rename-to-iso, that only happens to screenshots
+    if let Some((_, channel)) = iso_parts(stem) {
+        score.structure = STRUCTURE;
+        score.date = DATE;
+        score.time = TIME;
+        score.channel = if is_known(channel) { CHANNEL } else { 0.0 };
+        return score;
+    }
+
+    // the channel name can have _ in it, the date and time are the last four parts
+    let parts = strip_duplicate(stem).split('_').collect::<Vec<_>>();
+    let Some(at) = parts.len().checked_sub(4) else {
+        return score;
+    };
+    let channel = parts[..at].join("_");
+    if !channel.is_empty() {
+        score.structure = STRUCTURE;
+    }
+
+    // the weekday is redundant, a mismatching one doesn't make it less of a date
+    let date = parts[at]
+        .split_once('-')
+        .is_some_and(|(_, date)| NaiveDate::parse_from_str(date, "%b-%d-%Y").is_ok());
+    if date {
+        score.date = DATE;
+    }
+
+    let time = parts[at + 1..].join("_");
+    if NaiveTime::parse_from_str(&time, "%I_%M_%S-%p").is_ok() {
+        score.time = TIME;
+    }
+
+    if !channel.is_empty() && is_known(&channel) {
+        score.channel = CHANNEL;
+    }
+    score
+}
+
+/// Files need a score of at least `min` from now on to be taken for screenshots
+pub fn set_min(min: f32) {
+    MIN.store(min.to_bits(), Ordering::Relaxed);
+}
+
+/// What a file needs to score to be taken for a screenshot
+pub fn min() -> f32 {
+    f32::from_bits(MIN.load(Ordering::Relaxed))
+}
+
+/// Remember the channel folders in `save_dir`, screenshots of those channels score higher
+pub fn know_channels(save_dir: &Path) {
+    let Ok(entries) = fs::read_dir(save_dir) else {
+        return;
+    };
+    let mut known = KNOWN.lock().unwrap();
+    known.extend(
+        entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_ok_and(|ft| ft.is_dir()))
+            .map(|entry| entry.file_name().to_string_lossy().to_lowercase())
+            .filter(|name| !name.starts_with('.')),
+    );
+}
+
+/// Remember that `channel` has a folder now
+pub fn know(channel: &str) {
+    KNOWN.lock().unwrap().insert(channel.to_lowercase());
+}
+
+/// Whether `channel` has a folder
+pub fn is_known(channel: &str) -> bool {
+    KNOWN.lock().unwrap().contains(&channel.to_lowercase())
+}
+
+/// For clap, a confidence from 0 to 1
+pub fn parse_min(text: &str) -> Result<f32, String> {
+    let min = text
+        .parse::<f32>()
+        .map_err(|_| format!("\"{text}\" isn't a number"))?;
+    if (0.0..=1.0).contains(&min) {
+        Ok(min)
+    } else {
+        Err("a confidence goes from 0 to 1".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_matches_score_one() {
+        know("fullmatch");
+        let scored = score("fullmatch_Sat-Jan-18-2025_1_06_05-PM.png");
+        assert!((scored.total() - 1.0).abs() < 1e-6, "{scored}");
+    }
+
+    #[test]
+    fn dates_are_enough_by_default() {
+        let scored = score("unknownchannel_Sat-Jan-18-2025_1_06_05-PM.png");
+        assert!(scored.total() >= DEFAULT_MIN, "{scored}");
+        let scored = score("unknownchannel_Sat-Jan-18-2025_1_06_05.png");
+        assert!(scored.total() >= DEFAULT_MIN, "{scored}");
+    }
+
+    #[test]
+    fn names_without_a_date_fall_short_by_default() {
+        know("nodate");
+        let scored = score("nodate_notes_1_06_05-PM.png");
+        assert_eq!(scored.date, 0.0);
+        assert!(scored.time > 0.0 && scored.channel > 0.0, "{scored}");
+        assert!(scored.total() < DEFAULT_MIN, "{scored}");
+    }
+
+    #[test]
+    fn other_files_score_nothing() {
+        assert_eq!(score("notes.txt").total(), 0.0);
+    }
+}
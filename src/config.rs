@@ -0,0 +1,479 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use toml_edit::DocumentMut;
+
+use crate::{glob, same_channel};
+
+/// Settings read from config.toml, everything in it is optional
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Discord compatible webhook that gets a message for every organized screenshot
+    pub webhook_url: Option<String>,
+
+    /// How often a move is tried while something else holds the file, 5 by default
+    pub move_attempts: Option<u32>,
+
+    /// Folders and files left out when looking for screenshots, by name
+    pub exclude: Exclude,
+
+    /// The only channels organized, all of them when empty
+    pub channels: Channels,
+
+    /// Extra destinations for screenshots matching a rule, as [[fanout]] tables
+    pub fanout: Vec<FanoutRule>,
+
+    /// Split channel folders into year/month subfolders once they get too big
+    pub sharding: Option<Sharding>,
+
+    /// Keep downscaled copies of every screenshot in twitch-screenshots/.thumbs/
+    pub thumbnails: Option<Thumbnails>,
+
+    /// Check channel names against Twitch with an app of your own
+    pub twitch: Option<Twitch>,
+
+    /// Get rid of the oldest screenshots of a channel once it's over its limits
+    pub retention: Option<Retention>,
+
+    /// Mirror organized screenshots to an S3 compatible bucket
+    pub s3: Option<S3>,
+
+    /// Where files that can't be organized are put aside
+    pub quarantine: Quarantine,
+
+    /// How channels are shown in stats, the gallery and notifications
+    pub labels: Labels,
+
+    /// What `--interactive` was told to do with files named like some patterns, for every run
+    pub decisions: Decisions,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Sharding {
+    /// files in a flat channel folder before it's split, file managers choke past ~10k
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+
+    /// leave a symlink at every old location so existing links and bookmarks keep working
+    #[serde(default)]
+    pub symlinks: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Thumbnails {
+    /// bounding box, the aspect ratio is kept
+    pub width: u32,
+    pub height: u32,
+
+    /// jpeg quality from 1 to 100, webp thumbnails are always lossless
+    pub quality: u8,
+
+    pub format: ThumbnailFormat,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailFormat {
+    Jpeg,
+    Webp,
+}
+
+impl Default for Thumbnails {
+    fn default() -> Self {
+        Thumbnails {
+            width: 320,
+            height: 180,
+            quality: 80,
+            format: ThumbnailFormat::Jpeg,
+        }
+    }
+}
+
+/// Credentials of an app registered at dev.twitch.tv
+#[derive(Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Twitch {
+    pub client_id: String,
+    pub client_secret: String,
+
+    /// name channel folders the way the streamer capitalizes their name instead of the login
+    #[serde(default)]
+    pub display_names: bool,
+
+    /// ask what game a channel is playing when a screenshot of it comes in, for `{game}` in
+    /// fan-out folders and the index
+    #[serde(default)]
+    pub games: bool,
+
+    /// link every screenshot to its moment in the vod, in the index and a <screenshot>.json
+    #[serde(default)]
+    pub vods: bool,
+}
+
+// the config is logged at debug level, the secret stays out of it
+impl fmt::Debug for Twitch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Twitch")
+            .field("client_id", &self.client_id)
+            .field("client_secret", &"..")
+            .field("display_names", &self.display_names)
+            .field("games", &self.games)
+            .field("vods", &self.vods)
+            .finish()
+    }
+}
+
+/// An S3 compatible bucket ( AWS, MinIO, Cloudflare R2, Backblaze B2, .. ) and keys for it
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct S3 {
+    /// e.g. https://s3.eu-central-1.amazonaws.com
+    pub endpoint: String,
+
+    #[serde(default = "default_region")]
+    pub region: String,
+
+    pub bucket: String,
+
+    /// put in front of the path in twitch-screenshots/ for the key of each file
+    #[serde(default)]
+    pub prefix: String,
+
+    pub access_key_id: String,
+    pub secret_access_key: String,
+
+    /// <endpoint>/<bucket>/<key> urls instead of <bucket>.<endpoint host>/<key>, most self hosted
+    /// servers need this
+    #[serde(default)]
+    pub path_style: bool,
+}
+
+// same as for [Twitch], the secret stays out of the logs
+impl fmt::Debug for S3 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("S3")
+            .field("endpoint", &self.endpoint)
+            .field("region", &self.region)
+            .field("bucket", &self.bucket)
+            .field("prefix", &self.prefix)
+            .field("access_key_id", &self.access_key_id)
+            .field("secret_access_key", &"..")
+            .field("path_style", &self.path_style)
+            .finish()
+    }
+}
+
+/// Channel login -> how it's shown, e.g. `xqc = "xQc 🐸"`. Folders on disk keep the login.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(transparent)]
+pub struct Labels(pub BTreeMap<String, String>);
+
+impl Labels {
+    /// How `channel` is shown, the name itself when it has no label
+    pub fn of<'a>(&'a self, channel: &'a str) -> &'a str {
+        self.0
+            .iter()
+            .find(|(login, _)| same_channel(login, channel))
+            .map_or(channel, |(_, label)| label)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Name patterns of folders and files that are never looked into or organized, `*` and `?`
+/// wildcards, case insensitive. Defaults to `[".*"]`, hidden ones like the temp folders of sync
+/// clients. The save dir is always left out.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(transparent)]
+pub struct Exclude(pub Vec<String>);
+
+impl Default for Exclude {
+    fn default() -> Self {
+        Exclude(vec![".*".to_string()])
+    }
+}
+
+impl Exclude {
+    /// Whether a folder or file named `name` is left out
+    pub fn matches(&self, name: &str) -> bool {
+        self.0.iter().any(|pattern| glob::matches(pattern, name))
+    }
+}
+
+/// Channel patterns with `*` and `?` wildcards, case insensitive. Only screenshots ( and clips ) of
+/// matching channels are organized, the rest are left where they are. Empty for every channel.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(transparent)]
+pub struct Channels(pub Vec<String>);
+
+impl Channels {
+    /// Whether screenshots of `channel` are organized
+    pub fn allows(&self, channel: &str) -> bool {
+        self.0.is_empty() || self.0.iter().any(|pattern| glob::matches(pattern, channel))
+    }
+}
+
+/// Files that keep failing to move, or look like a screenshot with a mangled name, go into a
+/// folder of their own instead of staying in the downloads
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Quarantine {
+    /// relative to twitch-screenshots/ unless absolute, defaults to twitch-screenshots/_unsorted/
+    pub dir: Option<PathBuf>,
+
+    /// failed moves of a file before it's put aside, 0 leaves them where they are
+    pub after_failures: u32,
+
+    /// also put aside files named almost like a twitch screenshot
+    pub near_misses: bool,
+}
+
+impl Default for Quarantine {
+    fn default() -> Self {
+        Quarantine {
+            dir: None,
+            after_failures: 3,
+            near_misses: true,
+        }
+    }
+}
+
+/// Files that don't look enough like a screenshot to be organized on their own, or are of a
+/// channel twitch doesn't know
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Decisions {
+    /// file name patterns that are always left where they are, `*` and `?` wildcards, case
+    /// insensitive
+    pub skip: Vec<String>,
+
+    /// file name pattern -> channel the files go to, same syntax as `skip`
+    pub channels: BTreeMap<String, String>,
+}
+
+impl Decisions {
+    /// Whether files named `file_name` are left alone
+    pub fn skips(&self, file_name: &str) -> bool {
+        self.skip
+            .iter()
+            .any(|pattern| glob::matches(pattern, file_name))
+    }
+
+    /// The channel files named `file_name` were assigned to
+    pub fn channel_for(&self, file_name: &str) -> Option<&str> {
+        self.channels
+            .iter()
+            .find(|(pattern, _)| glob::matches(pattern, file_name))
+            .map(|(_, channel)| channel.as_str())
+    }
+}
+
+/// How much of each channel is kept, the oldest screenshots go first
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Retention {
+    /// in days, by capture time
+    pub max_age: Option<u32>,
+    pub max_per_channel: Option<usize>,
+    /// bytes, or a string like "500MB" or "2GB"
+    pub max_total_size: Option<Size>,
+
+    pub action: RetentionAction,
+
+    /// where `archive` puts screenshots, relative to twitch-screenshots/ unless absolute.
+    /// Defaults to twitch-screenshots/.archive/
+    pub archive_dir: Option<PathBuf>,
+
+    /// other limits for some channels, as [retention.channels.<channel>] tables
+    pub channels: BTreeMap<String, Limits>,
+}
+
+/// Limits of one channel, whatever isn't set comes from [retention]
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    pub max_age: Option<u32>,
+    pub max_per_channel: Option<usize>,
+    pub max_total_size: Option<Size>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionAction {
+    #[default]
+    Delete,
+    Trash,
+    /// move to [Retention::archive_dir], e.g. on a bigger disk
+    Archive,
+}
+
+impl Retention {
+    /// The limits of `channel`, its own where it has them
+    pub fn limits_for(&self, channel: &str) -> Limits {
+        let own = self
+            .channels
+            .iter()
+            .find(|(name, _)| same_channel(name, channel))
+            .map(|(_, limits)| limits.clone())
+            .unwrap_or_default();
+        Limits {
+            max_age: own.max_age.or(self.max_age),
+            max_per_channel: own.max_per_channel.or(self.max_per_channel),
+            max_total_size: own.max_total_size.or(self.max_total_size),
+        }
+    }
+}
+
+/// A size in bytes, written as a number or with a KB / MB / GB / TB suffix
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(try_from = "RawSize")]
+pub struct Size(pub u64);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawSize {
+    Bytes(u64),
+    Text(String),
+}
+
+impl TryFrom<RawSize> for Size {
+    type Error = String;
+
+    fn try_from(raw: RawSize) -> Result<Self, Self::Error> {
+        let text = match raw {
+            RawSize::Bytes(bytes) => return Ok(Size(bytes)),
+            RawSize::Text(text) => text,
+        };
+        let upper = text.trim().to_uppercase();
+        let number_end = upper
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(upper.len());
+        let (number, unit) = upper.split_at(number_end);
+        let unit: u64 = match unit.trim() {
+            "" | "B" => 1,
+            "K" | "KB" => 1 << 10,
+            "M" | "MB" => 1 << 20,
+            "G" | "GB" => 1 << 30,
+            "T" | "TB" => 1 << 40,
+            _ => return Err(format!("unknown unit in size \"{text}\"")),
+        };
+        let number: f64 = number
+            .parse()
+            .map_err(|_| format!("invalid size \"{text}\""))?;
+        Ok(Size((number * unit as f64) as u64))
+    }
+}
+
+/// Send matching screenshots to more folders on top of their channel folder
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FanoutRule {
+    /// channel name pattern, `*` and `?` wildcards, case insensitive
+    #[serde(default = "any")]
+    pub channel: String,
+
+    /// file name pattern, same syntax as `channel`
+    #[serde(default = "any")]
+    pub name: String,
+
+    /// folders relative to twitch-screenshots/ that get a link to the screenshot,
+    /// `{channel}` is replaced with the channel name and `{game}` with what was being played
+    /// ( see [Twitch::games] ), folders with `{game}` are left out when it's not known
+    pub to: Vec<String>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        toml::from_str(&contents).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+}
+
+/// Add `decision` to the config at `path` ( made if it isn't there ), leaving everything else in it
+/// as it was, comments and all
+pub fn remember(path: &Path, decision: Decision) -> io::Result<()> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(error),
+    };
+    let mut document = contents
+        .parse::<DocumentMut>()
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    let decisions = document
+        .entry("decisions")
+        .or_insert_with(toml_edit::table)
+        .as_table_mut()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "decisions isn't a table"))?;
+    match decision {
+        Decision::Skip(pattern) => {
+            let skip = decisions
+                .entry("skip")
+                .or_insert_with(|| toml_edit::value(toml_edit::Array::new()))
+                .as_array_mut()
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidData, "decisions.skip isn't a list")
+                })?;
+            skip.push(pattern);
+        }
+        Decision::Channel(pattern, channel) => {
+            let channels = decisions
+                .entry("channels")
+                .or_insert_with(toml_edit::table)
+                .as_table_like_mut()
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "decisions.channels isn't a table",
+                    )
+                })?;
+            channels.insert(&pattern, toml_edit::value(channel));
+        }
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, document.to_string())
+}
+
+/// Something `--interactive` was told to do from now on
+pub enum Decision {
+    /// leave files named like the pattern alone
+    Skip(String),
+    /// files named like the pattern go to the channel
+    Channel(String, String),
+}
+
+/// `--config` if given, otherwise the default location when there's a file there
+pub fn resolve_path(explicit: Option<&Path>) -> Option<PathBuf> {
+    match explicit {
+        Some(path) => Some(path.to_path_buf()),
+        None => default_path().filter(|path| path.is_file()),
+    }
+}
+
+/// ~/.config/twitch-screenshot-organizer/config.toml or the platform equivalent
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("twitch-screenshot-organizer").join("config.toml"))
+}
+
+fn any() -> String {
+    "*".to_string()
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_max_files() -> usize {
+    10_000
+}
@@ -0,0 +1,178 @@
+use clap::ValueEnum;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// how long `ctl` waits for the watcher to answer, it may be busy catching up
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// set by `ctl pause`, nothing is organized while it is
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// What `ctl` can ask the running watcher
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Request {
+    /// What it's watching, whether it's paused and what it did so far
+    Status,
+    /// Stop organizing until `resume`, new screenshots are remembered meanwhile
+    Pause,
+    /// Organize again, starting with what came in while paused
+    Resume,
+    /// Go through the whole folder for screenshots the watcher missed
+    Rescan,
+    /// Load the config file again
+    ReloadConfig,
+    /// What `status` shows, as json
+    #[value(hide = true)]
+    Health,
+}
+
+/// A request that came in on the control socket, `reply` takes the answer
+pub struct Message {
+    pub request: Request,
+    pub reply: mpsc::Sender<String>,
+}
+
+/// Whether `ctl pause` was sent and no `ctl resume` since
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::Relaxed)
+}
+
+/// Listen on the control socket in the background. Pause and resume are handled right there so
+/// they work while the watcher is busy, the rest is passed to `tx` to be answered.
+pub fn listen(tx: mpsc::Sender<Message>) -> io::Result<()> {
+    let listener = platform::bind()?;
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream.and_then(|mut stream| answer(&mut stream, &tx));
+            if let Err(error) = result {
+                log::warn!("Failed to answer a control request: {error}");
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Send `request` to the running watcher and return its answer
+pub fn send(request: Request) -> io::Result<String> {
+    let mut stream = platform::connect().map_err(|error| {
+        io::Error::new(
+            error.kind(),
+            format!("no watcher is running, or it's from before `ctl`: {error}"),
+        )
+    })?;
+    writeln!(stream, "{}", name(request))?;
+    stream.flush()?;
+    let mut answer = String::new();
+    stream.read_to_string(&mut answer)?;
+    Ok(answer)
+}
+
+/// one request per connection, a line with its name
+fn answer<S: Read + Write>(stream: &mut S, tx: &mpsc::Sender<Message>) -> io::Result<()> {
+    let mut line = String::new();
+    BufReader::new(&mut *stream).read_line(&mut line)?;
+    // another watcher checking if this one is still there
+    if line.is_empty() {
+        return Ok(());
+    }
+    let answer = match Request::from_str(line.trim(), true) {
+        Ok(Request::Pause) => {
+            PAUSED.store(true, Ordering::Relaxed);
+            log::info!("Paused, nothing is organized until `ctl resume`");
+            "Paused".to_string()
+        }
+        Ok(Request::Resume) => {
+            PAUSED.store(false, Ordering::Relaxed);
+            log::info!("Resumed");
+            "Resumed".to_string()
+        }
+        Ok(request) => {
+            let (reply, answer) = mpsc::channel();
+            tx.send(Message { request, reply })
+                .map_err(|_| io::Error::other("the watcher stopped"))?;
+            answer
+                .recv_timeout(ANSWER_TIMEOUT)
+                .unwrap_or_else(|_| "The watcher is busy catching up, try again in a bit".into())
+        }
+        Err(_) => format!("Unknown request {}", line.trim()),
+    };
+    writeln!(stream, "{answer}")?;
+    stream.flush()
+}
+
+fn name(request: Request) -> String {
+    request
+        .to_possible_value()
+        .expect("no request is skipped")
+        .get_name()
+        .to_string()
+}
+
+/// a socket next to the rest of the state, only the user can get there
+#[cfg(unix)]
+mod platform {
+    use super::*;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::PathBuf;
+
+    pub fn bind() -> io::Result<UnixListener> {
+        let path = socket_path();
+        // it's left behind by a watcher that didn't get to clean up unless one answers
+        if UnixStream::connect(&path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("another watcher is listening on {}", path.display()),
+            ));
+        }
+        let _ = fs::remove_file(&path);
+        fs::create_dir_all(path.parent().expect("state dir"))?;
+        UnixListener::bind(&path)
+    }
+
+    pub fn connect() -> io::Result<UnixStream> {
+        UnixStream::connect(socket_path())
+    }
+
+    fn socket_path() -> PathBuf {
+        crate::state_dir().join("control.sock")
+    }
+}
+
+/// std can't make named pipes, a loopback port written next to the rest of the state does the
+/// same job
+#[cfg(windows)]
+mod platform {
+    use super::*;
+    use std::net::{Ipv4Addr, TcpListener, TcpStream};
+    use std::path::PathBuf;
+
+    pub fn bind() -> io::Result<TcpListener> {
+        if connect().is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "another watcher is listening already",
+            ));
+        }
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let path = port_path();
+        fs::create_dir_all(path.parent().expect("state dir"))?;
+        fs::write(path, listener.local_addr()?.port().to_string())?;
+        Ok(listener)
+    }
+
+    pub fn connect() -> io::Result<TcpStream> {
+        let port: u16 = fs::read_to_string(port_path())?
+            .trim()
+            .parse()
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        TcpStream::connect((Ipv4Addr::LOCALHOST, port))
+    }
+
+    fn port_path() -> PathBuf {
+        crate::state_dir().join("control.port")
+    }
+}
@@ -0,0 +1,111 @@
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::DynamicImage;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+
+use crate::plan::Plan;
+use crate::storage::StorageBackend;
+
+/// originals kept with --keep-original, mirrors the channel folders
+const ORIGINALS: &str = ".originals";
+
+/// rav1e speed from 1 ( slowest, smallest files ) to 10
+const AVIF_SPEED: u8 = 6;
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Webp,
+    Avif,
+    Jpeg,
+}
+
+impl Format {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Webp => "webp",
+            Format::Avif => "avif",
+            Format::Jpeg => "jpg",
+        }
+    }
+}
+
+/// Transcode the organized screenshot at `plan.target` to `format`. The png is moved to
+/// .originals/ when `keep_original`, removed otherwise. Returns the plan pointing at the
+/// converted file and where the original went, if it was kept.
+pub fn convert(
+    plan: &Plan,
+    format: Format,
+    quality: u8,
+    keep_original: bool,
+    storage: &dyn StorageBackend,
+) -> io::Result<(Plan, Option<PathBuf>)> {
+    let image = image::open(&plan.target).map_err(io::Error::other)?;
+    let encoded = encode(&image, format, quality.clamp(1, 100))?;
+
+    let converted = plan.converted(format.extension());
+    storage.write(&converted.target, &encoded)?;
+
+    let original = if keep_original {
+        let original = original_path(plan)?;
+        storage.create_dir_all(original.parent().expect("original has a parent directory"))?;
+        storage.rename(&plan.target, &original)?;
+        Some(original)
+    } else {
+        storage.delete(&plan.target)?;
+        None
+    };
+
+    log::debug!(
+        "Converted {} to {}",
+        plan.target.display(),
+        converted.target.display()
+    );
+    Ok((converted, original))
+}
+
+fn encode(image: &DynamicImage, format: Format, quality: u8) -> io::Result<Vec<u8>> {
+    let mut encoded = Cursor::new(Vec::new());
+    match format {
+        Format::Jpeg => image
+            .to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut encoded, quality))
+            .map_err(io::Error::other)?,
+        Format::Avif => image
+            .to_rgba8()
+            .write_with_encoder(AvifEncoder::new_with_speed_quality(
+                &mut encoded,
+                AVIF_SPEED,
+                quality,
+            ))
+            .map_err(io::Error::other)?,
+        // the image crate only writes lossless webp, libwebp it is
+        Format::Webp => {
+            let rgba = image.to_rgba8();
+            let webp = webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height())
+                .encode(f32::from(quality));
+            return Ok(webp.to_vec());
+        }
+    }
+    Ok(encoded.into_inner())
+}
+
+/// .originals/<channel>/<file>.png in the save dir
+fn original_path(plan: &Plan) -> io::Result<PathBuf> {
+    let save_dir = plan
+        .channel_dir
+        .parent()
+        .expect("channel dir is in the save dir");
+    let relative = plan.target.strip_prefix(save_dir).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not in {}", plan.target.display(), save_dir.display()),
+        )
+    })?;
+    Ok(save_dir.join(ORIGINALS).join(relative))
+}
+
+/// A converted screenshot is already at `target` with the extension of `format`
+pub fn already_converted(target: &Path, format: Format) -> bool {
+    target.with_extension(format.extension()).is_file()
+}
@@ -0,0 +1,67 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// set for the re-launched child, it keeps `--daemonize` among its arguments but doesn't detach
+/// again. The arguments are passed on untouched, `--daemonize` could be the value of another flag.
+const CHILD_ENV: &str = "TWITCH_SCREENSHOT_ORGANIZER_DAEMONIZED";
+
+/// Re-launch the current binary with the same arguments detached from the terminal, with
+/// stdout/stderr (and so the logs) appended to `log_file`. Returns the child pid, the caller is
+/// expected to exit right after.
+pub fn daemonize(log_file: &Path) -> io::Result<u32> {
+    if let Some(parent) = log_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_file)?;
+
+    let mut command = Command::new(env::current_exe()?);
+    command
+        .args(env::args_os().skip(1))
+        .env(CHILD_ENV, "1")
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    detach(&mut command);
+
+    let child = command.spawn()?;
+    Ok(child.id())
+}
+
+/// Whether this is the process [daemonize] launched, it's detached already
+pub fn is_daemonized() -> bool {
+    env::var_os(CHILD_ENV).is_some()
+}
+
+/// log file used when daemonized, lives in the platform state dir
+pub fn default_log_file() -> PathBuf {
+    crate::state_dir().join("daemon.log")
+}
+
+/// start a new session so the child doesn't get SIGHUP when the terminal goes away
+#[cfg(unix)]
+fn detach(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    // SAFETY: setsid is async-signal-safe and is the only thing run between fork and exec
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(windows)]
+fn detach(command: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const DETACHED_PROCESS: u32 = 0x0000_0008;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+    command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+}
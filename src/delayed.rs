@@ -0,0 +1,129 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Default for --max-concurrent-moves, the threads working off the queue. A job mostly checks a
+/// file every now and then so a couple go a long way.
+pub const DEFAULT_WORKERS: usize = 2;
+
+/// set by --max-concurrent-moves, before the workers are started
+static WORKERS: AtomicUsize = AtomicUsize::new(DEFAULT_WORKERS);
+
+/// with this many screenshots waiting new ones are left where they are for the next rescan,
+/// instead of piling up without end while the disk can't keep up
+const CAPACITY: usize = 10_000;
+
+/// A piece of work that's run again after the delay it returns, until it returns none
+pub type Job = Box<dyn FnMut() -> Option<Duration> + Send>;
+
+static QUEUE: OnceLock<Queue> = OnceLock::new();
+
+struct Queue {
+    state: Mutex<State>,
+    /// signaled when a job is added, it may be due before the one the workers wait for
+    added: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    /// when each job is due next, by number so jobs due at the same time go first come first served
+    due: BinaryHeap<Reverse<(Instant, u64)>>,
+    jobs: HashMap<u64, Job>,
+    next: u64,
+    running: usize,
+}
+
+impl State {
+    fn push(&mut self, job: Job, at: Instant) {
+        let id = self.next;
+        self.next += 1;
+        self.due.push(Reverse((at, id)));
+        self.jobs.insert(id, job);
+    }
+}
+
+/// What [schedule] fails with when the queue is full, the job is dropped
+#[derive(Debug, Error)]
+#[error("the move was dropped, {CAPACITY} screenshots are waiting to be moved already")]
+pub struct QueueFull;
+
+/// Run `job` on the queue's workers once `after` passed, starting them on first use. Fails with
+/// [QueueFull] when the queue is full.
+pub fn schedule(job: Job, after: Duration) -> io::Result<()> {
+    let queue = QUEUE.get_or_init(start);
+    let mut state = queue.lock();
+    if state.jobs.len() + state.running >= CAPACITY {
+        return Err(io::Error::other(QueueFull));
+    }
+    state.push(job, Instant::now() + after);
+    queue.added.notify_one();
+    Ok(())
+}
+
+/// Work off the queue with `workers` threads, so at most that many moves happen at the same time.
+/// Only has an effect before the first job is scheduled.
+pub fn set_workers(workers: usize) {
+    WORKERS.store(workers.max(1), Ordering::Relaxed);
+}
+
+/// Jobs waiting or running right now
+pub fn pending() -> usize {
+    QUEUE.get().map_or(0, |queue| {
+        let state = queue.lock();
+        state.jobs.len() + state.running
+    })
+}
+
+fn start() -> Queue {
+    for _ in 0..WORKERS.load(Ordering::Relaxed) {
+        thread::spawn(|| work(QUEUE.wait()));
+    }
+    Queue {
+        state: Mutex::default(),
+        added: Condvar::new(),
+    }
+}
+
+impl Queue {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // jobs run without it, a panicking one can't poison it
+        self.state.lock().unwrap()
+    }
+}
+
+fn work(queue: &Queue) {
+    let mut state = queue.lock();
+    loop {
+        let now = Instant::now();
+        let Some(&Reverse((at, id))) = state.due.peek() else {
+            state = queue.added.wait(state).unwrap();
+            continue;
+        };
+        if at > now {
+            state = queue.added.wait_timeout(state, at - now).unwrap().0;
+            continue;
+        }
+        state.due.pop();
+        let mut job = state.jobs.remove(&id).expect("every due job is there");
+        state.running += 1;
+        drop(state);
+
+        let again = panic::catch_unwind(AssertUnwindSafe(&mut job)).unwrap_or_else(|_| {
+            // the panic itself is printed by the hook, the worker carries on with the next job
+            log::error!("A delayed move panicked, it's left where it is");
+            None
+        });
+
+        state = queue.lock();
+        state.running -= 1;
+        if let Some(after) = again {
+            state.push(job, Instant::now() + after);
+        }
+    }
+}
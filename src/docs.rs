@@ -0,0 +1,31 @@
+use clap::CommandFactory;
+use clap_complete::Shell;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::Args;
+
+/// Print the completion script for `shell`
+pub fn completions(shell: Shell) -> io::Result<()> {
+    let mut command = Args::command();
+    let name = command.get_name().to_string();
+    // written in one go, clap_complete panics on a closed stdout
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, name, &mut script);
+    io::stdout().write_all(&script)
+}
+
+/// Print the man page, or with `dir` write one for every subcommand in there
+pub fn man(dir: Option<&Path>) -> io::Result<()> {
+    let command = Args::command();
+    match dir {
+        Some(dir) => {
+            fs::create_dir_all(dir)?;
+            clap_mangen::generate_to(command, dir)?;
+            println!("Wrote the man pages to {}", dir.display());
+            Ok(())
+        }
+        None => clap_mangen::Man::new(command).render(&mut io::stdout()),
+    }
+}
@@ -0,0 +1,91 @@
+use image::imageops::FilterType;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+use crate::animated;
+use crate::collection::Screenshot;
+use crate::index::Index;
+
+/// Near duplicates among `screenshots`, in groups of two or more sorted by capture time.
+/// Screenshots count as near duplicates when their perceptual hashes differ in at most
+/// `threshold` of 64 bits, only screenshots of the same channel are compared.
+pub fn find(
+    screenshots: Vec<Screenshot>,
+    index: Option<&Index>,
+    threshold: u32,
+) -> Vec<Vec<Screenshot>> {
+    let mut channels: BTreeMap<String, Vec<(Screenshot, u64)>> = BTreeMap::new();
+    for screenshot in screenshots {
+        match hash(&screenshot.path, index) {
+            Ok(dhash) => channels
+                .entry(screenshot.channel.clone())
+                .or_default()
+                .push((screenshot, dhash)),
+            Err(error) => log::warn!("Skipping {}: {error}", screenshot.path.display()),
+        }
+    }
+
+    let mut groups = Vec::new();
+    for shots in channels.into_values() {
+        // union find over every pair close enough
+        let mut parent = (0..shots.len()).collect::<Vec<_>>();
+        for i in 0..shots.len() {
+            for j in i + 1..shots.len() {
+                if (shots[i].1 ^ shots[j].1).count_ones() <= threshold {
+                    let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                    parent[a.max(b)] = a.min(b);
+                }
+            }
+        }
+
+        let mut members: BTreeMap<usize, Vec<Screenshot>> = BTreeMap::new();
+        for (i, (screenshot, _)) in shots.into_iter().enumerate() {
+            let group = root(&mut parent, i);
+            members.entry(group).or_default().push(screenshot);
+        }
+        groups.extend(members.into_values().filter(|group| group.len() > 1));
+    }
+    for group in &mut groups {
+        group.sort_by(|a, b| (a.captured_at, &a.path).cmp(&(b.captured_at, &b.path)));
+    }
+    groups.sort_by(|a, b| a[0].captured_at.cmp(&b[0].captured_at));
+    groups
+}
+
+fn root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// the index remembers hashes so only new screenshots have to be decoded
+fn hash(path: &Path, index: Option<&Index>) -> io::Result<u64> {
+    if let Some(dhash) = index.map(|index| index.dhash(path)).transpose()?.flatten() {
+        return Ok(dhash);
+    }
+    let dhash = dhash(path)?;
+    if let Some(index) = index {
+        index.set_dhash(path, dhash)?;
+    }
+    Ok(dhash)
+}
+
+/// Difference hash: shrink to 9x8 grey and keep whether each pixel is brighter than its right
+/// neighbour. Survives rescaling and recompression, a changed chat line or overlay flips a few bits.
+pub fn dhash(path: &Path) -> io::Result<u64> {
+    let image = animated::open(path)?;
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut dhash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            dhash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                dhash |= 1;
+            }
+        }
+    }
+    Ok(dhash)
+}
@@ -0,0 +1,38 @@
+use std::io;
+use thiserror::Error;
+
+/// Why a run didn't go through, each kind exits with its own code so scripts and service
+/// managers can tell them apart
+#[derive(Debug, Error)]
+pub enum Error {
+    /// the config file can't be read or doesn't make sense, or flags don't go together
+    #[error("{0}")]
+    Config(String),
+
+    /// the folder to organize or the destination can't be used
+    #[error("{0}")]
+    Path(String),
+
+    /// some files couldn't be organized, everything else was
+    #[error("{0} file(s) couldn't be organized")]
+    Partial(u64),
+
+    /// anything else, `what` is what was being done
+    #[error("{what} failed: {source:?}")]
+    Failed {
+        what: &'static str,
+        source: io::Error,
+    },
+}
+
+impl Error {
+    /// 1 for failures, 2 for config errors and 3 for paths that can't be used, 0 is left for
+    /// everything going well
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Partial(_) | Error::Failed { .. } => 1,
+            Error::Config(_) => 2,
+            Error::Path(_) => 3,
+        }
+    }
+}
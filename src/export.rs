@@ -0,0 +1,110 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+
+use crate::index::{self, Index};
+use crate::progress::{self, Progress};
+use crate::search::{self, Query};
+use crate::stats::csv_field;
+use crate::{collection, in_parallel};
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default)]
+pub enum Format {
+    #[default]
+    Csv,
+    Json,
+}
+
+/// One organized screenshot
+#[derive(Serialize, Debug)]
+struct Row {
+    channel: String,
+    captured_at: Option<NaiveDateTime>,
+    path: String,
+    size: u64,
+    sha256: String,
+}
+
+/// Write every organized screenshot under `path` with its channel, capture time, path, size and
+/// sha256 to `out`, stdout if there's none. The hashes come from the index when there is one,
+/// otherwise every screenshot is hashed.
+pub fn run(path: &Path, format: Format, out: Option<&Path>) -> io::Result<()> {
+    let hashes = match Index::open_existing(&collection::save_dir(path))? {
+        Some(index) => index.hashes()?,
+        None => HashMap::new(),
+    };
+    let screenshots = search::find(path, &Query::default())?;
+
+    let rows = Mutex::new(Vec::with_capacity(screenshots.len()));
+    let progress = (hashes.is_empty() && screenshots.len() >= progress::THRESHOLD)
+        .then(|| Progress::start(screenshots.len()))
+        .flatten();
+    let jobs = thread::available_parallelism().map_or(1, |cores| cores.get());
+    in_parallel(&screenshots, jobs, |screenshot| {
+        let row = (|| {
+            let sha256 = match hashes.get(&screenshot.path) {
+                Some(hash) => hash.clone(),
+                None => index::hash_file(&screenshot.path)?,
+            };
+            io::Result::Ok(Row {
+                channel: screenshot.channel.clone(),
+                captured_at: screenshot.captured_at,
+                path: screenshot.path.to_string_lossy().into_owned(),
+                size: screenshot.path.metadata()?.len(),
+                sha256,
+            })
+        })();
+        match row {
+            Ok(row) => rows.lock().unwrap().push(row),
+            Err(error) => log::warn!("Skipping {}: {error}", screenshot.path.display()),
+        }
+        if let Some(progress) = &progress {
+            progress.inc();
+        }
+    });
+    drop(progress);
+    let mut rows = rows.into_inner().unwrap();
+    rows.sort_by(|a, b| {
+        (&a.channel, a.captured_at, &a.path).cmp(&(&b.channel, b.captured_at, &b.path))
+    });
+
+    let mut writer: Box<dyn Write> = match out {
+        Some(out) => Box::new(BufWriter::new(File::create(out)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    match format {
+        Format::Csv => write_csv(&mut writer, &rows)?,
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut writer, &rows)?;
+            writeln!(writer)?;
+        }
+    }
+    writer.flush()?;
+    if let Some(out) = out {
+        log::info!("Exported {} screenshot(s) to {}", rows.len(), out.display());
+    }
+    Ok(())
+}
+
+fn write_csv(writer: &mut dyn Write, rows: &[Row]) -> io::Result<()> {
+    writeln!(writer, "channel,captured_at,path,size,sha256")?;
+    for row in rows {
+        writeln!(
+            writer,
+            "{},{},{},{},{}",
+            csv_field(&row.channel),
+            row.captured_at
+                .map(|time| time.format("%Y-%m-%dT%H:%M:%S").to_string())
+                .unwrap_or_default(),
+            csv_field(&row.path),
+            row.size,
+            row.sha256
+        )?;
+    }
+    Ok(())
+}
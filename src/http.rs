@@ -0,0 +1,24 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+use ureq::tls::{TlsConfig, TlsProvider};
+use ureq::Agent;
+
+/// shared agent for everything that talks http, tls goes through the platform's native stack
+pub fn agent() -> &'static Agent {
+    static AGENT: OnceLock<Agent> = OnceLock::new();
+    AGENT.get_or_init(|| {
+        Agent::config_builder()
+            .tls_config(
+                TlsConfig::builder()
+                    .provider(TlsProvider::NativeTls)
+                    .build(),
+            )
+            .timeout_global(Some(Duration::from_secs(30)))
+            .user_agent(concat!(
+                "twitch-screenshot-organizer/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .build()
+            .into()
+    })
+}
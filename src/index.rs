@@ -0,0 +1,598 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::capture_time;
+use crate::collection::Screenshot;
+use crate::config::Labels;
+use crate::lock;
+use crate::twitch::{Cached, Stream, User};
+
+/// lives in the save dir next to the channel folders
+pub const FILE_NAME: &str = ".index.sqlite";
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// schema changes, each one runs once and bumps `PRAGMA user_version`
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE screenshots (
+        path TEXT PRIMARY KEY,
+        file_name TEXT NOT NULL,
+        channel TEXT NOT NULL,
+        captured_at TEXT,
+        hash TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        organized_at TEXT NOT NULL
+    );
+    CREATE INDEX screenshots_channel ON screenshots (channel);
+    CREATE INDEX screenshots_name_size ON screenshots (file_name, size);",
+    // perceptual hash for finding near duplicates, filled in the first time it's needed
+    "ALTER TABLE screenshots ADD COLUMN dhash INTEGER;",
+    // what the channel was playing, from twitch when it was organized
+    "ALTER TABLE screenshots ADD COLUMN game TEXT;",
+    // what twitch said the last time it was asked, ids and names are null when it had nothing
+    "CREATE TABLE twitch_users (
+        login TEXT PRIMARY KEY,
+        id TEXT,
+        display_name TEXT,
+        checked TEXT NOT NULL
+    );
+    CREATE TABLE twitch_streams (
+        login TEXT PRIMARY KEY,
+        started_at TEXT,
+        game_id TEXT,
+        game_name TEXT,
+        checked TEXT NOT NULL
+    );",
+    // link to the moment in the vod of the stream
+    "ALTER TABLE screenshots ADD COLUMN vod_url TEXT;",
+    // which channel and day every change to the screenshots was on, numbered across the whole
+    // table so whoever built something from the index can catch up on what changed since
+    "CREATE TABLE changes (
+        channel TEXT NOT NULL,
+        day TEXT NOT NULL,
+        version INTEGER NOT NULL,
+        PRIMARY KEY (channel, day)
+    );
+    CREATE TRIGGER screenshots_insert AFTER INSERT ON screenshots BEGIN
+        INSERT OR REPLACE INTO changes VALUES (NEW.channel, COALESCE(substr(NEW.captured_at, 1, 10), ''),
+            (SELECT COALESCE(MAX(version), 0) + 1 FROM changes));
+    END;
+    CREATE TRIGGER screenshots_delete AFTER DELETE ON screenshots BEGIN
+        INSERT OR REPLACE INTO changes VALUES (OLD.channel, COALESCE(substr(OLD.captured_at, 1, 10), ''),
+            (SELECT COALESCE(MAX(version), 0) + 1 FROM changes));
+    END;
+    CREATE TRIGGER screenshots_update AFTER UPDATE OF path, channel, captured_at ON screenshots BEGIN
+        INSERT OR REPLACE INTO changes VALUES (OLD.channel, COALESCE(substr(OLD.captured_at, 1, 10), ''),
+            (SELECT COALESCE(MAX(version), 0) + 1 FROM changes));
+        INSERT OR REPLACE INTO changes VALUES (NEW.channel, COALESCE(substr(NEW.captured_at, 1, 10), ''),
+            (SELECT COALESCE(MAX(version), 0) + 1 FROM changes));
+    END;",
+    // [labels] of the config, for commands run without it
+    "CREATE TABLE labels (
+        channel TEXT PRIMARY KEY,
+        label TEXT NOT NULL
+    );",
+    // the name it came with, when --rename-to-iso gave it another one
+    "ALTER TABLE screenshots ADD COLUMN original_name TEXT;
+    CREATE INDEX screenshots_original_name_size ON screenshots (original_name, size);",
+];
+
+/// SQLite index of every organized screenshot. Paths are stored relative to the save dir so
+/// the whole tree can be moved around with its index.
+#[derive(Debug)]
+pub struct Index {
+    root: PathBuf,
+    conn: Mutex<Connection>,
+}
+
+impl Index {
+    /// Open ( or create ) the index of the save dir `root`
+    pub fn open(root: &Path) -> io::Result<Index> {
+        std::fs::create_dir_all(root)?;
+        let root = root.canonicalize()?;
+        let conn = Connection::open(root.join(FILE_NAME)).map_err(db)?;
+        conn.busy_timeout(Duration::from_secs(5)).map_err(db)?;
+        // wal needs memory shared between everyone using the database, can't have that across
+        // machines
+        let journal_mode = if lock::is_shared(&root) {
+            "DELETE"
+        } else {
+            "WAL"
+        };
+        conn.pragma_update(None, "journal_mode", journal_mode)
+            .map_err(db)?;
+        {
+            let _lock = lock::acquire(&root)?;
+            migrate(&conn).map_err(db)?;
+        }
+
+        Ok(Index {
+            root,
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Add or refresh the row for a screenshot that now lives at `path`
+    pub fn record(&self, channel: &str, path: &Path) -> io::Result<()> {
+        let file_name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let size = path.metadata()?.len();
+        let hash = hash_file(path)?;
+        let captured_at = capture_time(&file_name).map(|time| time.format(TIME_FORMAT).to_string());
+
+        self.write(
+            "INSERT OR REPLACE INTO screenshots
+                (path, file_name, channel, captured_at, hash, size, organized_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                self.relative(path),
+                file_name,
+                channel,
+                captured_at,
+                hash,
+                size,
+                Local::now().to_rfc3339(),
+            ],
+        )
+    }
+
+    /// Follow a screenshot that was moved within the organized tree
+    pub fn relocate(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.write(
+            "UPDATE screenshots SET path = ?2 WHERE path = ?1",
+            params![self.relative_lexical(from), self.relative(to)],
+        )
+    }
+
+    /// Follow a screenshot that was moved into another channel's folder under a new name
+    pub fn retag(&self, from: &Path, to: &Path, channel: &str) -> io::Result<()> {
+        let file_name = to.file_name().unwrap().to_string_lossy().into_owned();
+        self.write(
+            "UPDATE screenshots SET path = ?2, file_name = ?3, channel = ?4 WHERE path = ?1",
+            params![
+                self.relative_lexical(from),
+                self.relative(to),
+                file_name,
+                channel
+            ],
+        )
+    }
+
+    /// Forget a screenshot that was deleted
+    pub fn remove(&self, path: &Path) -> io::Result<()> {
+        self.write(
+            "DELETE FROM screenshots WHERE path = ?1",
+            params![self.relative_lexical(path)],
+        )
+    }
+
+    /// The cached perceptual hash of the screenshot at `path`
+    pub fn dhash(&self, path: &Path) -> io::Result<Option<u64>> {
+        let dhash: Option<Option<i64>> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT dhash FROM screenshots WHERE path = ?1",
+                params![self.relative(path)],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(db)?;
+        // stored as the same 64 bits, sqlite integers are signed
+        Ok(dhash.flatten().map(|dhash| dhash as u64))
+    }
+
+    /// Remember the perceptual hash of an indexed screenshot
+    pub fn set_dhash(&self, path: &Path, dhash: u64) -> io::Result<()> {
+        self.write(
+            "UPDATE screenshots SET dhash = ?2 WHERE path = ?1",
+            params![self.relative(path), dhash as i64],
+        )
+    }
+
+    /// Remember the name an indexed screenshot had before it was organized
+    pub fn set_original_name(&self, path: &Path, name: &str) -> io::Result<()> {
+        self.write(
+            "UPDATE screenshots SET original_name = ?2 WHERE path = ?1",
+            params![self.relative(path), name],
+        )
+    }
+
+    /// Remember what was being played in an indexed screenshot
+    pub fn set_game(&self, path: &Path, game: &str) -> io::Result<()> {
+        self.write(
+            "UPDATE screenshots SET game = ?2 WHERE path = ?1",
+            params![self.relative(path), game],
+        )
+    }
+
+    /// Remember where in its vod an indexed screenshot was taken
+    pub fn set_vod(&self, path: &Path, url: &str) -> io::Result<()> {
+        self.write(
+            "UPDATE screenshots SET vod_url = ?2 WHERE path = ?1",
+            params![self.relative(path), url],
+        )
+    }
+
+    /// What twitch said about the channel `login` the last time it was asked
+    pub fn cached_twitch_user(&self, login: &str) -> io::Result<Option<Cached<User>>> {
+        let row: Option<(Option<String>, Option<String>, String)> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT id, display_name, checked FROM twitch_users WHERE login = ?1",
+                params![login],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()
+            .map_err(db)?;
+        Ok(row.and_then(|(id, display_name, checked)| {
+            Some(Cached {
+                value: id.zip(display_name).map(|(id, display_name)| User {
+                    id,
+                    login: login.to_string(),
+                    display_name,
+                }),
+                checked: parse_checked(&checked)?,
+            })
+        }))
+    }
+
+    pub fn cache_twitch_user(&self, login: &str, cached: &Cached<User>) -> io::Result<()> {
+        let user = cached.value.as_ref();
+        self.write(
+            "INSERT OR REPLACE INTO twitch_users (login, id, display_name, checked)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                login,
+                user.map(|user| &user.id),
+                user.map(|user| &user.display_name),
+                cached.checked.to_rfc3339(),
+            ],
+        )
+    }
+
+    /// The stream of `login` the last time twitch was asked, none in it if it wasn't live
+    pub fn cached_twitch_stream(&self, login: &str) -> io::Result<Option<Cached<Stream>>> {
+        type Row = (Option<String>, Option<String>, Option<String>, String);
+        let row: Option<Row> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT started_at, game_id, game_name, checked FROM twitch_streams
+                 WHERE login = ?1",
+                params![login],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .optional()
+            .map_err(db)?;
+        Ok(row.and_then(|(started_at, game_id, game_name, checked)| {
+            let started_at = started_at
+                .as_deref()
+                .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+                .map(|time| time.with_timezone(&Utc));
+            Some(Cached {
+                value: started_at.map(|started_at| Stream {
+                    game_id: game_id.unwrap_or_default(),
+                    game_name: game_name.unwrap_or_default(),
+                    started_at,
+                }),
+                checked: parse_checked(&checked)?,
+            })
+        }))
+    }
+
+    pub fn cache_twitch_stream(&self, login: &str, cached: &Cached<Stream>) -> io::Result<()> {
+        let stream = cached.value.as_ref();
+        self.write(
+            "INSERT OR REPLACE INTO twitch_streams (login, started_at, game_id, game_name, checked)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                login,
+                stream.map(|stream| stream.started_at.to_rfc3339()),
+                stream.map(|stream| &stream.game_id),
+                stream.map(|stream| &stream.game_name),
+                cached.checked.to_rfc3339(),
+            ],
+        )
+    }
+
+    /// Keep the labels of the config in the index, replacing the ones from before
+    pub fn set_labels(&self, labels: &Labels) -> io::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let _lock = lock::acquire(&self.root)?;
+        let transaction = conn.transaction().map_err(db)?;
+        transaction.execute("DELETE FROM labels", []).map_err(db)?;
+        for (channel, label) in &labels.0 {
+            transaction
+                .execute(
+                    "INSERT INTO labels (channel, label) VALUES (?1, ?2)",
+                    params![channel, label],
+                )
+                .map_err(db)?;
+        }
+        transaction.commit().map_err(db)
+    }
+
+    pub fn labels(&self) -> io::Result<Labels> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn
+            .prepare("SELECT channel, label FROM labels")
+            .map_err(db)?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(db)?;
+        rows.collect::<Result<_, _>>().map(Labels).map_err(db)
+    }
+
+    /// Write a consistent copy of the index to `file`, while it may be in use
+    pub fn back_up(&self, file: &Path) -> io::Result<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("VACUUM INTO ?1", params![file.to_string_lossy()])
+            .map_err(db)?;
+        Ok(())
+    }
+
+    /// Open the index of `root` only if one was created before
+    pub fn open_existing(root: &Path) -> io::Result<Option<Index>> {
+        if root.join(FILE_NAME).is_file() {
+            Index::open(root).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Everything in the index, sorted by capture time
+    pub fn screenshots(&self) -> io::Result<Vec<Screenshot>> {
+        self.query_screenshots("TRUE", [])
+    }
+
+    /// Every indexed path with the size the screenshot had when it was indexed
+    pub fn sizes(&self) -> io::Result<HashMap<PathBuf, u64>> {
+        self.by_path("size")
+    }
+
+    /// Every indexed path with the hash of the screenshot when it was indexed
+    pub fn hashes(&self) -> io::Result<HashMap<PathBuf, String>> {
+        self.by_path("hash")
+    }
+
+    /// The screenshots of `channel` on `day` ( the ones without a capture time for none ),
+    /// sorted by capture time
+    pub fn screenshots_on(
+        &self,
+        channel: &str,
+        day: Option<NaiveDate>,
+    ) -> io::Result<Vec<Screenshot>> {
+        self.query_screenshots(
+            "channel = ?1 AND COALESCE(substr(captured_at, 1, 10), '') = ?2",
+            params![channel, day_key(day)],
+        )
+    }
+
+    /// The number of the latest change to the screenshots, 0 before the first one
+    pub fn version(&self) -> io::Result<i64> {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT COALESCE(MAX(version), 0) FROM changes", [], |row| {
+                row.get(0)
+            })
+            .map_err(db)
+    }
+
+    /// Every channel and day that had screenshots added, moved or removed after change `version`
+    pub fn changed_since(&self, version: i64) -> io::Result<Vec<(String, Option<NaiveDate>)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn
+            .prepare("SELECT channel, day FROM changes WHERE version > ?1")
+            .map_err(db)?;
+        let rows = statement
+            .query_map(params![version], |row| {
+                let day: String = row.get(1)?;
+                Ok((
+                    row.get(0)?,
+                    NaiveDate::parse_from_str(&day, "%Y-%m-%d").ok(),
+                ))
+            })
+            .map_err(db)?;
+        rows.collect::<Result<_, _>>().map_err(db)
+    }
+
+    fn query_screenshots(
+        &self,
+        filter: &str,
+        params: impl rusqlite::Params,
+    ) -> io::Result<Vec<Screenshot>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn
+            .prepare(&format!(
+                "SELECT path, channel, captured_at FROM screenshots WHERE {filter}
+                 ORDER BY captured_at, path"
+            ))
+            .map_err(db)?;
+        let rows = statement
+            .query_map(params, |row| {
+                let path: String = row.get(0)?;
+                let captured_at: Option<String> = row.get(2)?;
+                Ok(Screenshot {
+                    path: self.root.join(path),
+                    channel: row.get(1)?,
+                    captured_at: captured_at.as_deref().and_then(parse_time),
+                })
+            })
+            .map_err(db)?;
+        rows.collect::<Result<_, _>>().map_err(db)
+    }
+
+    /// Where the screenshot at `source` was already organized to, if it's still there. Only one
+    /// with its name and size is hashed to make sure, re-captures can have both the same.
+    pub fn find_organized(&self, source: &Path) -> io::Result<Option<PathBuf>> {
+        let file_name = source
+            .file_name()
+            .expect("screenshot has a name")
+            .to_string_lossy();
+        let candidates = {
+            let conn = self.conn.lock().unwrap();
+            let mut statement = conn
+                .prepare(
+                    "SELECT path, hash FROM screenshots
+                     WHERE (file_name = ?1 OR original_name = ?1) AND size = ?2",
+                )
+                .map_err(db)?;
+            let rows = statement
+                .query_map(params![file_name, source.metadata()?.len()], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })
+                .map_err(db)?;
+            rows.collect::<Result<Vec<_>, _>>().map_err(db)?
+        };
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+
+        let hash = hash_file(source)?;
+        Ok(candidates
+            .into_iter()
+            .filter(|(_, indexed)| *indexed == hash)
+            .map(|(path, _)| self.root.join(path))
+            .find(|path| path.exists()))
+    }
+
+    /// `column` of every row by the path of its screenshot
+    fn by_path<T: rusqlite::types::FromSql>(
+        &self,
+        column: &str,
+    ) -> io::Result<HashMap<PathBuf, T>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn
+            .prepare(&format!("SELECT path, {column} FROM screenshots"))
+            .map_err(db)?;
+        let rows = statement
+            .query_map([], |row| {
+                let path: String = row.get(0)?;
+                Ok((self.root.join(path), row.get(1)?))
+            })
+            .map_err(db)?;
+        rows.collect::<Result<_, _>>().map_err(db)
+    }
+
+    /// run a write, taking turns with other machines when the save dir is shared
+    fn write(&self, sql: &str, params: impl rusqlite::Params) -> io::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let _lock = lock::acquire(&self.root)?;
+        conn.execute(sql, params).map_err(db)?;
+        Ok(())
+    }
+
+    fn relative(&self, path: &Path) -> String {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        path.strip_prefix(&self.root)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+
+    /// [Index::relative] for a path that doesn't exist anymore, only its folder is resolved
+    fn relative_lexical(&self, path: &Path) -> String {
+        let path = match (path.parent().map(Path::canonicalize), path.file_name()) {
+            (Some(Ok(parent)), Some(name)) => parent.join(name),
+            _ => path.to_path_buf(),
+        };
+        path.strip_prefix(&self.root)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+}
+
+/// The labels of the config, or when it has none the ones kept in the index of `save_dir`
+pub fn labels(save_dir: &Path, configured: &Labels) -> Labels {
+    if !configured.is_empty() {
+        return configured.clone();
+    }
+    let kept = Index::open_existing(save_dir)
+        .and_then(|index| index.map(|index| index.labels()).transpose());
+    match kept {
+        Ok(labels) => labels.unwrap_or_default(),
+        Err(error) => {
+            log::warn!("Failed to read the channel labels from the index: {error}");
+            Labels::default()
+        }
+    }
+}
+
+/// sha256 of the file contents as hex
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// a day the way the changes table has it, empty for screenshots without a capture time
+fn day_key(day: Option<NaiveDate>) -> String {
+    day.map(|day| day.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+fn parse_time(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, TIME_FORMAT).ok()
+}
+
+fn parse_checked(value: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|time| time.with_timezone(&Local))
+}
+
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        conn.execute_batch(migration)?;
+        conn.pragma_update(None, "user_version", i + 1)?;
+    }
+    Ok(())
+}
+
+fn db(error: rusqlite::Error) -> io::Error {
+    io::Error::other(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const NAME: &str = "chan_Sat-Jan-18-2025_1_06_05-PM.png";
+
+    #[test]
+    fn organized_screenshots_are_found_by_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let save_dir = dir.path().join("twitch-screenshots");
+        let index = Index::open(&save_dir).unwrap();
+        let save_dir = save_dir.canonicalize().unwrap();
+        fs::create_dir_all(save_dir.join("chan")).unwrap();
+        let organized = save_dir.join("chan").join(NAME);
+        fs::write(&organized, b"one").unwrap();
+        index.record("chan", &organized).unwrap();
+
+        let again = dir.path().join(NAME);
+        fs::write(&again, b"one").unwrap();
+        assert_eq!(index.find_organized(&again).unwrap(), Some(organized));
+
+        // a re-capture with the same name and size
+        fs::write(&again, b"two").unwrap();
+        assert_eq!(index.find_organized(&again).unwrap(), None);
+    }
+}
@@ -0,0 +1,144 @@
+use chrono::NaiveDate;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+
+use crate::config::{self, Decision};
+use crate::plan;
+use crate::{strip_extension, Options};
+
+/// Whether there's someone at a terminal to ask
+pub fn can_ask() -> bool {
+    io::stdin().is_terminal() && io::stderr().is_terminal()
+}
+
+/// Ask what to do with `path`, a file that isn't clearly a screenshot to organize because `why`.
+/// `guess` is the channel it would go to as it is. Returns the channel it goes to, none when it's
+/// left where it is. What's decided for files named like it from now on goes into the config.
+pub fn ask(
+    path: &Path,
+    why: &str,
+    guess: Option<&str>,
+    options: &mut Options,
+) -> io::Result<Option<String>> {
+    let file_name = path.file_name().expect("file has a name").to_string_lossy();
+    // answered already for an earlier file named like it
+    if options.config.decisions.skips(&file_name) {
+        return Ok(None);
+    }
+    if let Some(channel) = options.config.decisions.channel_for(&file_name) {
+        return Ok(Some(channel.to_string()));
+    }
+
+    eprintln!("\n{}: {why}", path.display());
+    if let Some(guess) = guess {
+        eprintln!("  [m] move it as it is, into the folder of {guess}");
+    }
+    eprintln!("  [c] move files named like it into the folder of a channel");
+    eprintln!("  [s] skip it this time");
+    eprintln!("  [n] never organize files named like it");
+    loop {
+        let answer = prompt("> ")?;
+        match (answer.to_lowercase().as_str(), guess) {
+            ("m", Some(guess)) => return Ok(Some(guess.to_string())),
+            ("c", _) => {
+                let channel = loop {
+                    let channel = prompt("Channel: ")?;
+                    if plan::is_folder_name(&channel) {
+                        break channel;
+                    }
+                    eprintln!("That can't be a folder name, try another one");
+                };
+                let pattern = ask_pattern(&file_name)?;
+                remember(Decision::Channel(pattern, channel.clone()), options)?;
+                return Ok(Some(channel));
+            }
+            ("s", _) => return Ok(None),
+            ("n", _) => {
+                let pattern = ask_pattern(&file_name)?;
+                remember(Decision::Skip(pattern), options)?;
+                return Ok(None);
+            }
+            _ => eprintln!("Answer with one of the letters in brackets"),
+        }
+    }
+}
+
+/// The channel in a name like `channel_Sat-Jan-18-2025.png`, the part before the date
+pub fn guess_channel(file_name: &str) -> Option<String> {
+    let stem = strip_extension(file_name)?;
+    let date = stem
+        .split(['_', ' '])
+        .find(|part| NaiveDate::parse_from_str(part, "%a-%b-%d-%Y").is_ok())?;
+    let channel = stem[..stem.find(date)?].trim_end_matches(['_', ' ']);
+    (!channel.is_empty() && !channel.contains(' ')).then(|| channel.to_string())
+}
+
+/// the pattern of files named like `file_name` the user settles on
+fn ask_pattern(file_name: &str) -> io::Result<String> {
+    let suggested = pattern(file_name);
+    let pattern = prompt(&format!("Files named like [{suggested}]: "))?;
+    Ok(if pattern.is_empty() {
+        suggested
+    } else {
+        pattern
+    })
+}
+
+/// `file_name` with every part that has a number in it ( dates, times, counters ) as a wildcard
+fn pattern(file_name: &str) -> String {
+    let (stem, extension) = match file_name.rsplit_once('.') {
+        Some((stem, extension)) => (stem, format!(".{extension}")),
+        None => (file_name, String::new()),
+    };
+    let mut pattern = stem
+        .split_inclusive(['_', ' '])
+        .map(|part| {
+            let word = part.trim_end_matches(['_', ' ']);
+            if word.contains(|c: char| c.is_ascii_digit()) {
+                part.replacen(word, "*", 1)
+            } else {
+                part.to_string()
+            }
+        })
+        .collect::<String>();
+    // one wildcard covers a whole run of them
+    while let Some(at) = pattern.find("*_*").or_else(|| pattern.find("* *")) {
+        pattern.replace_range(at..at + 3, "*");
+    }
+    pattern + &extension
+}
+
+fn remember(decision: Decision, options: &mut Options) -> io::Result<()> {
+    let path = options
+        .config_path
+        .clone()
+        .or_else(config::default_path)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "there's no config dir"))?;
+    match &decision {
+        Decision::Skip(pattern) => options.config.decisions.skip.push(pattern.clone()),
+        Decision::Channel(pattern, channel) => {
+            options
+                .config
+                .decisions
+                .channels
+                .insert(pattern.clone(), channel.clone());
+        }
+    }
+    config::remember(&path, decision)?;
+    log::info!("Saved that to {}", path.display());
+    Ok(())
+}
+
+/// a line from stdin, failing when it's closed
+fn prompt(question: &str) -> io::Result<String> {
+    eprint!("{question}");
+    io::stderr().flush()?;
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "stdin was closed",
+        ));
+    }
+    Ok(line.trim().to_string())
+}
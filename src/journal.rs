@@ -0,0 +1,391 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::collection;
+use crate::index::Index;
+use crate::plan::SAVE_TO;
+use crate::sidecar;
+
+/// off for replays, moves in a throwaway folder are nothing to undo
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// One line of the move journal, paths are absolute
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Record {
+    Moved {
+        at: DateTime<Local>,
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// a move that was reversed, `from` and `to` are those of the original move
+    Undone {
+        at: DateTime<Local>,
+        from: PathBuf,
+        to: PathBuf,
+    },
+}
+
+/// A journal line as exported, paths are relative to the organized folder or its save dir so
+/// they still mean something once the tree was copied somewhere else
+#[derive(Serialize, Deserialize, Debug)]
+struct Exported {
+    event: Event,
+    at: DateTime<Local>,
+    from: Place,
+    to: Place,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Event {
+    Moved,
+    Undone,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+enum Place {
+    /// relative to the folder that was organized
+    Source(String),
+    /// relative to the folder the channel folders are in
+    Dest(String),
+}
+
+impl Record {
+    fn new(event: Event, at: DateTime<Local>, from: PathBuf, to: PathBuf) -> Record {
+        match event {
+            Event::Moved => Record::Moved { at, from, to },
+            Event::Undone => Record::Undone { at, from, to },
+        }
+    }
+
+    fn parts(&self) -> (Event, DateTime<Local>, &Path, &Path) {
+        match self {
+            Record::Moved { at, from, to } => (Event::Moved, *at, from, to),
+            Record::Undone { at, from, to } => (Event::Undone, *at, from, to),
+        }
+    }
+}
+
+/// Remember that a screenshot was moved so it can be put back later
+pub fn moved(from: &Path, to: &Path) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    append(&[Record::Moved {
+        at: Local::now(),
+        // a name that isn't valid unicode can't go into json, it's put back under its lossy form
+        from: PathBuf::from(absolute(from).to_string_lossy().into_owned()),
+        to: absolute(to),
+    }]);
+}
+
+/// Stop journaling moves for the rest of this run
+pub fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+/// Put the `last` most recent moves in or out of `path` back where they came from, all of them
+/// if `last` is none. With `clean_empty` channel folders left empty are removed.
+pub fn undo(path: &Path, last: Option<usize>, clean_empty: bool) -> io::Result<()> {
+    let roots = Roots::of(path);
+    let index = Index::open_existing(&roots.dest)?;
+    let mut done = Vec::new();
+
+    for (from, to) in latest(&roots, Event::Moved)?
+        .into_iter()
+        .take(last.unwrap_or(usize::MAX))
+    {
+        match reverse(&to, &from) {
+            Ok(()) => {
+                log::info!("Moved back {} -> {}", to.display(), from.display());
+                if let Some(index) = &index {
+                    if let Err(error) = index.remove(&to) {
+                        log::warn!("Failed to drop {} from the index: {error}", to.display());
+                    }
+                }
+                done.push(Record::Undone {
+                    at: Local::now(),
+                    from,
+                    to,
+                });
+            }
+            Err(error) => log::warn!("Can't undo {} -> {}: {error}", from.display(), to.display()),
+        }
+    }
+
+    println!("Undid {} move(s)", done.len());
+    append(&done);
+    if clean_empty {
+        let removed = collection::remove_empty_channels(&roots.dest);
+        println!("Removed {removed} empty channel folder(s)");
+    }
+    Ok(())
+}
+
+/// Apply the `last` most recently undone moves in or out of `path` again
+pub fn redo(path: &Path, last: Option<usize>) -> io::Result<()> {
+    let roots = Roots::of(path);
+    let index = Index::open_existing(&roots.dest)?;
+    let mut done = Vec::new();
+
+    for (from, to) in latest(&roots, Event::Undone)?
+        .into_iter()
+        .take(last.unwrap_or(usize::MAX))
+    {
+        match reverse(&from, &to) {
+            Ok(()) => {
+                log::info!("Moved again {} -> {}", from.display(), to.display());
+                if let Some(index) = &index {
+                    let channel = crate::channel_name(&to.file_name().unwrap().to_string_lossy());
+                    if let Err(error) = index.record(&channel, &to) {
+                        log::warn!("Failed to index {}: {error}", to.display());
+                    }
+                }
+                done.push(Record::Moved {
+                    at: Local::now(),
+                    from,
+                    to,
+                });
+            }
+            Err(error) => log::warn!("Can't redo {} -> {}: {error}", from.display(), to.display()),
+        }
+    }
+
+    println!("Redid {} move(s)", done.len());
+    append(&done);
+    Ok(())
+}
+
+/// Write the journal entries of `path` to `file` with relative paths
+pub fn export(path: &Path, file: &Path) -> io::Result<()> {
+    let (out, exported) = exported(path)?;
+    fs::write(file, out)?;
+    println!("Exported {exported} journal entries to {}", file.display());
+    Ok(())
+}
+
+/// The journal entries of `path` as `export` writes them, and how many there are
+pub fn exported(path: &Path) -> io::Result<(String, usize)> {
+    let roots = Roots::of(path);
+    let mut out = String::new();
+    let mut exported = 0;
+    let mut skipped = 0;
+
+    for record in read()? {
+        let (event, at, from, to) = record.parts();
+        if !roots.involves(from, to) {
+            continue;
+        }
+        let (Some(from), Some(to)) = (roots.place(from), roots.place(to)) else {
+            skipped += 1;
+            continue;
+        };
+        out.push_str(&serde_json::to_string(&Exported {
+            event,
+            at,
+            from,
+            to,
+        })?);
+        out.push('\n');
+        exported += 1;
+    }
+
+    if skipped > 0 {
+        log::warn!(
+            "Skipped {skipped} entries with paths outside {}",
+            path.display()
+        );
+    }
+    Ok((out, exported))
+}
+
+/// Add the entries of an exported journal to this machine's journal, resolved against `path`
+/// and `dest` ( the twitch-screenshots folder in `path` by default )
+pub fn import(file: &Path, path: &Path, dest: Option<&Path>) -> io::Result<()> {
+    let imported = import_from(BufReader::new(fs::File::open(file)?), path, dest)?;
+    println!(
+        "Imported {imported} journal entries for {}",
+        absolute(path).display()
+    );
+    Ok(())
+}
+
+/// [import] from anything with the lines of an exported journal, returns how many entries there
+/// were
+pub fn import_from(exported: impl BufRead, path: &Path, dest: Option<&Path>) -> io::Result<usize> {
+    let roots = Roots {
+        source: absolute(path),
+        dest: absolute(&dest.map_or_else(|| path.join(SAVE_TO), Path::to_path_buf)),
+    };
+
+    let mut records = Vec::new();
+    for line in exported.lines() {
+        let exported: Exported = serde_json::from_str(&line?)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        records.push(Record::new(
+            exported.event,
+            exported.at,
+            roots.resolve(&exported.from),
+            roots.resolve(&exported.to),
+        ));
+    }
+
+    append(&records);
+    Ok(records.len())
+}
+
+/// The two folders journal paths are made relative to
+struct Roots {
+    source: PathBuf,
+    dest: PathBuf,
+}
+
+impl Roots {
+    /// `path` may be the organized folder or the save dir itself, like for the other commands
+    fn of(path: &Path) -> Roots {
+        Roots {
+            source: absolute(path),
+            dest: absolute(&collection::save_dir(path)),
+        }
+    }
+
+    fn involves(&self, from: &Path, to: &Path) -> bool {
+        [from, to]
+            .iter()
+            .any(|path| path.starts_with(&self.source) || path.starts_with(&self.dest))
+    }
+
+    /// the save dir is usually inside the source, so it's tried first
+    fn place(&self, path: &Path) -> Option<Place> {
+        if let Ok(relative) = path.strip_prefix(&self.dest) {
+            Some(Place::Dest(portable(relative)))
+        } else {
+            path.strip_prefix(&self.source)
+                .ok()
+                .map(|relative| Place::Source(portable(relative)))
+        }
+    }
+
+    fn resolve(&self, place: &Place) -> PathBuf {
+        let (root, relative) = match place {
+            Place::Source(relative) => (&self.source, relative),
+            Place::Dest(relative) => (&self.dest, relative),
+        };
+        relative
+            .split('/')
+            .fold(root.clone(), |path, part| path.join(part))
+    }
+}
+
+/// moves of `roots` whose latest journal event is `event`, most recent first
+fn latest(roots: &Roots, event: Event) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let mut state: HashMap<(PathBuf, PathBuf), (usize, Event)> = HashMap::new();
+    for (i, record) in read()?.iter().enumerate() {
+        let (record_event, _, from, to) = record.parts();
+        if roots.involves(from, to) {
+            state.insert((from.to_path_buf(), to.to_path_buf()), (i, record_event));
+        }
+    }
+
+    let mut moves = state
+        .into_iter()
+        .filter(|(_, (_, latest))| *latest == event)
+        .map(|(paths, (i, _))| (i, paths))
+        .collect::<Vec<_>>();
+    moves.sort_by_key(|(i, _)| std::cmp::Reverse(*i));
+    Ok(moves.into_iter().map(|(_, paths)| paths).collect())
+}
+
+/// move `from` to `to` without overwriting anything
+fn reverse(from: &Path, to: &Path) -> io::Result<()> {
+    if !from.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} is gone", from.display()),
+        ));
+    }
+    // sharding can leave a symlink to the file where it was before
+    let links_back = to
+        .symlink_metadata()
+        .is_ok_and(|metadata| metadata.file_type().is_symlink())
+        && to.canonicalize().ok() == from.canonicalize().ok();
+    if links_back {
+        fs::remove_file(to)?;
+    } else if to.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} is taken", to.display()),
+        ));
+    }
+    fs::create_dir_all(to.parent().expect("journal paths have a parent directory"))?;
+    fs::rename(from, to)?;
+    sidecar::relocate(from, to);
+    Ok(())
+}
+
+fn read() -> io::Result<Vec<Record>> {
+    let file = match fs::File::open(journal_path()) {
+        Ok(file) => file,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+    let mut records = Vec::new();
+    for line in BufReader::new(file).lines() {
+        match serde_json::from_str(&line?) {
+            Ok(record) => records.push(record),
+            Err(error) => log::debug!("Skipping unreadable journal line: {error}"),
+        }
+    }
+    Ok(records)
+}
+
+/// the journal is append only, one json record per line
+fn append(records: &[Record]) {
+    if records.is_empty() {
+        return;
+    }
+    let result = (|| {
+        let path = journal_path();
+        fs::create_dir_all(path.parent().expect("journal has a parent directory"))?;
+        let mut lines = String::new();
+        for record in records {
+            lines.push_str(&serde_json::to_string(record)?);
+            lines.push('\n');
+        }
+        // a single write so concurrent moves don't interleave lines
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(lines.as_bytes())
+    })();
+    if let Err(error) = result {
+        log::warn!("Failed to write move journal: {error}");
+    }
+}
+
+/// canonical form of a path that may not exist ( anymore ), only its folder is resolved
+fn absolute(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    match (path.parent().map(Path::canonicalize), path.file_name()) {
+        (Some(Ok(parent)), Some(name)) => parent.join(name),
+        _ => std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf()),
+    }
+}
+
+fn portable(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+fn journal_path() -> PathBuf {
+    crate::state_dir().join("moves.jsonl")
+}
@@ -0,0 +1,43 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::Path;
+
+/// in the save dir of a destination that more than one machine organizes into, both as the
+/// marker and the file that's locked
+pub const FILE_NAME: &str = ".shared.lock";
+
+/// Exclusive advisory lock on a shared save dir, released when dropped
+#[derive(Debug)]
+pub struct Lock {
+    // closing it lets go of the lock
+    _file: File,
+}
+
+/// Mark `save_dir` as shared with organizers on other machines, from now on every instance
+/// takes turns on it
+pub fn mark_shared(save_dir: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(save_dir)?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(save_dir.join(FILE_NAME))
+        .map(drop)
+}
+
+pub fn is_shared(save_dir: &Path) -> bool {
+    save_dir.join(FILE_NAME).is_file()
+}
+
+/// Wait for the lock on `save_dir` if it's shared, none if it isn't
+pub fn acquire(save_dir: &Path) -> io::Result<Option<Lock>> {
+    let file = match OpenOptions::new()
+        .write(true)
+        .open(save_dir.join(FILE_NAME))
+    {
+        Ok(file) => file,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    file.lock()?;
+    Ok(Some(Lock { _file: file }))
+}
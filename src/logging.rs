@@ -0,0 +1,136 @@
+use chrono::{Local, NaiveDate};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use crate::progress;
+
+/// a log file is started over once it's this big ..
+const MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// .. or at midnight, keeping this many old ones as <file>.1 ( the newest ) to <file>.5
+const KEEP: usize = 5;
+
+/// How log lines look
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default)]
+pub enum Format {
+    /// The usual `[time LEVEL target] message`
+    #[default]
+    Pretty,
+    /// One json object per line with ts, level, target and message, for journald, ELK and co.
+    Json,
+}
+
+/// Set up logging in `format` to stderr or `file`. With a file panics are logged too, so a crash
+/// doesn't only go to a stderr nobody reads.
+pub fn init(format: Format, file: Option<&Path>) -> io::Result<()> {
+    let mut builder =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"));
+    if let Format::Json = format {
+        builder.format(|buf, record| {
+            let line = serde_json::json!({
+                "ts": Local::now().to_rfc3339(),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            writeln!(buf, "{line}")
+        });
+    }
+    let Some(file) = file else {
+        // colors as if it went to stderr directly, a pipe never gets them otherwise
+        if std::env::var_os("RUST_LOG_STYLE").is_none() {
+            builder.write_style(if io::stderr().is_terminal() {
+                env_logger::WriteStyle::Always
+            } else {
+                env_logger::WriteStyle::Never
+            });
+        }
+        builder
+            .target(env_logger::Target::Pipe(Box::new(progress::Stderr)))
+            .init();
+        return Ok(());
+    };
+    builder
+        .target(env_logger::Target::Pipe(Box::new(RotatingFile::open(
+            file,
+        )?)))
+        .write_style(env_logger::WriteStyle::Never)
+        .init();
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        log::error!("{info}");
+        default_hook(info);
+    }));
+    Ok(())
+}
+
+/// A log file that's moved aside when it gets too big or a new day starts
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    day: NaiveDate,
+}
+
+impl RotatingFile {
+    fn open(path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let metadata = file.metadata()?;
+        // picking up where an earlier run left off, by the day it last wrote
+        let day = metadata
+            .modified()
+            .map(|modified| chrono::DateTime::<Local>::from(modified).date_naive())
+            .unwrap_or_else(|_| Local::now().date_naive());
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            size: metadata.len(),
+            day,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for n in (1..KEEP).rev() {
+            let _ = fs::rename(self.rotated(n), self.rotated(n + 1));
+        }
+        fs::rename(&self.path, self.rotated(1))?;
+        *self = Self::open(&self.path)?;
+        Ok(())
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(format!(".{n}"));
+        rotated.into()
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let today = Local::now().date_naive();
+        let full = self.size + buf.len() as u64 > MAX_SIZE;
+        if self.size > 0 && (full || today != self.day) {
+            if let Err(error) = self.rotate() {
+                // better one file too long than no logs at all
+                eprintln!("Failed to rotate {}: {error}", self.path.display());
+                self.size = 0;
+            }
+        }
+        self.day = today;
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
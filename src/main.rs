@@ -1,176 +1,2052 @@
-use clap::Parser;
-use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+mod activity;
+mod animated;
+mod archive;
+mod collection;
+mod confidence;
+mod config;
+mod control;
+mod convert;
+mod daemon;
+mod delayed;
+mod docs;
+mod dupes;
+mod error;
+mod export;
+mod glob;
+mod http;
+mod index;
+mod interactive;
+mod journal;
+mod lock;
+mod logging;
+mod manifest;
+mod metadata;
+mod metrics;
+mod migrate;
+mod notifications;
+#[cfg(feature = "ocr")]
+mod ocr;
+mod output;
+mod plan;
+mod progress;
+mod quarantine;
+mod rclone;
+mod readiness;
+mod reindex;
+mod removal;
+mod replay;
+mod retention;
+mod review;
+mod s3;
+mod search;
+mod serve;
+mod service;
+mod session;
+mod sharding;
+mod sidecar;
+mod snapshot;
+mod stats;
+mod status;
+mod storage;
+mod sync_tools;
+mod thumbnails;
+mod tui;
+mod twitch;
+mod upgrade;
+mod verify;
+mod videos;
+mod watch;
+mod webhook;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use clap::{Parser, Subcommand};
+use config::Config;
+use error::Error;
+use index::Index;
+use output::{Action, Outcome};
+use plan::{Plan, Transfer};
+use progress::Progress;
+use rclone::Rclone;
+use std::collections::{BTreeSet, HashSet};
 use std::fs;
-use std::fs::read_dir;
 use std::io;
-use std::path::Path;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
+use walkdir::{DirEntry, WalkDir};
 
+/// Organizes twitch screenshots ( like the ones FFZ saves ) into a folder per channel
 #[derive(Parser, Debug)]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     /// Path to process for twitch screenshots in
-    path: String,
+    #[clap(required = true)]
+    path: Option<PathBuf>,
 
     /// Watch mode. If enabled, program will keep running and watch for new screenshots to move
     #[clap(short, long)]
     watch: bool,
+
+    /// Detach from the terminal and keep watching in the background, logs go to a file
+    #[clap(long, requires = "watch")]
+    daemonize: bool,
+
+    /// Write every raw watcher event to this file, `replay` plays it back to reproduce bugs
+    #[clap(long, requires = "watch", value_name = "FILE")]
+    record_events: Option<PathBuf>,
+
+    /// Config file, defaults to config.toml in the platform config dir
+    /// ( ~/.config/twitch-screenshot-organizer/ on linux ) when it exists
+    #[clap(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// How log lines look, json has one object per line for journald, ELK and the like
+    #[clap(long, value_enum, default_value_t, global = true)]
+    log_format: logging::Format,
+
+    /// Write the logs to this file instead of stderr, it's moved aside to <file>.1 when it gets
+    /// big or a new day starts and the last 5 are kept
+    #[clap(long, global = true, value_name = "FILE")]
+    log_file: Option<PathBuf>,
+
+    /// Move screenshots to the trash instead of deleting them for good, whenever they'd be
+    /// deleted ( retention, archive --remove, the TUI ) or overwritten by one with the same name
+    #[clap(long, global = true)]
+    use_trash: bool,
+
+    /// How sure it has to be that a file is a screenshot from 0 to 1, going by its extension,
+    /// the structure of its name, whether the date and time in it parse and whether there's a
+    /// folder of the channel. Lower takes more oddly named files, RUST_LOG=debug shows how each
+    /// file scored.
+    #[clap(long, global = true, value_parser = confidence::parse_min, value_name = "SCORE",
+        default_value_t = confidence::DEFAULT_MIN)]
+    min_confidence: f32,
+
+    #[clap(flatten)]
+    options: Options,
+}
+
+/// Knobs that affect how each screenshot is organized
+#[derive(clap::Args, Debug, Clone)]
+struct Options {
+    /// Keep a screenshots_<session start>.txt per stream session in each channel folder, listing
+    /// every screenshot with its time offset into the session
+    #[clap(long)]
+    session_files: bool,
+
+    /// Keep a manifest.json in each channel folder listing every screenshot with its capture
+    /// time, sha256, size and original name, for static site generators and backup scripts
+    #[clap(long)]
+    manifest: bool,
+
+    /// Show a desktop notification when screenshots are organized or a move fails
+    #[clap(long)]
+    notify: bool,
+
+    /// Keep a SQLite index ( twitch-screenshots/.index.sqlite ) of everything organized, files
+    /// that were already organized before are left alone
+    #[clap(long = "index")]
+    use_index: bool,
+
+    /// Folder the channel folders go into, defaults to twitch-screenshots next to the screenshots.
+    /// rclone:<remote>:<path> puts them on a remote set up in rclone
+    #[clap(long)]
+    dest: Option<PathBuf>,
+
+    /// Transcode screenshots to this format as they're organized, png takes a lot of space
+    #[clap(long, value_enum)]
+    convert: Option<convert::Format>,
+
+    /// Quality for --convert from 1 to 100
+    #[clap(long, default_value_t = 80, value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: u8,
+
+    /// Keep the png next to the converted file in twitch-screenshots/.originals/
+    #[clap(long, requires = "convert")]
+    keep_original: bool,
+
+    /// Write the channel, capture time and original name into the organized screenshots ( png
+    /// text chunks, EXIF for jpeg ) so they survive renames and copies
+    #[clap(long)]
+    tag_metadata: bool,
+
+    /// Rename screenshots to 2025-01-18T13-06-05_channel.png as they're organized, so they sort
+    /// by capture time in file managers. The original name is kept in the index.
+    #[clap(long)]
+    rename_to_iso: bool,
+
+    /// Other machines organize into the same destination ( e.g. a NAS ), take turns with them on
+    /// the index and on putting screenshots there. The destination stays marked as shared.
+    #[clap(long)]
+    shared_dest: bool,
+
+    /// Also organize screenshots in subfolders, like the per-day folders some capture tools
+    /// make. They all go into the one twitch-screenshots folder and the subfolders they leave
+    /// empty are removed once nothing was written to them for a day.
+    #[clap(long)]
+    recursive: bool,
+
+    /// How many levels of subfolders --recursive looks into
+    #[clap(long, default_value_t = 2, requires = "recursive")]
+    max_depth: usize,
+
+    /// Remove channel folders that were left empty, e.g. by retention limits, and with
+    /// --recursive every empty subfolder instead of only the ones this run emptied
+    #[clap(long)]
+    clean_empty: bool,
+
+    /// With --watch, look for new screenshots by scanning the folder instead of waiting for
+    /// events from the system, which never come for network shares ( SMB, NFS .. )
+    #[clap(long)]
+    poll: bool,
+
+    /// Seconds between --poll scans
+    #[clap(long, default_value_t = 5, requires = "poll", value_name = "SECS")]
+    poll_interval: u64,
+
+    /// With --watch, also go through the whole folder this often ( like 30m, 1h or 90s ) for
+    /// screenshots the watcher missed, e.g. while it was broken or its queue overflowed
+    #[clap(long, value_parser = watch::parse_interval, value_name = "DURATION")]
+    rescan_interval: Option<Duration>,
+
+    /// With --watch, serve Prometheus metrics ( moves, failures, events, queue depth and how
+    /// long moves take ) on this address, like 127.0.0.1:9184
+    #[clap(long, value_name = "ADDR")]
+    metrics_addr: Option<SocketAddr>,
+
+    /// With --watch, how many screenshots are moved at the same time at most, so a burst of them
+    /// ( a gallery extension saving hundreds at once ) doesn't hammer the disk
+    #[clap(long, default_value_t = delayed::DEFAULT_WORKERS, value_name = "N",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    max_concurrent_moves: usize,
+
+    /// With --watch, gather what turns up until nothing new did for this long ( like 2s ) and
+    /// organize it as one batch in the order the screenshots were taken, with one summary line
+    /// instead of a move per event
+    #[clap(long, value_parser = watch::parse_interval, value_name = "DURATION")]
+    batch_window: Option<Duration>,
+
+    /// Also report every file that was looked at on stdout, json has one object per line with
+    /// source, destination, channel, action and error for scripts to read
+    #[clap(long, value_enum, default_value_t)]
+    output: output::Format,
+
+    /// How many screenshots are moved at the same time when going through a folder, defaults to
+    /// the number of cores up to 4
+    #[clap(long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Read the channel off png captures that aren't named like a screenshot ( PrintScreen,
+    /// snipping tools .. ) with tesseract, so captures of a twitch page get organized too
+    #[cfg(feature = "ocr")]
+    #[clap(long)]
+    ocr: bool,
+
+    /// Only organize screenshots of these channels ( comma separated, * and ? wildcards ) and leave
+    /// everything else where it is, e.g. in a Downloads folder other screenshots go into as well.
+    /// Takes the place of `channels` in the config.
+    #[clap(long, alias = "channel", value_delimiter = ',', value_name = "LIST")]
+    channels: Vec<String>,
+
+    /// Only organize screenshots captured on or after this day ( YYYY-MM-DD ), going by their
+    /// name. Files without a capture time in their name are left where they are. Not with --watch.
+    #[clap(long, value_name = "DAY")]
+    since: Option<NaiveDate>,
+
+    /// Only organize screenshots captured on or before this day ( YYYY-MM-DD ), like --since
+    #[clap(long, value_name = "DAY")]
+    until: Option<NaiveDate>,
+
+    /// Also organize clips and vods downloaded with a tool that puts the channel in the name (
+    /// TwitchDownloader, yt-dlp with the uploader id ) into a clips folder in the channel folder
+    #[clap(long)]
+    include_videos: bool,
+
+    /// Ask what to do with files that are only named a bit like a screenshot or are of a channel
+    /// twitch doesn't know, instead of leaving them or putting them aside. What's decided for
+    /// files named like them goes into [decisions] in the config. Not with --watch.
+    #[clap(long)]
+    interactive: bool,
+
+    #[clap(skip)]
+    config: Config,
+
+    /// where `config` came from, `ctl reload-config` loads it again
+    #[clap(skip)]
+    config_path: Option<PathBuf>,
+
+    #[clap(skip)]
+    index: Option<Arc<Index>>,
+
+    /// set up when the config has twitch credentials
+    #[clap(skip)]
+    twitch: Option<Arc<twitch::Helix>>,
+
+    /// set up by --record-events, watcher events go in there as they come in
+    #[clap(skip)]
+    recorder: Option<Arc<replay::Recorder>>,
+
+    /// screenshots can't be removed from the source, they're linked or copied instead
+    #[clap(skip)]
+    read_only_source: bool,
+
+    /// set up when the destination isn't on the local filesystem
+    #[clap(skip)]
+    storage: Option<Arc<dyn storage::StorageBackend>>,
+
+    /// channel folders made during a batch run, each one is only made once
+    #[clap(skip)]
+    made_dirs: Option<Arc<Mutex<HashSet<PathBuf>>>>,
+}
+
+impl Options {
+    /// where screenshots from `source_dir` are organized into
+    fn save_dir(&self, source_dir: &Path) -> PathBuf {
+        self.dest
+            .clone()
+            .unwrap_or_else(|| source_dir.join(plan::SAVE_TO))
+    }
+
+    /// with --recursive screenshots in subfolders of `root` go where the ones in `root` go, not
+    /// into a twitch-screenshots folder in each subfolder
+    fn collapse_into(&mut self, root: &Path) {
+        if self.recursive && self.dest.is_none() {
+            self.dest = Some(self.save_dir(root));
+        }
+    }
+
+    /// whether screenshots of `channel` are organized, going by --channels or else the config
+    fn allows(&self, channel: &str) -> bool {
+        if self.channels.is_empty() {
+            self.config.channels.allows(channel)
+        } else {
+            config::Channels(self.channels.clone()).allows(channel)
+        }
+    }
+
+    /// whether the file named `file_name` was captured between --since and --until
+    fn in_dates(&self, file_name: &str) -> bool {
+        if self.since.is_none() && self.until.is_none() {
+            return true;
+        }
+        capture_time(file_name).is_some_and(|time| {
+            self.since.is_none_or(|since| time.date() >= since)
+                && self.until.is_none_or(|until| time.date() <= until)
+        })
+    }
+
+    fn jobs(&self) -> usize {
+        self.jobs
+            .unwrap_or_else(|| {
+                thread::available_parallelism().map_or(1, |cores| cores.get().min(4))
+            })
+            .max(1)
+    }
+
+    /// make `dir` in the destination, only once per batch run
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()> {
+        let Some(made) = &self.made_dirs else {
+            return self.storage().create_dir_all(dir);
+        };
+        if made.lock().unwrap().contains(dir) {
+            return Ok(());
+        }
+        self.storage().create_dir_all(dir)?;
+        made.lock().unwrap().insert(dir.to_path_buf());
+        Ok(())
+    }
+
+    /// where screenshots are put, the local filesystem unless something else was set up
+    fn storage(&self) -> &dyn storage::StorageBackend {
+        self.storage.as_deref().unwrap_or(&storage::LocalFs)
+    }
+
+    /// put screenshots on the rclone remote if --dest is one, refusing what only works with the
+    /// destination on this machine
+    fn connect_storage(&mut self) -> io::Result<()> {
+        let Some(rclone) = self
+            .dest
+            .as_deref()
+            .map(Rclone::for_dest)
+            .transpose()?
+            .flatten()
+        else {
+            return Ok(());
+        };
+        let local_only = [
+            ("--index", self.use_index),
+            ("--convert", self.convert.is_some()),
+            ("--tag-metadata", self.tag_metadata),
+            ("--session-files", self.session_files),
+            ("--manifest", self.manifest),
+            ("--shared-dest", self.shared_dest),
+            ("[[fanout]]", !self.config.fanout.is_empty()),
+            ("[sharding]", self.config.sharding.is_some()),
+            ("[thumbnails]", self.config.thumbnails.is_some()),
+            ("[retention]", self.config.retention.is_some()),
+            ("[s3]", self.config.s3.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(what, on)| on.then_some(what))
+        .collect::<Vec<_>>();
+        if !local_only.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} only work with the destination on this machine, not an rclone remote",
+                    local_only.join(", ")
+                ),
+            ));
+        }
+        log::info!(
+            "Putting screenshots on the rclone remote {}",
+            rclone.remote()
+        );
+        self.storage = Some(Arc::new(rclone));
+        Ok(())
+    }
+
+    /// open the index of the save dir for `source_dir` if --index was passed
+    fn open_index(&mut self, source_dir: &Path) -> io::Result<()> {
+        if self.use_index {
+            let index = Index::open(&self.save_dir(source_dir))?;
+            if !self.config.labels.is_empty() {
+                index.set_labels(&self.config.labels)?;
+            }
+            self.index = Some(Arc::new(index));
+        }
+        Ok(())
+    }
+
+    /// load the config file again, for the watcher to pick up changes without a restart
+    fn reload_config(&mut self) -> io::Result<()> {
+        let Some(path) = self.config_path.clone() else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "there's no config file, pass --config",
+            ));
+        };
+        let config = Config::load(&path)?;
+        if let Some(index) = &self.index {
+            if !config.labels.is_empty() {
+                index.set_labels(&config.labels)?;
+            }
+        }
+        let twitch_changed = config.twitch != self.config.twitch;
+        self.config = config;
+        if twitch_changed {
+            self.connect_twitch();
+        }
+        log::info!("Reloaded the config from {}", path.display());
+        Ok(())
+    }
+
+    /// get ready to ask twitch about channels if the config has credentials, after the index is
+    /// open so lookups can be kept in there
+    fn connect_twitch(&mut self) {
+        self.twitch = self.config.twitch.clone().map(|settings| {
+            log::info!("Checking channel names with Twitch");
+            Arc::new(twitch::Helix::new(settings, self.index.clone()))
+        });
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Manage running the watcher as a background service
+    #[clap(subcommand)]
+    Service(ServiceCommand),
+
+    /// Undo or redo moves, or carry the journal of moves over to another machine
+    #[clap(subcommand)]
+    Journal(JournalCommand),
+
+    /// Save the config, index, journals and layout markers to one file, or put them back, e.g.
+    /// before trying something new or when moving to another computer
+    #[clap(subcommand)]
+    Snapshot(SnapshotCommand),
+
+    /// Ask the running watcher what it's doing, pause or resume it, rescan or reload the config
+    Ctl {
+        #[clap(value_enum)]
+        request: control::Request,
+    },
+
+    /// Whether a watcher is running on the path, when it last saw an event, how many screenshots
+    /// wait to be moved and the last error. Fails when none is running, for cron to alert on.
+    Status {
+        /// The folder the watcher should be running on
+        path: PathBuf,
+    },
+
+    /// Show past organizer sessions, what they organized and roughly how much time that saved
+    Activity {
+        /// How many of the most recent sessions to show
+        #[clap(long, default_value_t = 20)]
+        limit: usize,
+    },
+
+    /// Find organized screenshots, uses the index when there is one
+    Search {
+        /// Folder that was organized ( or its twitch-screenshots folder )
+        path: PathBuf,
+
+        #[clap(flatten)]
+        query: search::Query,
+
+        /// Open the matches in the default image viewer
+        #[clap(long)]
+        open: bool,
+    },
+
+    /// Per channel counts, disk usage, date range and screenshots per month
+    Stats {
+        /// Folder that was organized ( or its twitch-screenshots folder )
+        path: PathBuf,
+
+        #[clap(long, value_enum, default_value_t)]
+        format: stats::Format,
+    },
+
+    /// Write every organized screenshot with its channel, capture time, path, size and sha256 to
+    /// a csv or json file, for spreadsheets and other tools. Uses the index when there is one.
+    Export {
+        /// Folder that was organized ( or its twitch-screenshots folder )
+        path: PathBuf,
+
+        #[clap(long, value_enum, default_value_t)]
+        format: export::Format,
+
+        /// File to write to, stdout if it's not given
+        #[clap(long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Move screenshots kept in another organizer's layout into this one
+    Migrate {
+        /// Folder the screenshots are in now
+        path: PathBuf,
+
+        /// `date-first` for twitch named screenshots in date folders, `dump` for renamed
+        /// screenshots in one folder, or a pattern like `{year}/{month}/{channel} {day} {hour}-{minute}-{second}.png`
+        #[clap(long)]
+        from: String,
+
+        /// Only print what would be moved where
+        #[clap(long)]
+        dry_run: bool,
+
+        #[clap(flatten)]
+        options: Options,
+    },
+
+    /// Go through groups of near duplicate screenshots side by side and pick the keepers, the
+    /// rest go to the trash
+    ReviewDupes {
+        /// Folder that was organized ( or its twitch-screenshots folder )
+        path: PathBuf,
+
+        #[clap(flatten)]
+        query: search::Query,
+
+        /// How many of the 64 bits of the perceptual hashes may differ
+        #[clap(long, default_value_t = 6)]
+        threshold: u32,
+
+        /// Delete for good instead of moving to the trash
+        #[clap(long)]
+        permanent: bool,
+    },
+
+    /// Pack screenshots into one zip per channel and month, to cold store or share them
+    Archive {
+        /// Folder that was organized ( or its twitch-screenshots folder )
+        path: PathBuf,
+
+        /// Only channels matching this pattern, `*` and `?` wildcards
+        #[clap(long)]
+        channel: Option<String>,
+
+        /// Only screenshots captured before this day, as YYYY-MM-DD
+        #[clap(long)]
+        before: Option<NaiveDate>,
+
+        /// Folder the zips go into, defaults to twitch-screenshots/.archive/
+        #[clap(long)]
+        out: Option<PathBuf>,
+
+        /// Delete the screenshots once they're in the zip
+        #[clap(long)]
+        remove: bool,
+    },
+
+    /// Delete or archive the oldest screenshots of every channel that's over the limits in
+    /// [retention]
+    Prune {
+        /// Folder that was organized ( or its twitch-screenshots folder )
+        path: PathBuf,
+
+        /// Only channels matching this pattern, `*` and `?` wildcards
+        #[clap(long)]
+        channel: Option<String>,
+
+        /// Only print what would be pruned
+        #[clap(long)]
+        dry_run: bool,
+
+        /// Remove the channel folders that are empty afterwards
+        #[clap(long, conflicts_with = "dry_run")]
+        clean_empty: bool,
+    },
+
+    /// Play back events written with --record-events against a temp folder, to reproduce what
+    /// the watcher did with them
+    Replay {
+        /// Recording to play back
+        file: PathBuf,
+
+        /// Don't wait between events like the recording did
+        #[clap(long)]
+        fast: bool,
+
+        #[clap(flatten)]
+        options: Options,
+    },
+
+    /// Serve a gallery of organized screenshots by channel and day, e.g. to look at them from a
+    /// phone on the same network
+    Serve {
+        /// Folder that was organized ( or its twitch-screenshots folder )
+        path: PathBuf,
+
+        #[clap(long, default_value_t = 8080)]
+        port: u16,
+    },
+
+    /// Generate missing thumbnails in twitch-screenshots/.thumbs/ and drop stale ones, sized as
+    /// configured in [thumbnails]
+    Thumbnails {
+        /// Folder that was organized ( or its twitch-screenshots folder )
+        path: PathBuf,
+    },
+
+    /// Browse organized screenshots by channel and day, open, delete or move misfiled ones
+    Tui {
+        /// Folder that was organized ( or its twitch-screenshots folder )
+        path: PathBuf,
+    },
+
+    /// Bring the index of an organized folder up to date with what's in it, indexing and hashing
+    /// new or changed screenshots and forgetting ones that are gone. Screenshots in the wrong
+    /// channel folder are listed.
+    Reindex {
+        /// Folder that was organized ( or its twitch-screenshots folder )
+        dest: PathBuf,
+
+        /// Move screenshots in the wrong channel folder to their own
+        #[clap(long)]
+        fix: bool,
+    },
+
+    /// Check the index of an organized folder against the files: indexed screenshots that are
+    /// gone, ones that aren't indexed, ones that changed since and empty channel folders. Fails
+    /// when something doesn't add up.
+    Verify {
+        /// Folder that was organized ( or its twitch-screenshots folder )
+        path: PathBuf,
+
+        /// Repair what can be: forget what's gone, index what isn't and remove empty folders
+        #[clap(long)]
+        fix: bool,
+    },
+
+    /// Put twitch-screenshots folders organized before --index into an index, once
+    UpgradeLayout {
+        /// Folder that was organized, its twitch-screenshots folder or a folder further up with
+        /// several of them
+        path: PathBuf,
+
+        /// Only print what would be registered
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    /// Print a completion script for the shell, e.g. `completions bash >
+    /// /etc/bash_completion.d/twitch-screenshot-organizer`
+    Completions {
+        #[clap(value_enum)]
+        shell: clap_complete::Shell,
+    },
+
+    /// Print the man page
+    Man {
+        /// Write a page for every subcommand into this folder instead
+        #[clap(long, value_name = "DIR")]
+        out: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum JournalCommand {
+    /// Move screenshots organized in path back to where they came from, most recent first
+    Undo {
+        /// Folder that was organized ( or its twitch-screenshots folder )
+        path: PathBuf,
+
+        /// Only the last N moves instead of all of them
+        #[clap(long)]
+        last: Option<usize>,
+
+        /// Remove the channel folders that are empty afterwards
+        #[clap(long)]
+        clean_empty: bool,
+    },
+    /// Apply moves that were undone again
+    Redo {
+        /// Folder that was organized ( or its twitch-screenshots folder )
+        path: PathBuf,
+
+        /// Only the last N undone moves instead of all of them
+        #[clap(long)]
+        last: Option<usize>,
+    },
+    /// Write the moves of path to a file with paths relative to it
+    Export {
+        /// Folder that was organized ( or its twitch-screenshots folder )
+        path: PathBuf,
+
+        /// File to write the journal to
+        file: PathBuf,
+    },
+    /// Take over an exported journal, e.g. after copying the whole folder to a NAS
+    Import {
+        /// File written by `journal export`
+        file: PathBuf,
+
+        /// Where the organized folder lives on this machine
+        path: PathBuf,
+
+        /// Where its channel folders live, if not in twitch-screenshots inside path
+        #[clap(long)]
+        dest: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SnapshotCommand {
+    /// Write a snapshot of the organizer's state for path to a zip
+    Create {
+        /// Folder that was organized ( or its twitch-screenshots folder )
+        path: PathBuf,
+
+        /// Zip to write
+        file: PathBuf,
+    },
+    /// Put a snapshot back, replaced files are kept as <name>.bak
+    Restore {
+        /// Zip written by `snapshot create`
+        file: PathBuf,
+
+        /// Where the organized folder lives on this machine
+        path: PathBuf,
+
+        /// Where its channel folders live, if not in twitch-screenshots inside path
+        #[clap(long)]
+        dest: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ServiceCommand {
+    /// Register a service that watches the given path on login ( systemd user unit, launchd
+    /// agent on macOS, windows service )
+    Install {
+        /// Path to watch for twitch screenshots
+        path: PathBuf,
+
+        /// Extra options passed to the organizer, e.g. `-- --session-files`
+        #[clap(last = true)]
+        args: Vec<String>,
+    },
+    /// Stop and remove the service
+    Uninstall,
+    /// Start the installed service now
+    Start,
+    /// Entry point used by the windows service control manager
+    #[cfg(windows)]
+    #[clap(hide = true)]
+    Run {
+        path: PathBuf,
+
+        #[clap(flatten)]
+        options: Options,
+    },
 }
 
 fn main() {
     let args = Args::parse();
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
-    log::info!("Watching {} for new screenshots to process...", args.path);
+    if let Err(error) = logging::init(args.log_format, args.log_file.as_deref()) {
+        eprintln!("Failed to open the log file: {error}");
+        std::process::exit(1);
+    }
+    if let Err(error) = start(args) {
+        log::error!("{error}");
+        std::process::exit(error.exit_code());
+    }
+}
+
+fn start(mut args: Args) -> Result<(), Error> {
     log::debug!("Args were: {:?}", args);
+    if args.use_trash {
+        removal::use_trash();
+    }
+    confidence::set_min(args.min_confidence);
 
-    let handle = move_all(&args.path);
+    let config_path = config::resolve_path(args.config.as_deref());
+    // the config is what gets restored, it needn't be there or load yet
+    let restoring = matches!(
+        args.command,
+        Some(Command::Snapshot(SnapshotCommand::Restore { .. }))
+    );
+    let config = match &config_path {
+        Some(path) if !restoring => Config::load(path).map_err(|error| {
+            Error::Config(format!("Failed to load config {}: {error}", path.display()))
+        })?,
+        _ => Config::default(),
+    };
+    log::debug!("Config was: {:?}", config);
 
-    if args.watch {
-        if let Err(error) = run_as_daemon(args.path) {
+    if let Some(command) = args.command {
+        let (what, result) = match command {
+            Command::Ctl { request } => (
+                "Control request",
+                control::send(request).map(|answer| print!("{answer}")),
+            ),
+            Command::Status { path } => ("Health check", status::run(&path)),
+            Command::Activity { limit } => ("Activity report", activity::report(limit)),
+            Command::Search { path, query, open } => ("Search", search::run(&path, &query, open)),
+            Command::Stats { path, format } => ("Stats", stats::run(&path, format, &config.labels)),
+            Command::Export { path, format, out } => {
+                ("Export", export::run(&path, format, out.as_deref()))
+            }
+            Command::Migrate {
+                path,
+                from,
+                dry_run,
+                mut options,
+            } => {
+                options.config = config;
+                ("Migration", migrate::run(&path, &from, dry_run, options))
+            }
+            Command::ReviewDupes {
+                path,
+                query,
+                threshold,
+                permanent,
+            } => (
+                "Duplicate review",
+                review::run(&path, &query, threshold, permanent),
+            ),
+            Command::Archive {
+                path,
+                channel,
+                before,
+                out,
+                remove,
+            } => (
+                "Archiving",
+                archive::run(&path, channel.as_deref(), before, out.as_deref(), remove),
+            ),
+            Command::Prune {
+                path,
+                channel,
+                dry_run,
+                clean_empty,
+            } => (
+                "Pruning",
+                match &config.retention {
+                    Some(retention) => {
+                        retention::run(&path, retention, channel.as_deref(), dry_run, clean_empty)
+                    }
+                    None => {
+                        return Err(Error::Config(
+                            "Pruning needs [retention] limits in the config".to_string(),
+                        ))
+                    }
+                },
+            ),
+            Command::Replay {
+                file,
+                fast,
+                mut options,
+            } => {
+                options.config = config;
+                ("Replay", replay::run(&file, fast, options))
+            }
+            Command::Serve { path, port } => {
+                let (thumbnails, labels) = (config.thumbnails, config.labels);
+                (
+                    "Gallery server",
+                    serve::run(&path, port, thumbnails, &labels),
+                )
+            }
+            Command::Thumbnails { path } => (
+                "Thumbnail generation",
+                thumbnails::sync(&path, &config.thumbnails.unwrap_or_default()),
+            ),
+            Command::Tui { path } => ("TUI", tui::run(&path)),
+            Command::Reindex { dest, fix } => ("Reindex", reindex::run(&dest, fix)),
+            Command::Verify { path, fix } => ("Verification", verify::run(&path, fix)),
+            Command::UpgradeLayout { path, dry_run } => {
+                ("Layout upgrade", upgrade::run(&path, dry_run))
+            }
+            Command::Completions { shell } => ("Completions", docs::completions(shell)),
+            Command::Man { out } => ("Man page", docs::man(out.as_deref())),
+            Command::Journal(command) => ("Journal command", run_journal(command)),
+            Command::Snapshot(command) => (
+                "Snapshot command",
+                run_snapshot(command, config_path, args.config),
+            ),
+            Command::Service(command) => {
+                ("Service command", run_service(command, config_path, config))
+            }
+        };
+        return result.map_err(|source| Error::Failed { what, source });
+    }
+
+    let path = args.path.expect("path is required without a subcommand");
+
+    if args.daemonize && !daemon::is_daemonized() {
+        let log_file = args
+            .log_file
+            .clone()
+            .unwrap_or_else(daemon::default_log_file);
+        let pid = daemon::daemonize(&log_file).map_err(|source| Error::Failed {
+            what: "Daemonizing",
+            source,
+        })?;
+        log::info!(
+            "Daemonized with pid {pid}, logging to {}",
+            log_file.display()
+        );
+        return Ok(());
+    }
+
+    if let Some(file) = &args.record_events {
+        let recorder = replay::Recorder::create(file, &path).map_err(|source| Error::Failed {
+            what: "Recording events",
+            source,
+        })?;
+        args.options.recorder = Some(Arc::new(recorder));
+    }
+
+    args.options.config = config;
+    args.options.config_path = config_path;
+    run(path, args.watch, args.options)
+}
+
+fn run_journal(command: JournalCommand) -> io::Result<()> {
+    match command {
+        JournalCommand::Undo {
+            path,
+            last,
+            clean_empty,
+        } => journal::undo(&path, last, clean_empty),
+        JournalCommand::Redo { path, last } => journal::redo(&path, last),
+        JournalCommand::Export { path, file } => journal::export(&path, &file),
+        JournalCommand::Import { file, path, dest } => {
+            journal::import(&file, &path, dest.as_deref())
+        }
+    }
+}
+
+/// `config_path` is the config in use, a restored one goes to `--config` or the default location
+fn run_snapshot(
+    command: SnapshotCommand,
+    config_path: Option<PathBuf>,
+    explicit_config: Option<PathBuf>,
+) -> io::Result<()> {
+    match command {
+        SnapshotCommand::Create { path, file } => {
+            snapshot::create(&path, &file, config_path.as_deref())
+        }
+        SnapshotCommand::Restore { file, path, dest } => {
+            let config = explicit_config
+                .or_else(config::default_path)
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        "no config folder on this system, pass --config",
+                    )
+                })?;
+            snapshot::restore(&file, &path, dest.as_deref(), &config)
+        }
+    }
+}
+
+fn run_service(
+    command: ServiceCommand,
+    config_path: Option<PathBuf>,
+    #[cfg_attr(not(windows), allow(unused_variables))] config: Config,
+) -> io::Result<()> {
+    match command {
+        ServiceCommand::Install { path, args } => {
+            service::install(&path, config_path.as_deref(), &args)
+        }
+        ServiceCommand::Uninstall => service::uninstall(),
+        ServiceCommand::Start => service::start(),
+        #[cfg(windows)]
+        ServiceCommand::Run { path, mut options } => {
+            options.config = config;
+            options.config_path = config_path;
+            service::run(path, options)
+        }
+    }
+}
+
+/// organize everything already in `path`, then keep watching it if asked to. Fails with
+/// [Error::Partial] when some screenshots of a batch run couldn't be organized.
+fn run(path: PathBuf, watch: bool, mut options: Options) -> Result<(), Error> {
+    if options.interactive && (watch || !interactive::can_ask()) {
+        return Err(Error::Config(
+            "--interactive needs a terminal to ask on and doesn't work with --watch".to_string(),
+        ));
+    }
+    if watch && (options.since.is_some() || options.until.is_some()) {
+        return Err(Error::Config(
+            "--since and --until are for organizing a folder once, not with --watch".to_string(),
+        ));
+    }
+    if let Err(error) = fs::read_dir(&path) {
+        return Err(Error::Path(format!(
+            "Can't read {}: {error}",
+            path.display()
+        )));
+    }
+    log::info!(
+        "Watching {} for new screenshots to process...",
+        path.display()
+    );
+
+    #[cfg(feature = "ocr")]
+    if options.ocr {
+        ocr::check().map_err(|error| Error::Config(format!("--ocr needs tesseract: {error}")))?;
+    }
+
+    activity::start(&path, watch);
+    options.collapse_into(&path);
+    confidence::know_channels(&options.save_dir(&path));
+
+    if !plan::is_writable(&path) {
+        if options.save_dir(&path).starts_with(&path) {
+            return Err(Error::Path(format!(
+                "{} is read-only, pass --dest with a writable folder to copy screenshots into",
+                path.display()
+            )));
+        }
+        log::warn!(
+            "{} is read-only, screenshots will be linked or copied instead of moved",
+            path.display()
+        );
+        options.read_only_source = true;
+    }
+
+    if options.shared_dest {
+        lock::mark_shared(&options.save_dir(&path)).map_err(|error| {
+            Error::Path(format!(
+                "Failed to mark the destination as shared: {error:?}"
+            ))
+        })?;
+    }
+
+    options
+        .connect_storage()
+        .map_err(|error| Error::Path(format!("Failed to use the destination: {error}")))?;
+
+    options
+        .open_index(&path)
+        .map_err(|error| Error::Path(format!("Failed to open index: {error:?}")))?;
+    options.connect_twitch();
+
+    delayed::set_workers(options.max_concurrent_moves);
+    let handle = move_all(&path, options.clone(), watch);
+
+    if watch {
+        if let Err(error) = watch::run_as_daemon(path, &options) {
             log::error!("Error: {error:?}");
         }
     }
 
-    handle.join().expect("Failed to join on move all op");
+    if handle.join().is_err() {
+        return Err(Error::Failed {
+            what: "Organizing",
+            source: io::Error::other("it crashed"),
+        });
+    }
+    match activity::counts() {
+        (_, 0) => Ok(()),
+        (_, failed) => Err(Error::Partial(failed)),
+    }
 }
 
-/// for all files in the directory ( non recursive ) move to appropriate folder if it's a screenshot
-/// in a separate thread
-fn move_all<P: AsRef<Path>>(path: P) -> thread::JoinHandle<()> {
+/// What's left out when looking for screenshots in a folder: the save dir, wherever it is, and
+/// everything in the config's `exclude`
+struct Scope {
+    /// the folder as it was given and as it really is
+    root: PathBuf,
+    real_root: PathBuf,
+    save_dir: PathBuf,
+    exclude: config::Exclude,
+}
+
+impl Scope {
+    fn new(root: &Path, options: &Options) -> Scope {
+        let save_dir = options.save_dir(root);
+        Scope {
+            root: root.to_path_buf(),
+            real_root: root.canonicalize().unwrap_or_else(|_| root.to_path_buf()),
+            save_dir: save_dir.canonicalize().unwrap_or(save_dir),
+            exclude: options.config.exclude.clone(),
+        }
+    }
+
+    /// whether `path`, a folder or file that turned up in the root, is left out
+    fn excludes(&self, path: &Path) -> bool {
+        let relative = path
+            .strip_prefix(&self.root)
+            .or_else(|_| path.strip_prefix(&self.real_root));
+        let Ok(relative) = relative else {
+            // reported some other way than root was given
+            return path.starts_with(&self.save_dir);
+        };
+        self.real_root.join(relative).starts_with(&self.save_dir)
+            || relative.components().any(|component| {
+                self.exclude
+                    .matches(&component.as_os_str().to_string_lossy())
+            })
+    }
+}
+
+/// subfolders changed more recently than this may be where a capture tool puts today's screenshots
+const FOLDER_IDLE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// more screenshots than this waiting when the watcher starts are caught up on in batches
+const CATCH_UP_THRESHOLD: usize = 200;
+
+const CATCH_UP_BATCH: usize = 50;
+
+/// between batches, so the disk isn't swamped while a stream is going on
+const CATCH_UP_PAUSE: Duration = Duration::from_secs(1);
+
+/// how often a catch up checks if the watcher is done with its screenshots
+const LIVE_POLL: Duration = Duration::from_millis(100);
+
+/// for all files in the directory ( and its subfolders with --recursive ) move to appropriate
+/// folder if it's a screenshot in a separate thread. With `throttle` ( the watcher runs at the
+/// same time ) a big backlog is worked off in batches, giving way to new screenshots.
+fn move_all<P: AsRef<Path>>(path: P, options: Options, throttle: bool) -> thread::JoinHandle<()> {
     let path = path.as_ref().to_path_buf();
-    return thread::spawn(move || {
-        read_dir(path)
-            .expect("Failed to read directory")
+    let mut options = options;
+    options.made_dirs = Some(Arc::default());
+    thread::spawn(move || {
+        let save_dir = options.save_dir(&path);
+        let scope = Scope::new(&path, &options);
+        let depth = if options.recursive {
+            options.max_depth + 1
+        } else {
+            1
+        };
+        // subfolders a capture tool may still be writing to are left even when emptied
+        let mut busy = BTreeSet::new();
+        let files = WalkDir::new(&path)
+            .min_depth(1)
+            .max_depth(depth)
+            .into_iter()
+            .filter_entry(|entry| !scope.excludes(entry.path()))
             .filter_map(Result::ok)
-            .filter(|entry| entry.file_type().map(|ft| ft.is_file()).unwrap_or(false))
-            .for_each(|entry| {
-                let path = entry.path();
-                if is_screenshot(&path) {
-                    log::info!("Moving screenshot: {}", path.display());
-                    if let Err(error) = move_file(&path, false) {
-                        log::error!("Error: {error:?}");
+            .filter(|entry| {
+                if entry.file_type().is_dir() {
+                    if readiness::age(entry.path()).is_none_or(|age| age < FOLDER_IDLE) {
+                        busy.insert(entry.path().to_path_buf());
                     }
+                    return false;
                 }
-            });
-    });
-}
+                entry.file_type().is_file()
+            })
+            .collect::<Vec<_>>();
+
+        let jobs = options.jobs();
+        let screenshots = Mutex::new(Vec::new());
+        let videos = Mutex::new(Vec::new());
+        // with --interactive, the ones to ask about and why
+        let unsure = Mutex::new(Vec::new());
+        in_parallel(&files, jobs, |entry| {
+            match classify(entry.path(), &options) {
+                Kind::Screenshot(None) if options.interactive => {
+                    match doubt(entry.path(), &options) {
+                        Some(why) => unsure.lock().unwrap().push((entry, why)),
+                        None => screenshots.lock().unwrap().push((entry, None)),
+                    }
+                }
+                Kind::Screenshot(channel) => screenshots.lock().unwrap().push((entry, channel)),
+                Kind::Video(channel) => videos.lock().unwrap().push((entry, channel)),
+                Kind::Skipped => {
+                    log::debug!("Leaving {}, it's left out", entry.path().display())
+                }
+                Kind::Other if options.interactive && quarantine::is_near_miss(entry.path()) => {
+                    let why = "it's named almost like a twitch screenshot".to_string();
+                    unsure.lock().unwrap().push((entry, why));
+                }
+                Kind::Other => quarantine::near_miss(entry.path(), &options),
+            }
+        });
+        let mut screenshots = screenshots.into_inner().unwrap();
+        let mut unsure = unsure.into_inner().unwrap();
+        unsure.sort_by(|(a, _), (b, _)| a.path().cmp(b.path()));
+        for (entry, why) in unsure {
+            let file_name = entry.file_name().to_string_lossy();
+            let guess = if is_screenshot(entry.path()) {
+                Some(channel_name(&file_name))
+            } else {
+                interactive::guess_channel(&file_name)
+            };
+            match interactive::ask(entry.path(), &why, guess.as_deref(), &mut options) {
+                Ok(Some(channel)) => screenshots.push((entry, Some(channel))),
+                Ok(None) => output::report(
+                    &options,
+                    Outcome::new(entry.path(), Action::Skipped).error(why),
+                ),
+                Err(error) => {
+                    log::error!("Stopped asking, the rest is left where it is: {error}");
+                    break;
+                }
+            }
+        }
+        // the same name from different subfolders, or with a (1) the browser added, goes to one
+        // worker after the other, the first one keeps the name and the others are numbered
+        let base_name = |entry: &DirEntry| {
+            let file_name = entry.file_name().to_string_lossy();
+            unnumbered(&file_name).unwrap_or_else(|| file_name.into_owned())
+        };
+        screenshots.sort_by_cached_key(|(entry, _)| {
+            (
+                base_name(entry),
+                entry.file_name().len(),
+                entry.file_name().to_os_string(),
+            )
+        });
+        let groups = screenshots
+            .chunk_by(|(a, _), (b, _)| base_name(a) == base_name(b))
+            .collect::<Vec<_>>();
+
+        let total = screenshots.len();
+        let catch_up = throttle && total > CATCH_UP_THRESHOLD;
+        if catch_up {
+            log::info!(
+                "Catching up on {total} screenshot(s) in batches of {CATCH_UP_BATCH}, new ones go first"
+            );
+        }
 
-/// Watch for new screenshots in the directory and move them to appropriate folder
-fn run_as_daemon<P: AsRef<Path>>(path: P) -> notify::Result<()> {
-    let (tx, rx) = std::sync::mpsc::channel();
-
-    // pick whatever is the best implfementation for system
-    let mut watcher = RecommendedWatcher::new(tx, Config::default())?;
-
-    // Add a path to be watched. All files and directories at that path and
-    // below will be monitored for changes.
-    watcher.watch(path.as_ref(), RecursiveMode::NonRecursive)?;
-
-    for res in rx {
-        match res {
-            Ok(Event {
-                kind: EventKind::Create(_),
-                paths,
-                ..
-            }) => {
-                for path in paths {
-                    log::debug!("Processing: {}", path.display());
-                    if is_screenshot(&path) {
-                        log::info!("Moving screenshot: {}", path.display());
-                        if let Err(error) = move_file(&path, true) {
-                            log::error!("Error: {error:?}");
+        // a batch run of many files gets a bar and a summary instead of only log lines
+        let summarize = !throttle && total >= progress::THRESHOLD;
+        let progress = summarize.then(|| Progress::start(total)).flatten();
+        let started = Instant::now();
+        output::take_tally();
+
+        let moved = AtomicUsize::new(0);
+        let emptied = Mutex::new(BTreeSet::new());
+        let batch = if catch_up {
+            CATCH_UP_BATCH
+        } else {
+            groups.len().max(1)
+        };
+        let mut done = 0;
+        for (n, batch) in groups.chunks(batch).enumerate() {
+            if catch_up && n > 0 {
+                log::info!("Caught up on {done}/{total} screenshot(s)");
+                thread::sleep(CATCH_UP_PAUSE);
+            }
+            in_parallel(batch, jobs, |group| {
+                for (entry, channel) in *group {
+                    // the watcher's own screenshots go first
+                    while catch_up && delayed::pending() > 0 {
+                        thread::sleep(LIVE_POLL);
+                    }
+                    // `ctl pause` holds off the watcher's passes as well
+                    while throttle && control::is_paused() {
+                        thread::sleep(LIVE_POLL);
+                    }
+
+                    let file = entry.path();
+                    log::info!("Moving screenshot: {}", file.display());
+                    match move_file(file, channel.as_deref(), false, &options) {
+                        Ok(()) => {
+                            if entry.depth() > 1 {
+                                emptied
+                                    .lock()
+                                    .unwrap()
+                                    .insert(file.parent().unwrap().to_path_buf());
+                            }
+                            moved.fetch_add(1, Ordering::Relaxed);
                         }
+                        Err(error) => report_failure(file, &error, &options),
+                    }
+                    if let Some(progress) = &progress {
+                        progress.inc();
                     }
                 }
+            });
+            done += batch.iter().map(|group| group.len()).sum::<usize>();
+        }
+        drop(progress);
+        if catch_up {
+            log::info!("Caught up on all {total} screenshot(s)");
+        }
+        in_parallel(&videos.into_inner().unwrap(), jobs, |(entry, channel)| {
+            let file = entry.path();
+            log::info!("Moving video: {}", file.display());
+            match videos::organize(file, channel, false, &options) {
+                Ok(()) if entry.depth() > 1 => {
+                    emptied
+                        .lock()
+                        .unwrap()
+                        .insert(file.parent().unwrap().to_path_buf());
+                }
+                Ok(()) => {}
+                Err(error) => report_failure(file, &error, &options),
+            }
+        });
+        if summarize && !output::is_json(&options) {
+            output::print_summary(total, started.elapsed(), &output::take_tally());
+        }
+
+        // deepest first so a month folder goes after the day folders in it
+        let moved = moved.into_inner();
+        for dir in emptied.into_inner().unwrap().iter().rev() {
+            if !busy.contains(dir) {
+                migrate::remove_empty(dir, &path);
+            }
+        }
+        if options.clean_empty {
+            if options.recursive {
+                remove_empty_subfolders(&path, depth, &scope, &busy);
+            }
+            collection::remove_empty_channels(&save_dir);
+        }
+
+        if let Some(settings) = &options.config.s3 {
+            s3::upload_pending(&save_dir, settings);
+        }
+        if let Some(retention) = &options.config.retention {
+            retention::enforce_all(&save_dir, retention, options.index.as_deref());
+        }
+
+        if options.notify && moved > 0 {
+            notifications::organized(moved);
+        }
+    })
+}
+
+/// remove the subfolders of `path` down to `depth` that have no files left, apart from what's
+/// out of `scope` and the `busy` ones
+fn remove_empty_subfolders(path: &Path, depth: usize, scope: &Scope, busy: &BTreeSet<PathBuf>) {
+    let dirs = WalkDir::new(path)
+        .min_depth(1)
+        .max_depth(depth)
+        .into_iter()
+        .filter_entry(|entry| !scope.excludes(entry.path()))
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_dir() && !busy.contains(entry.path()))
+        .map(|entry| entry.into_path())
+        .collect::<Vec<_>>();
+    // deepest first, and a folder with files is simply left
+    for dir in dirs.iter().rev() {
+        if fs::remove_dir(dir).is_ok() {
+            log::info!("Removed empty folder {}", dir.display());
+        }
+    }
+}
+
+/// run `work` on every item on up to `jobs` threads, in no particular order
+fn in_parallel<'a, T: Sync>(items: &'a [T], jobs: usize, work: impl Fn(&'a T) + Sync) {
+    let next = AtomicUsize::new(0);
+    let work = &work;
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(items.len()) {
+            scope.spawn(|| {
+                while let Some(item) = items.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    work(item);
+                }
+            });
+        }
+    });
+}
+
+/// How a file that turned up is treated
+enum Kind {
+    /// organized, into the folder of the channel it was assigned in the config if it was
+    Screenshot(Option<String>),
+    /// a clip or vod of the channel, with --include-videos
+    Video(String),
+    /// the config says to leave files named like it, or --channels, --since or --until leave it
+    /// out
+    Skipped,
+    Other,
+}
+
+/// what to do with `path` going by its name, the config's [decisions], --channels, --since and
+/// --until
+fn classify(path: &Path, options: &Options) -> Kind {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let kind = classify_name(path, &file_name, options);
+    let channel = match &kind {
+        Kind::Screenshot(Some(channel)) | Kind::Video(channel) => channel.clone(),
+        Kind::Screenshot(None) => channel_name(&file_name),
+        Kind::Skipped | Kind::Other => return kind,
+    };
+    if !options.allows(&channel) {
+        log::debug!(
+            "{} is of {channel}, that's not one of the channels to organize",
+            path.display()
+        );
+        return Kind::Skipped;
+    }
+    if !options.in_dates(&file_name) {
+        log::debug!(
+            "{} wasn't captured between --since and --until",
+            path.display()
+        );
+        return Kind::Skipped;
+    }
+    kind
+}
+
+/// what to do with `path` going by its name and the config's [decisions]
+fn classify_name(path: &Path, file_name: &str, options: &Options) -> Kind {
+    let decisions = &options.config.decisions;
+    if decisions.skips(file_name) {
+        Kind::Skipped
+    } else if let Some(channel) = decisions.channel_for(file_name) {
+        Kind::Screenshot(Some(channel.to_string()))
+    } else if is_screenshot(path) {
+        Kind::Screenshot(None)
+    } else if let Some(channel) = options
+        .include_videos
+        .then(|| videos::channel_of(file_name))
+        .flatten()
+    {
+        Kind::Video(channel)
+    } else {
+        #[cfg(feature = "ocr")]
+        if options.ocr && file_name.to_lowercase().ends_with(".png") {
+            match ocr::channel_of(path) {
+                Ok(Some(channel)) => return Kind::Screenshot(Some(channel)),
+                Ok(None) => {}
+                Err(error) => log::warn!("Failed to read {}: {error}", path.display()),
             }
-            Ok(_) => {} // Ignore other kind of events
-            Err(error) => log::error!("Error: {error:?}"),
         }
+        Kind::Other
     }
+}
 
-    Ok(())
+/// why --interactive should ask about the screenshot at `path` before organizing it, if it should
+fn doubt(path: &Path, options: &Options) -> Option<String> {
+    let file_name = path.file_name()?.to_string_lossy();
+    if capture_time(&file_name).is_none() {
+        return Some("it's only named a bit like a twitch screenshot".to_string());
+    }
+    let channel = channel_name(&file_name);
+    match options.twitch.as_ref()?.user(&channel) {
+        Ok(None) => Some(format!("there's no twitch channel {channel}")),
+        _ => None,
+    }
 }
 
-/// Simple heuristic to determine if a file is a twitch screenshot
+/// Whether a file is a twitch screenshot, going by how sure [confidence::score] is of its name
 fn is_screenshot(path: &Path) -> bool {
-    let filename = path
-        .file_name()
-        .unwrap()
-        .to_str()
-        .expect("Invalid filename");
-
-    // its a png
-    if !filename.ends_with(".png") {
+    // names that aren't valid unicode are still screenshots, the odd bytes stand in for a
+    // character or two of the channel
+    let Some(filename) = path.file_name().map(|name| name.to_string_lossy()) else {
+        return false;
+    };
+
+    // something a sync tool is still writing, it gets renamed when done
+    if sync_tools::is_temp(&filename) {
         return false;
     }
 
-    // remove png
-    let filename = filename.strip_suffix(".png").unwrap();
+    let score = confidence::score(&filename);
+    let min = confidence::min();
+    log::debug!("{filename} scored {score}, {min:.2} is needed");
+    score.total() >= min
+}
 
-    // that has three _
-    let parts: Vec<&str> = filename.split('_').collect();
-    if parts.len() < 5 {
-        // not equal to 5 because the channel name can have _ in it
-        return false;
+/// move the file to wherever its [Plan] says, into the folder of `channel` if it was assigned one
+fn move_file(
+    file_path: &Path,
+    channel: Option<&str>,
+    daemon_mode: bool,
+    options: &Options,
+) -> io::Result<()> {
+    let picked_up = Instant::now();
+    if let Some(index) = &options.index {
+        if let Some(existing) = index.find_organized(file_path)? {
+            log::info!(
+                "{} was already organized to {}, leaving it",
+                file_path.display(),
+                existing.display()
+            );
+            output::report(
+                options,
+                Outcome::new(file_path, Action::Skipped).to(&existing),
+            );
+            return Ok(());
+        }
     }
 
-    let splits = parts.len();
-    // -3 to end is time
-    let time = parts[splits - 3..].join("_");
-    // -4 is date
-    let date = parts[splits - 4];
-    // channel name would be 0 to -5 joined _
+    if let (Some(twitch), None) = (&options.twitch, channel) {
+        let channel = channel_name(&file_path.file_name().unwrap().to_string_lossy());
+        match twitch.user(&channel) {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                log::info!(
+                    "{} looks like a screenshot but there's no twitch channel {channel}, leaving it",
+                    file_path.display()
+                );
+                output::report(
+                    options,
+                    Outcome::new(file_path, Action::Skipped)
+                        .channel(&channel)
+                        .error("there's no twitch channel by that name"),
+                );
+                return Ok(());
+            }
+            Err(error) => {
+                log::warn!("Couldn't check {channel} with Twitch, organizing it anyway: {error}")
+            }
+        }
+    }
 
-    // its of format Sat-Jan-18-2025
-    if date.len() != 15 || date.split('-').count() != 4 {
-        return false;
+    let plan = Plan::new(file_path, channel, options);
+    if let Some(format) = options.convert {
+        if convert::already_converted(&plan.target, format) {
+            log::debug!("{} is already organized and converted", file_path.display());
+            output::report(options, skipped(file_path, &plan));
+            return Ok(());
+        }
     }
+    let (plan, claim) = plan.claim(file_path, options.storage())?;
+    let target_dir = plan.target.parent().expect("Target has a parent directory");
+    options.create_dir_all(target_dir)?; // Ensure the target directory exists
 
-    // remove the possible duplicate number suffix like (1) or (2)
-    let time = time.split('(').next().unwrap();
-    // this should look like 1_06_05-PM or 12_06_05-AM
-    if (time.len() == 10 || time.len() == 11) && time.split('_').count() != 3 {
-        return false;
+    // Move the file once whatever is writing it let go of it
+    if daemon_mode {
+        let options = options.clone();
+        let source = file_path.to_path_buf();
+        let mut wait = readiness::Wait::start();
+        let mut plan = Some(plan);
+        delayed::schedule(
+            Box::new(move || {
+                // held for as long as the job is, whether it runs, fails or is never run
+                let _claim = &claim;
+                if let Some(again) = wait.check(&source) {
+                    return Some(again);
+                }
+                let plan = plan.take().expect("a move is done once");
+                match execute(&plan, &source, &options) {
+                    Err(e) => report_failure(&source, &e, &options),
+                    Ok(Transfer::AlreadyThere) => {
+                        log::debug!("{} is already organized", source.display());
+                        output::report(&options, skipped(&source, &plan));
+                    }
+                    Ok(transfer) => {
+                        let plan = land(&source, plan, transfer, &options);
+                        metrics::moved_in(picked_up.elapsed());
+                        if options.notify {
+                            notifications::moved(
+                                options.config.labels.of(&plan.channel),
+                                &plan.target,
+                            );
+                        }
+                    }
+                }
+                None
+            }),
+            Duration::ZERO,
+        )?;
+    } else {
+        if readiness::recently_modified(file_path) {
+            readiness::wait(file_path);
+        }
+        let transfer = execute(&plan, file_path, options);
+        drop(claim);
+        match transfer? {
+            Transfer::AlreadyThere => {
+                log::debug!("{} is already organized", file_path.display());
+                output::report(options, skipped(file_path, &plan));
+            }
+            transfer => {
+                land(file_path, plan, transfer, options);
+                metrics::moved_in(picked_up.elapsed());
+            }
+        }
     }
 
-    // this is now very likely a screenshot
-    return true;
+    Ok(())
 }
 
-/// move the file to [SAVE_TO]/[channel_name]/[filename]
-fn move_file(file_path: &Path, daemon_mode: bool) -> io::Result<()> {
-    let parent_dir = file_path.parent().expect("File has no parent directory");
-    let channel_name = channel_name(file_path.file_name().unwrap().to_str().unwrap());
+/// first wait before a move of a file that's in use is tried again, doubled every time
+const LOCKED_BACKOFF: Duration = Duration::from_millis(250);
 
-    const SAVE_TO: &str = "twitch-screenshots";
+/// default for `move_attempts` in the config
+const MOVE_ATTEMPTS: u32 = 5;
 
-    let target_dir = parent_dir.join(SAVE_TO).join(channel_name);
-    fs::create_dir_all(&target_dir)?; // Ensure the target directory exists
-    let file_name = file_path.file_name().unwrap();
-    let target_file_path = target_dir.join(file_name);
+/// [Plan::execute], trying again a couple times while the browser or an antivirus scan still
+/// holds the file ( windows doesn't let open files be moved )
+fn execute(plan: &Plan, source: &Path, options: &Options) -> io::Result<Transfer> {
+    let attempts = options.config.move_attempts.unwrap_or(MOVE_ATTEMPTS).max(1);
+    let mut backoff = LOCKED_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match plan.execute(source, options.read_only_source, options.storage()) {
+            Err(error) if attempt < attempts && plan::is_locked(&error) => {
+                log::debug!(
+                    "{} is in use ( {error} ), trying again in {backoff:?}",
+                    source.display()
+                );
+                thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+            result => break result,
+        }
+    }
+}
 
-    // Move the file after 2s to ensure it's fully written when moving
-    let file_path_clone = file_path.to_path_buf();
+/// take a screenshot that just got to its target the rest of the way: convert it if asked to,
+/// journal the move and do the bookkeeping. Returns the plan for where it ended up.
+fn land(source: &Path, plan: Plan, transfer: Transfer, options: &Options) -> Plan {
+    log::info!("File {transfer} to: {}", plan.target.to_string_lossy());
+    quarantine::forget(source);
+    confidence::know(&plan.channel);
+    // a linked screenshot is still the source, and a copied one has to stay the same as the
+    // source to be recognized next time, only files that are ours alone are tagged
+    // the still formats would keep only the first frame of an animated capture
+    let convert = options
+        .convert
+        .filter(|_| !animated::is_animated(&plan.target));
+    // journal undo can't bring screenshots back from a remote
+    let journaled = options.storage.is_none();
+    let (plan, ours) = match convert {
+        None => {
+            if transfer == Transfer::Moved && journaled {
+                journal::moved(source, &plan.target);
+            }
+            (plan, transfer == Transfer::Moved)
+        }
+        Some(format) => {
+            let converted = convert::convert(
+                &plan,
+                format,
+                options.quality,
+                options.keep_original,
+                options.storage(),
+            );
+            match converted {
+                Ok((converted, original)) => {
+                    log::info!("Converted to: {}", converted.target.display());
+                    // only a kept original can be put back, the converted file isn't the screenshot
+                    if let (Transfer::Moved, Some(original)) = (transfer, original) {
+                        journal::moved(source, &original);
+                    }
+                    (converted, true)
+                }
+                Err(error) => {
+                    log::error!("Failed to convert {}: {error:?}", plan.target.display());
+                    if transfer == Transfer::Moved {
+                        journal::moved(source, &plan.target);
+                    }
+                    (plan, transfer == Transfer::Moved)
+                }
+            }
+        }
+    };
 
-    if daemon_mode {
-        thread::spawn(move || {
-            thread::sleep(std::time::Duration::from_secs(2));
-            if let Err(e) = fs::rename(&file_path_clone, &target_file_path) {
-                log::error!("Failed to move file: {}", e);
-            } else {
-                log::info!("File moved to: {}", target_file_path.to_string_lossy());
+    if options.tag_metadata && ours {
+        let tags = metadata::Tags {
+            channel: &plan.channel,
+            captured_at: capture_time(&plan.target.file_name().unwrap().to_string_lossy()),
+            original_name: &source.file_name().unwrap().to_string_lossy(),
+        };
+        if let Err(error) = metadata::tag(&plan.target, &tags) {
+            log::error!("Failed to tag {}: {error:?}", plan.target.display());
+        }
+    }
+    after_move(&plan, options);
+    let original = source.file_name().unwrap();
+    if let (Some(index), Some(name)) = (&options.index, plan.target.file_name()) {
+        if name != original {
+            if let Err(error) = index.set_original_name(&plan.target, &original.to_string_lossy()) {
+                log::error!("Failed to index {}: {error:?}", plan.target.display());
             }
-        });
+        }
+    }
+    if options.manifest {
+        if let Err(error) = manifest::record(&plan, &original.to_string_lossy()) {
+            log::error!(
+                "Failed to update the manifest of {}: {error:?}",
+                plan.channel_dir.display()
+            );
+        }
+    }
+    let action = match transfer {
+        Transfer::Linked => Action::Linked,
+        Transfer::Copied => Action::Copied,
+        Transfer::Moved | Transfer::AlreadyThere => Action::Moved,
+    };
+    output::report(
+        options,
+        Outcome::new(source, action)
+            .to(&plan.target)
+            .channel(&plan.channel),
+    );
+    plan
+}
+
+/// `source` is organized to `plan` already
+fn skipped<'a>(source: &'a Path, plan: &'a Plan) -> Outcome<'a> {
+    Outcome::new(source, Action::Skipped)
+        .to(&plan.target)
+        .channel(&plan.channel)
+}
+
+/// log a failed move, and let the user know if they asked for notifications
+fn report_failure(path: &Path, error: &io::Error, options: &Options) {
+    if error.kind() == io::ErrorKind::WouldBlock {
+        // a sync tool is busy with the target, not a failure, it's picked up on the next run
+        log::warn!("Leaving {} for now: {error}", path.display());
+        output::report(options, Outcome::new(path, Action::Skipped).error(error));
+        return;
+    }
+    if error.kind() == io::ErrorKind::NotFound && !path.exists() {
+        // moved away by hand, or organized by a rescan that got to it first
+        log::debug!("{} is gone, nothing to move", path.display());
+        output::report(options, Outcome::new(path, Action::Skipped).error(error));
+        return;
+    }
+    if error
+        .get_ref()
+        .is_some_and(|inner| inner.is::<delayed::QueueFull>())
+    {
+        // left where it is, counted like any other failure so it's put aside if it keeps happening
+        log::error!("Dropped {}: {error}", path.display());
     } else {
-        fs::rename(&file_path, &target_file_path)?;
-        log::info!("File moved to: {}", target_file_path.to_string_lossy());
+        log::error!("Failed to move {}: {error:?}", path.display());
     }
+    status::error(format!("Failed to move {}: {error}", path.display()));
+    output::report(options, Outcome::new(path, Action::Failed).error(error));
+    activity::failed();
+    quarantine::failed(path, error, options);
+    if options.notify {
+        notifications::failed(path, error);
+    }
+}
 
-    Ok(())
+/// bookkeeping once a screenshot landed in its channel folder
+fn after_move(plan: &Plan, options: &Options) {
+    activity::organized(&plan.channel);
+    plan.create_links();
+    if let Some(index) = &options.index {
+        let indexed = index.record(&plan.channel, &plan.target).and_then(|()| {
+            plan.game
+                .as_ref()
+                .map_or(Ok(()), |game| index.set_game(&plan.target, game))
+        });
+        if let Err(error) = indexed {
+            log::error!("Failed to index {}: {error:?}", plan.target.display());
+        }
+    }
+    if let Some(twitch) = &options.twitch {
+        link_vod(twitch, plan, options);
+    }
+    if options.session_files {
+        if let Err(error) = session::update(&plan.target) {
+            log::error!("Failed to update session file: {error:?}");
+        }
+    }
+    if let Some(url) = &options.config.webhook_url {
+        webhook::post(url, options.config.labels.of(&plan.channel), &plan.target);
+    }
+    if let Some(settings) = &options.config.thumbnails {
+        let save_dir = plan
+            .channel_dir
+            .parent()
+            .expect("channel dir is in the save dir");
+        if let Err(error) = thumbnails::update(save_dir, &plan.target, settings) {
+            log::error!(
+                "Failed to make a thumbnail of {}: {error:?}",
+                plan.target.display()
+            );
+        }
+    }
+    if let Some(settings) = &options.config.s3 {
+        let save_dir = plan
+            .channel_dir
+            .parent()
+            .expect("channel dir is in the save dir");
+        s3::enqueue(save_dir, &plan.target, settings);
+    }
+    // this can move the screenshot again
+    if let Err(error) = sharding::maybe_shard(&plan.channel_dir, options) {
+        log::error!("Failed to split {}: {error:?}", plan.channel_dir.display());
+    }
+    // last, this can get rid of it
+    if let Some(retention) = &options.config.retention {
+        if let Err(error) =
+            retention::enforce(&plan.channel_dir, retention, options.index.as_deref())
+        {
+            log::error!("Failed to prune {}: {error:?}", plan.channel_dir.display());
+        }
+    }
+}
+
+/// find the moment of the screenshot in the vod, for the index and a sidecar next to it
+fn link_vod(twitch: &twitch::Helix, plan: &Plan, options: &Options) {
+    let Some(captured_at) = capture_time(&plan.target.file_name().unwrap().to_string_lossy())
+    else {
+        return;
+    };
+    let Some(vod) = twitch.vod(&plan.channel, captured_at) else {
+        return;
+    };
+    log::info!("In the vod at: {}", vod.url);
+
+    let sidecar = sidecar::Sidecar {
+        channel: &plan.channel,
+        captured_at,
+        vod: &vod,
+    };
+    if let Err(error) = sidecar::write(options.storage(), &plan.target, &sidecar) {
+        log::error!(
+            "Failed to write the sidecar of {}: {error:?}",
+            plan.target.display()
+        );
+    }
+    if let Some(index) = &options.index {
+        if let Err(error) = index.set_vod(&plan.target, &vod.url) {
+            log::error!("Failed to index {}: {error:?}", plan.target.display());
+        }
+    }
+}
+
+/// whether two channel names are the same one, without caring about case in any script since
+/// display names can be in any
+fn same_channel(a: &str, b: &str) -> bool {
+    a == b || a.to_lowercase() == b.to_lowercase()
 }
 
 /// channel name from filename
 fn channel_name(filename: &str) -> String {
-    let parts = filename.split('_').collect::<Vec<&str>>();
-    let channel_name = parts[0..parts.len() - 4].join("_");
-    channel_name
+    if let Some((_, channel)) = strip_extension(filename).and_then(iso_parts) {
+        return channel.to_string();
+    }
+    let stem = strip_extension(filename).unwrap_or(filename);
+    let parts = stem.split('_').collect::<Vec<&str>>();
+    // a name too short for a date and time only gets here with a low --min-confidence
+    parts[0..parts.len().saturating_sub(4).max(1)].join("_")
+}
+
+/// twitch saves png, webp, avif and jpg are what --convert writes, gif and webm are short clips
+/// some capture tools save with the same names
+const EXTENSIONS: [&str; 6] = ["png", "webp", "avif", "jpg", "gif", "webm"];
+
+/// filename without its image extension, none if it's not one of [EXTENSIONS]
+fn strip_extension(filename: &str) -> Option<&str> {
+    let (stem, extension) = filename.rsplit_once('.')?;
+    EXTENSIONS.contains(&extension).then_some(stem)
+}
+
+/// capture time from a filename like channel_Sat-Jan-18-2025_1_06_05-PM.png
+fn capture_time(filename: &str) -> Option<NaiveDateTime> {
+    let filename = strip_extension(filename)?;
+    if let Some((time, _)) = iso_parts(filename) {
+        return Some(time);
+    }
+    let parts = strip_duplicate(filename).split('_').collect::<Vec<&str>>();
+    if parts.len() < 5 {
+        return None;
+    }
+
+    let splits = parts.len();
+    // the weekday is redundant, skip it so a mismatching one doesn't throw the date away
+    let (_weekday, date) = parts[splits - 4].split_once('-')?;
+    let date = NaiveDate::parse_from_str(date, "%b-%d-%Y").ok()?;
+    let time = parts[splits - 3..].join("_");
+    let time = NaiveTime::parse_from_str(&time, "%I_%M_%S-%p").ok()?;
+
+    Some(date.and_time(time))
+}
+
+/// how --rename-to-iso names screenshots, by name is by capture time then
+const ISO_FORMAT: &str = "%Y-%m-%dT%H-%M-%S";
+
+/// capture time and channel from a name without extension that --rename-to-iso gave, like
+/// 2025-01-18T13-06-05_channel
+fn iso_parts(filename: &str) -> Option<(NaiveDateTime, &str)> {
+    let (time, channel) = filename.split_once('_')?;
+    let time = NaiveDateTime::parse_from_str(time, ISO_FORMAT).ok()?;
+    // drop the number telling duplicates apart, (1) or -2, channel names can't have either
+    let channel = channel.split(['(', '-']).next().unwrap().trim_end();
+    (!channel.is_empty()).then_some((time, channel))
+}
+
+/// the name --rename-to-iso gives a screenshot named like twitch does, without the number
+/// telling duplicates apart. None if it's named that way already or has no capture time.
+fn iso_name(filename: &str) -> Option<String> {
+    let stem = strip_extension(filename)?;
+    if iso_parts(stem).is_some() {
+        return None;
+    }
+    let time = capture_time(filename)?;
+    let (_, extension) = filename.rsplit_once('.')?;
+    Some(format!(
+        "{}_{}.{extension}",
+        time.format(ISO_FORMAT),
+        channel_name(filename)
+    ))
+}
+
+/// `stem` without the number that tells screenshots with the same name apart: the ` (1)` a
+/// browser adds when the name is taken in the downloads, or the `-2` [plan::Plan::claim] adds
+/// when it's taken in the channel folder
+fn strip_duplicate(stem: &str) -> &str {
+    let is_number = |text: &str| !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit());
+    if let Some((name, number)) = stem
+        .strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once('('))
+    {
+        if is_number(number) {
+            return name.trim_end();
+        }
+    }
+    match stem.rsplit_once('-') {
+        // twitch names end with AM or PM, and iso ones with a channel that can't have a -
+        Some((name, number))
+            if is_number(number)
+                && (name.ends_with("AM") || name.ends_with("PM") || iso_parts(name).is_some()) =>
+        {
+            name
+        }
+        _ => stem,
+    }
+}
+
+/// `filename` without the number telling duplicates apart, none if it doesn't have one
+fn unnumbered(filename: &str) -> Option<String> {
+    let stem = strip_extension(filename)?;
+    let (_, extension) = filename.rsplit_once('.')?;
+    let name = strip_duplicate(stem);
+    (name.len() != stem.len()).then(|| format!("{name}.{extension}"))
+}
+
+/// where the organizer keeps its own files ( logs, journals ),
+/// ~/.local/state/twitch-screenshot-organizer on linux
+fn state_dir() -> PathBuf {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("twitch-screenshot-organizer")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAME: &str = "chan_Sat-Jan-18-2025_1_06_05-PM";
+
+    #[test]
+    fn browser_numbers_are_stripped() {
+        assert_eq!(strip_duplicate(&format!("{NAME} (1)")), NAME);
+        assert_eq!(strip_duplicate(&format!("{NAME} (12)")), NAME);
+        assert_eq!(strip_duplicate(&format!("{NAME}(3)")), NAME);
+        assert_eq!(
+            unnumbered(&format!("{NAME} (1).png")),
+            Some(format!("{NAME}.png"))
+        );
+    }
+
+    #[test]
+    fn claim_numbers_are_stripped() {
+        assert_eq!(strip_duplicate(&format!("{NAME}-2")), NAME);
+        assert_eq!(strip_duplicate(&format!("{NAME}-10")), NAME);
+        assert_eq!(
+            unnumbered(&format!("{NAME}-2.png")),
+            Some(format!("{NAME}.png"))
+        );
+    }
+
+    #[test]
+    fn names_that_only_look_numbered_are_kept() {
+        for stem in [
+            NAME,
+            "my-clip-2",
+            "highlight-",
+            "photo (final)",
+            "photo ()",
+            "chan_Sat-Jan-18-2025_1_06_05-PM (1a)",
+            "chan_Sat-Jan-18-2025_1_06_05-PM-",
+            "2025-01-18T13-06-05_chan",
+        ] {
+            assert_eq!(strip_duplicate(stem), stem);
+            assert_eq!(unnumbered(&format!("{stem}.png")), None);
+        }
+        assert_eq!(unnumbered(NAME), None);
+    }
+
+    #[test]
+    fn numbered_screenshots_keep_their_capture_time() {
+        let captured = capture_time(&format!("{NAME}.png"));
+        assert!(captured.is_some());
+        assert_eq!(capture_time(&format!("{NAME} (1).png")), captured);
+        assert_eq!(capture_time(&format!("{NAME}-2.png")), captured);
+    }
+
+    #[test]
+    fn iso_names_drop_the_number() {
+        let iso = Some("2025-01-18T13-06-05_chan.png".to_string());
+        assert_eq!(iso_name(&format!("{NAME}.png")), iso);
+        assert_eq!(iso_name(&format!("{NAME} (1).png")), iso);
+        assert_eq!(iso_name(&format!("{NAME}-2.png")), iso);
+        // already named that way, numbered or not
+        assert_eq!(iso_name("2025-01-18T13-06-05_chan.png"), None);
+        assert_eq!(iso_name("2025-01-18T13-06-05_chan-2.png"), None);
+    }
+
+    #[test]
+    fn numbered_iso_names_are_read() {
+        let time = NaiveDateTime::parse_from_str("2025-01-18T13-06-05", ISO_FORMAT).unwrap();
+        for stem in [
+            "2025-01-18T13-06-05_chan",
+            "2025-01-18T13-06-05_chan-2",
+            "2025-01-18T13-06-05_chan (1)",
+        ] {
+            assert_eq!(iso_parts(stem), Some((time, "chan")));
+        }
+        assert_eq!(
+            unnumbered("2025-01-18T13-06-05_chan-2.png"),
+            Some("2025-01-18T13-06-05_chan.png".to_string())
+        );
+        assert_eq!(
+            unnumbered("2025-01-18T13-06-05_chan (1).png"),
+            Some("2025-01-18T13-06-05_chan.png".to_string())
+        );
+        assert_eq!(iso_parts("2025-01-18T13-06-05_"), None);
+        assert_eq!(iso_parts("not-a-time_chan"), None);
+    }
 }
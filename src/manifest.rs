@@ -0,0 +1,106 @@
+use chrono::{DateTime, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::plan::Plan;
+use crate::{capture_time, index, sharding, sync_tools};
+
+/// in every channel folder with --manifest
+pub const FILE: &str = "manifest.json";
+
+/// moves of the same channel happen on several threads, each one reads and writes the manifest
+static WRITING: Mutex<()> = Mutex::new(());
+
+/// What's in a channel folder, for static site generators and backup scripts that don't want to
+/// read the SQLite index
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Manifest {
+    pub channel: String,
+    /// by path
+    pub files: Vec<Entry>,
+}
+
+/// One screenshot in a [Manifest]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Entry {
+    /// relative to the channel folder, with / between folders when it's split by month
+    pub path: String,
+    pub captured_at: Option<NaiveDateTime>,
+    pub sha256: String,
+    pub size: u64,
+    /// what it was called before it was organized
+    pub original_name: String,
+    pub organized_at: DateTime<Local>,
+}
+
+/// Add the screenshot `plan` put in its channel folder to the folder's manifest.json. Files that
+/// aren't there anymore ( pruned, deleted by hand .. ) drop out of it, the ones split into month
+/// folders since are followed there. The manifest is replaced in one go, readers never see half
+/// of it.
+pub fn record(plan: &Plan, original_name: &str) -> io::Result<()> {
+    let _writing = WRITING.lock().unwrap();
+    let channel_dir = &plan.channel_dir;
+    let mut manifest = read(channel_dir)?;
+    manifest.channel = plan.channel.clone();
+
+    let file_name = plan.target.file_name().expect("screenshot has a name");
+    manifest
+        .files
+        .retain(|entry| entry.path.rsplit('/').next() != Some(&*file_name.to_string_lossy()));
+    // split into a month folder or pruned by retention right away
+    if let Some(target) = locate(channel_dir, &plan.target) {
+        manifest.files.push(Entry {
+            path: relative(channel_dir, &target),
+            captured_at: capture_time(&file_name.to_string_lossy()),
+            sha256: index::hash_file(&target)?,
+            size: target.metadata()?.len(),
+            original_name: original_name.to_string(),
+            organized_at: Local::now(),
+        });
+    }
+
+    manifest.files.retain_mut(
+        |entry| match locate(channel_dir, &channel_dir.join(&entry.path)) {
+            Some(path) => {
+                entry.path = relative(channel_dir, &path);
+                true
+            }
+            None => false,
+        },
+    );
+    manifest.files.sort_by(|a, b| a.path.cmp(&b.path));
+    write(channel_dir, &manifest)
+}
+
+/// where `path` in `channel_dir` is now, it may have been split into a month folder
+fn locate(channel_dir: &Path, path: &Path) -> Option<PathBuf> {
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    sharding::shard_path(channel_dir, path.file_name()?).filter(|sharded| sharded.is_file())
+}
+
+fn read(channel_dir: &Path) -> io::Result<Manifest> {
+    match fs::read(channel_dir.join(FILE)) {
+        Ok(json) => Ok(serde_json::from_slice(&json)?),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Manifest::default()),
+        Err(error) => Err(error),
+    }
+}
+
+fn write(channel_dir: &Path, manifest: &Manifest) -> io::Result<()> {
+    sync_tools::write(
+        &channel_dir.join(FILE),
+        &serde_json::to_vec_pretty(manifest)?,
+    )
+}
+
+fn relative(channel_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(channel_dir)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
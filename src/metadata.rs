@@ -0,0 +1,273 @@
+use chrono::NaiveDateTime;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::sync_tools;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+const SOFTWARE: &str = "twitch-screenshot-organizer";
+
+/// What's written into an organized screenshot so it can be told apart from the rest once it's
+/// renamed or copied out of its channel folder
+pub struct Tags<'a> {
+    pub channel: &'a str,
+    pub captured_at: Option<NaiveDateTime>,
+    /// name of the screenshot before it was organized
+    pub original_name: &'a str,
+}
+
+impl Tags<'_> {
+    /// keyword and text pairs, the keywords are what exiftool & co show
+    fn text(&self) -> Vec<(&'static str, String)> {
+        let mut text = vec![
+            ("Channel", self.channel.to_string()),
+            ("Original Filename", self.original_name.to_string()),
+            ("Software", SOFTWARE.to_string()),
+        ];
+        if let Some(time) = self.captured_at {
+            text.push((
+                "Creation Time",
+                time.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            ));
+        }
+        text
+    }
+}
+
+/// Write `tags` into the screenshot at `path`, text chunks for png and EXIF for jpeg. Earlier
+/// tags are replaced, other formats are left alone.
+pub fn tag(path: &Path, tags: &Tags) -> io::Result<()> {
+    let tagged = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("png") => tag_png(&fs::read(path)?, tags)?,
+        Some("jpg") => tag_jpeg(&fs::read(path)?, tags)?,
+        _ => {
+            log::debug!("No metadata written to {}", path.display());
+            return Ok(());
+        }
+    };
+    sync_tools::write(path, &tagged)
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what.to_string())
+}
+
+/// text chunks go right after the header, chunks with the same keywords are dropped
+fn tag_png(png: &[u8], tags: &Tags) -> io::Result<Vec<u8>> {
+    let chunks = png
+        .strip_prefix(PNG_SIGNATURE)
+        .ok_or_else(|| invalid("not a png"))?;
+    let text = tags.text();
+
+    let mut tagged = PNG_SIGNATURE.to_vec();
+    let mut rest = chunks;
+    while !rest.is_empty() {
+        if rest.len() < 12 {
+            return Err(invalid("truncated png chunk"));
+        }
+        let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+        let Some(chunk) = rest.get(..len + 12) else {
+            return Err(invalid("truncated png chunk"));
+        };
+        rest = &rest[len + 12..];
+
+        let kind = &chunk[4..8];
+        let keyword = chunk[8..8 + len]
+            .split(|b| *b == 0)
+            .next()
+            .unwrap_or_default();
+        let ours = text
+            .iter()
+            .any(|(our_keyword, _)| our_keyword.as_bytes() == keyword);
+        if (kind == b"tEXt" || kind == b"iTXt") && ours {
+            continue;
+        }
+        tagged.extend_from_slice(chunk);
+
+        if kind == b"IHDR" {
+            for (keyword, value) in &text {
+                text_chunk(&mut tagged, keyword, value);
+            }
+        }
+    }
+    Ok(tagged)
+}
+
+/// tEXt is latin-1, anything else has to go into an iTXt chunk
+fn text_chunk(png: &mut Vec<u8>, keyword: &str, value: &str) {
+    let mut data = keyword.as_bytes().to_vec();
+    data.push(0);
+    let kind = if value.chars().all(|c| (c as u32) < 0x100) {
+        data.extend(value.chars().map(|c| c as u8));
+        b"tEXt"
+    } else {
+        // not compressed, no language, no translated keyword
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(value.as_bytes());
+        b"iTXt"
+    };
+
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(&data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// the crc png chunks end with, bit by bit is plenty for a few short chunks
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// EXIF segment after the JFIF one, earlier EXIF segments are dropped
+fn tag_jpeg(jpeg: &[u8], tags: &Tags) -> io::Result<Vec<u8>> {
+    let mut rest = jpeg
+        .strip_prefix(b"\xFF\xD8")
+        .ok_or_else(|| invalid("not a jpeg"))?;
+    let exif = exif(tags);
+    let mut app1 = b"\xFF\xE1".to_vec();
+    app1.extend_from_slice(&(exif.len() as u16 + 2).to_be_bytes());
+    app1.extend_from_slice(&exif);
+
+    let mut tagged = b"\xFF\xD8".to_vec();
+    let mut app1 = Some(app1);
+    // segments up to the image data, the rest is copied as is
+    while let [0xFF, marker, ..] = *rest {
+        if marker == 0xDA {
+            break;
+        }
+        if rest.len() < 4 {
+            return Err(invalid("truncated jpeg segment"));
+        }
+        let len = usize::from(u16::from_be_bytes([rest[2], rest[3]])) + 2;
+        let Some(segment) = rest.get(..len) else {
+            return Err(invalid("truncated jpeg segment"));
+        };
+        rest = &rest[len..];
+
+        if marker == 0xE1 && segment[4..].starts_with(b"Exif\0\0") {
+            continue;
+        }
+        if marker != 0xE0 {
+            tagged.extend(app1.take().unwrap_or_default());
+        }
+        tagged.extend_from_slice(segment);
+    }
+    tagged.extend(app1.unwrap_or_default());
+    tagged.extend_from_slice(rest);
+    Ok(tagged)
+}
+
+/// EXIF field types
+const ASCII: u16 = 2;
+const LONG: u16 = 4;
+
+struct Entry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    value: Vec<u8>,
+}
+
+impl Entry {
+    fn ascii(tag: u16, value: &str) -> Entry {
+        let mut value = value.as_bytes().to_vec();
+        value.push(0);
+        Entry {
+            tag,
+            kind: ASCII,
+            count: value.len() as u32,
+            value,
+        }
+    }
+}
+
+/// "Exif\0\0" and a little endian tiff header with the channel as the image description, the
+/// original name as the document name and the capture time as when it was taken
+fn exif(tags: &Tags) -> Vec<u8> {
+    let time = tags
+        .captured_at
+        .map(|time| time.format("%Y:%m:%d %H:%M:%S").to_string());
+
+    let mut ifd0 = vec![
+        Entry::ascii(0x010D, tags.original_name),
+        Entry::ascii(0x010E, tags.channel),
+        Entry::ascii(0x0131, SOFTWARE),
+    ];
+    let mut exif_ifd = Vec::new();
+    if let Some(time) = &time {
+        ifd0.push(Entry::ascii(0x0132, time));
+        exif_ifd.push(Entry::ascii(0x9003, time));
+        // offset of the exif ifd, right after ifd0 which starts after the 8 byte header
+        let offset = 8 + ifd_len(&ifd0) + 12;
+        ifd0.push(Entry {
+            tag: 0x8769,
+            kind: LONG,
+            count: 1,
+            value: (offset as u32).to_le_bytes().to_vec(),
+        });
+    }
+
+    let mut tiff = b"II\x2A\x00\x08\x00\x00\x00".to_vec();
+    write_ifd(&mut tiff, &ifd0);
+    if !exif_ifd.is_empty() {
+        write_ifd(&mut tiff, &exif_ifd);
+    }
+
+    let mut exif = b"Exif\0\0".to_vec();
+    exif.extend(tiff);
+    exif
+}
+
+/// bytes an ifd takes including the values that don't fit into their entry
+fn ifd_len(entries: &[Entry]) -> usize {
+    2 + 12 * entries.len()
+        + 4
+        + entries
+            .iter()
+            .filter(|entry| entry.value.len() > 4)
+            .map(|entry| entry.value.len().next_multiple_of(2))
+            .sum::<usize>()
+}
+
+/// append an ifd with its values to `tiff`, offsets are from the start of the tiff header
+fn write_ifd(tiff: &mut Vec<u8>, entries: &[Entry]) {
+    let mut values = tiff.len() + 2 + 12 * entries.len() + 4;
+    let mut overflow = Vec::new();
+
+    tiff.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    for entry in entries {
+        tiff.extend_from_slice(&entry.tag.to_le_bytes());
+        tiff.extend_from_slice(&entry.kind.to_le_bytes());
+        tiff.extend_from_slice(&entry.count.to_le_bytes());
+        if entry.value.len() <= 4 {
+            let mut inline = entry.value.clone();
+            inline.resize(4, 0);
+            tiff.extend_from_slice(&inline);
+        } else {
+            tiff.extend_from_slice(&(values as u32).to_le_bytes());
+            overflow.extend_from_slice(&entry.value);
+            if entry.value.len() % 2 == 1 {
+                overflow.push(0);
+            }
+            values += entry.value.len().next_multiple_of(2);
+        }
+    }
+    // no next ifd
+    tiff.extend_from_slice(&0u32.to_le_bytes());
+    tiff.extend(overflow);
+}
@@ -0,0 +1,122 @@
+use std::fmt::Write as _;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Response, Server};
+
+use crate::{activity, delayed};
+
+/// upper bounds in seconds of the move duration buckets, from a file that was done right away to
+/// one that was written for minutes
+const BUCKETS: [f64; 10] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 300.0];
+
+/// every metric's name starts with this
+const PREFIX: &str = "twitch_screenshot_organizer";
+
+static EVENTS: AtomicU64 = AtomicU64::new(0);
+static RESTARTS: AtomicU64 = AtomicU64::new(0);
+
+/// moves per bucket, the last one for those slower than all of them
+static DURATIONS: [AtomicU64; BUCKETS.len() + 1] = [const { AtomicU64::new(0) }; BUCKETS.len() + 1];
+static DURATION_SUM_MICROS: AtomicU64 = AtomicU64::new(0);
+
+/// Serve what the watcher did so far on `addr` in the Prometheus text format, in the background
+pub fn serve(addr: SocketAddr) -> io::Result<()> {
+    let server = Server::http(addr).map_err(io::Error::other)?;
+    log::info!("Serving metrics on http://{addr}/metrics");
+    thread::spawn(move || {
+        let content_type =
+            Header::from_bytes("Content-Type", "text/plain; version=0.0.4").expect("valid header");
+        for request in server.incoming_requests() {
+            let response = match request.url() {
+                "/metrics" => Response::from_string(render()).with_header(content_type.clone()),
+                _ => Response::from_string("Not found, metrics are at /metrics")
+                    .with_status_code(404),
+            };
+            if let Err(error) = request.respond(response) {
+                log::debug!("Failed to answer a metrics request: {error}");
+            }
+        }
+    });
+    Ok(())
+}
+
+/// A watcher event came in
+pub fn event() {
+    EVENTS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// The watcher was replaced by a fresh one
+pub fn restarted() {
+    RESTARTS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A screenshot got to its channel folder `took` after it was picked up
+pub fn moved_in(took: Duration) {
+    let bucket = BUCKETS
+        .iter()
+        .position(|bound| took.as_secs_f64() <= *bound)
+        .unwrap_or(BUCKETS.len());
+    DURATIONS[bucket].fetch_add(1, Ordering::Relaxed);
+    DURATION_SUM_MICROS.fetch_add(took.as_micros() as u64, Ordering::Relaxed);
+}
+
+fn render() -> String {
+    let (organized, failed) = activity::counts();
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: u64| {
+        let _ = writeln!(out, "# HELP {PREFIX}_{name} {help}");
+        let _ = writeln!(out, "# TYPE {PREFIX}_{name} {kind}");
+        let _ = writeln!(out, "{PREFIX}_{name} {value}");
+    };
+    metric(
+        "moved_total",
+        "counter",
+        "Screenshots organized into their channel folder",
+        organized,
+    );
+    metric(
+        "failed_total",
+        "counter",
+        "Screenshots that failed to be organized",
+        failed,
+    );
+    metric(
+        "events_total",
+        "counter",
+        "File system events the watcher saw",
+        EVENTS.load(Ordering::Relaxed),
+    );
+    metric(
+        "watcher_restarts_total",
+        "counter",
+        "Times the watcher broke or stalled and was replaced",
+        RESTARTS.load(Ordering::Relaxed),
+    );
+    metric(
+        "queue_depth",
+        "gauge",
+        "Screenshots waiting to be written out or being moved",
+        delayed::pending() as u64,
+    );
+
+    let name = format!("{PREFIX}_move_duration_seconds");
+    let _ = writeln!(
+        out,
+        "# HELP {name} Time from picking a screenshot up to it being in its channel folder"
+    );
+    let _ = writeln!(out, "# TYPE {name} histogram");
+    let mut count = 0;
+    for (bound, moves) in BUCKETS.iter().zip(&DURATIONS) {
+        count += moves.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {count}");
+    }
+    count += DURATIONS[BUCKETS.len()].load(Ordering::Relaxed);
+    let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {count}");
+    let sum = DURATION_SUM_MICROS.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+    let _ = writeln!(out, "{name}_sum {sum}");
+    let _ = writeln!(out, "{name}_count {count}");
+    out
+}
@@ -0,0 +1,371 @@
+use chrono::{Month, NaiveDate, NaiveDateTime, NaiveTime};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use walkdir::WalkDir;
+
+use crate::output::{self, Action, Outcome};
+use crate::plan::Plan;
+use crate::{capture_time, channel_name, land, sync_tools, Options};
+
+/// Twitch screenshot names anywhere in a tree of date folders ( 2025/01/18/, 2025-01-18/xqc/ ... )
+const DATE_FIRST: &[&str] = &["**/{name}"];
+
+/// Renamed screenshots in a single folder, the usual ways screenshot tools and scripts name them.
+/// Separators are interchangeable so `xqc 2025-01-18 13.06.05.png` matches the first one too.
+const DUMP: &[&str] = &[
+    "{channel}_{year}-{month}-{day}_{hour}-{minute}-{second}.png",
+    "{channel}_{year}-{month}-{day}_{hour}-{minute}.png",
+    "{year}-{month}-{day}_{hour}-{minute}-{second}_{channel}.png",
+    "{year}-{month}-{day}_{hour}-{minute}_{channel}.png",
+    "{channel}_{year}{month}{day}_{hour}{minute}{second}.png",
+    "{year}{month}{day}_{hour}{minute}{second}_{channel}.png",
+];
+
+/// Move screenshots kept in another organizer's layout into this one. `from` is `date-first`,
+/// `dump` or a pattern like `{year}/{month}/{channel}-{day}_{hour}{minute}{second}.png` matched
+/// against paths relative to `path`.
+pub fn run(path: &Path, from: &str, dry_run: bool, mut options: Options) -> io::Result<()> {
+    let patterns = match from {
+        "date-first" => DATE_FIRST.iter().map(|p| p.to_string()).collect(),
+        "dump" => DUMP.iter().map(|p| p.to_string()).collect(),
+        pattern if pattern.contains('{') => vec![pattern.to_string()],
+        other => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown layout {other}, expected date-first, dump or a pattern"),
+            ))
+        }
+    };
+    let patterns = patterns
+        .iter()
+        .map(|pattern| parse(pattern))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let save_dir = options.save_dir(path);
+    if !dry_run {
+        options.open_index(path)?;
+    }
+
+    let mut moved = 0;
+    let mut unmatched = 0;
+    let mut emptied = BTreeSet::new();
+    for entry in WalkDir::new(path)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.path() != save_dir && !entry.file_name().to_string_lossy().starts_with('.')
+        })
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| !sync_tools::is_temp(&entry.file_name().to_string_lossy()))
+    {
+        let relative = entry
+            .path()
+            .strip_prefix(path)
+            .expect("walked path is under the root")
+            .to_string_lossy()
+            .replace('\\', "/");
+        let Some(file_name) = patterns
+            .iter()
+            .find_map(|pattern| screenshot_name(pattern, &relative))
+        else {
+            log::debug!("{relative} doesn't match the layout, leaving it");
+            unmatched += 1;
+            continue;
+        };
+
+        // planned as if the file already had its twitch name and sat in the root
+        let plan = Plan::new(&path.join(&file_name), None, &options);
+        if dry_run {
+            if output::is_json(&options) {
+                output::report(
+                    &options,
+                    Outcome::new(entry.path(), Action::WouldMove)
+                        .to(&plan.target)
+                        .channel(&plan.channel),
+                );
+            } else {
+                println!("{relative} -> {}", plan.target.display());
+            }
+            moved += 1;
+            continue;
+        }
+        if plan.target.exists() {
+            log::warn!(
+                "{} is already taken, leaving {relative}",
+                plan.target.display()
+            );
+            output::report(
+                &options,
+                Outcome::new(entry.path(), Action::Skipped)
+                    .to(&plan.target)
+                    .channel(&plan.channel)
+                    .error("the destination is already taken"),
+            );
+            continue;
+        }
+
+        let storage = options.storage();
+        let result = storage
+            .create_dir_all(plan.target.parent().expect("target has a parent directory"))
+            .and_then(|()| plan.execute(entry.path(), false, storage));
+        match result {
+            Ok(transfer) => {
+                log::info!("Migrating {relative}");
+                land(entry.path(), plan, transfer, &options);
+                moved += 1;
+                if let Some(parent) = entry.path().parent() {
+                    emptied.insert(parent.to_path_buf());
+                }
+            }
+            Err(error) => {
+                log::error!("Failed to migrate {relative}: {error:?}");
+                output::report(
+                    &options,
+                    Outcome::new(entry.path(), Action::Failed)
+                        .to(&plan.target)
+                        .channel(&plan.channel)
+                        .error(error),
+                );
+            }
+        }
+    }
+
+    // deepest first so a date folder goes after the day folders in it
+    for dir in emptied.iter().rev() {
+        remove_empty(dir, path);
+    }
+
+    let summary = format!(
+        "{} {moved} screenshot(s), {unmatched} file(s) didn't match the layout",
+        if dry_run { "Would migrate" } else { "Migrated" }
+    );
+    // stdout is for the json lines
+    if output::is_json(&options) {
+        log::info!("{summary}");
+    } else {
+        println!("{summary}");
+    }
+    Ok(())
+}
+
+/// remove `dir` and its parents up to `root` as long as they're empty
+pub fn remove_empty(dir: &Path, root: &Path) {
+    let mut dir = dir;
+    while dir != root && dir.starts_with(root) && fs::remove_dir(dir).is_ok() {
+        log::debug!("Removed empty folder {}", dir.display());
+        match dir.parent() {
+            Some(parent) => dir = parent,
+            None => break,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Field {
+    Channel,
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    /// an untouched twitch screenshot name
+    Name,
+}
+
+#[derive(Debug)]
+enum Token {
+    Literal(char),
+    /// any of ` _-.`, they're all used to separate parts of a name
+    Separator,
+    Field(Field),
+    /// anything inside one folder or file name
+    Star,
+    /// any number of folders
+    Folders,
+}
+
+fn parse(pattern: &str) -> io::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = pattern;
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("**/") {
+            tokens.push(Token::Folders);
+            rest = after;
+        } else if c == '{' {
+            let (name, after) = rest[1..].split_once('}').ok_or_else(|| invalid(pattern))?;
+            tokens.push(Token::Field(match name {
+                "channel" => Field::Channel,
+                "year" => Field::Year,
+                "month" => Field::Month,
+                "day" => Field::Day,
+                "hour" => Field::Hour,
+                "minute" => Field::Minute,
+                "second" => Field::Second,
+                "name" => Field::Name,
+                _ => return Err(invalid(pattern)),
+            }));
+            rest = after;
+        } else {
+            tokens.push(match c {
+                '*' => Token::Star,
+                ' ' | '_' | '-' | '.' => Token::Separator,
+                c => Token::Literal(c),
+            });
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    let has = |field| {
+        tokens
+            .iter()
+            .any(|token| matches!(token, Token::Field(f) if *f == field))
+    };
+    let dated = [
+        Field::Channel,
+        Field::Year,
+        Field::Month,
+        Field::Day,
+        Field::Hour,
+        Field::Minute,
+    ]
+    .into_iter()
+    .all(has);
+    if !has(Field::Name) && !dated {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{pattern} needs {{name}}, or {{channel}} and the date and time down to the minute"
+            ),
+        ));
+    }
+    Ok(tokens)
+}
+
+fn invalid(pattern: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Can't read layout pattern {pattern}"),
+    )
+}
+
+/// the twitch name the file at `relative` should have, if it fits the pattern
+fn screenshot_name(pattern: &[Token], relative: &str) -> Option<String> {
+    let text = relative.chars().collect::<Vec<_>>();
+    let mut captures = HashMap::new();
+    if !matches(pattern, &text, &mut captures) {
+        return None;
+    }
+
+    if let Some(name) = captures.get(&Field::Name) {
+        return Some(name.clone());
+    }
+    let number = |field| {
+        captures
+            .get(&field)
+            .and_then(|v: &String| v.parse::<u32>().ok())
+    };
+    let month = captures
+        .get(&Field::Month)
+        .and_then(|month| month_number(month))?;
+    let date = NaiveDate::from_ymd_opt(number(Field::Year)? as i32, month, number(Field::Day)?)?;
+    let time = NaiveTime::from_hms_opt(
+        number(Field::Hour)?,
+        number(Field::Minute)?,
+        number(Field::Second).unwrap_or(0),
+    )?;
+    Some(twitch_name(&captures[&Field::Channel], date.and_time(time)))
+}
+
+/// 1, 01 or Jan
+fn month_number(value: &str) -> Option<u32> {
+    value
+        .parse()
+        .ok()
+        .or_else(|| {
+            Month::from_str(value)
+                .ok()
+                .map(|month| month.number_from_month())
+        })
+        .filter(|month| (1..=12).contains(month))
+}
+
+/// channel_Sat-Jan-18-2025_1_06_05-PM.png, the way twitch names screenshots
+fn twitch_name(channel: &str, time: NaiveDateTime) -> String {
+    format!(
+        "{channel}_{}_{}.png",
+        time.format("%a-%b-%d-%Y"),
+        time.format("%-I_%M_%S-%p")
+    )
+}
+
+/// backtracking match of `pattern` against all of `text`, filling in the captured fields
+fn matches(pattern: &[Token], text: &[char], captures: &mut HashMap<Field, String>) -> bool {
+    let Some((token, rest)) = pattern.split_first() else {
+        return text.is_empty();
+    };
+    match token {
+        Token::Literal(c) => {
+            text.first().is_some_and(|t| t.eq_ignore_ascii_case(c))
+                && matches(rest, &text[1..], captures)
+        }
+        Token::Separator => {
+            text.first().is_some_and(|t| " _-.".contains(*t)) && matches(rest, &text[1..], captures)
+        }
+        Token::Star => (0..=segment_len(text)).any(|len| matches(rest, &text[len..], captures)),
+        Token::Folders => {
+            // nothing, or up to and including any later '/'
+            matches(rest, text, captures)
+                || text
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, c)| **c == '/')
+                    .any(|(i, _)| matches(rest, &text[i + 1..], captures))
+        }
+        Token::Field(field) => {
+            for len in 1..=segment_len(text) {
+                let value = text[..len].iter().collect::<String>();
+                if !field.accepts(&value) {
+                    continue;
+                }
+                let previous = captures.insert(*field, value);
+                if matches(rest, &text[len..], captures) {
+                    return true;
+                }
+                match previous {
+                    Some(previous) => captures.insert(*field, previous),
+                    None => captures.remove(field),
+                };
+            }
+            false
+        }
+    }
+}
+
+/// how much of `text` is left before the next '/'
+fn segment_len(text: &[char]) -> usize {
+    text.iter().position(|c| *c == '/').unwrap_or(text.len())
+}
+
+impl Field {
+    fn accepts(&self, value: &str) -> bool {
+        let digits = |min, max| {
+            (min..=max).contains(&value.len()) && value.chars().all(|c| c.is_ascii_digit())
+        };
+        match self {
+            Field::Channel => value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+            Field::Year => digits(4, 4),
+            Field::Month => digits(1, 2) || month_number(value).is_some(),
+            Field::Day | Field::Hour | Field::Minute | Field::Second => digits(1, 2),
+            Field::Name => {
+                value.ends_with(".png")
+                    && capture_time(value).is_some()
+                    && !channel_name(value).is_empty()
+            }
+        }
+    }
+}
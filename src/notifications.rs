@@ -0,0 +1,40 @@
+use notify_rust::Notification;
+use std::fmt::Display;
+use std::path::Path;
+
+const APP_NAME: &str = "Twitch Screenshot Organizer";
+
+/// a single screenshot was organized by the watcher
+pub fn moved(channel: &str, target: &Path) {
+    show(
+        &format!("Saved {channel} screenshot"),
+        &target.display().to_string(),
+    );
+}
+
+/// summary for a batch run, one popup per file would be too much
+pub fn organized(count: usize) {
+    show(
+        "Screenshots organized",
+        &format!("Moved {count} screenshot(s) into channel folders"),
+    );
+}
+
+pub fn failed(path: &Path, error: &dyn Display) {
+    show(
+        "Failed to organize screenshot",
+        &format!("{}: {error}", path.display()),
+    );
+}
+
+/// fire and forget, a missing notification daemon shouldn't get in the way of organizing
+fn show(summary: &str, body: &str) {
+    if let Err(error) = Notification::new()
+        .appname(APP_NAME)
+        .summary(summary)
+        .body(body)
+        .show()
+    {
+        log::warn!("Failed to show notification: {error}");
+    }
+}
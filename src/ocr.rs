@@ -0,0 +1,90 @@
+use std::io;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use crate::{confidence, twitch};
+
+/// what's shown around the stream on twitch.tv, a capture with a couple of these in it is of a
+/// twitch page
+const MARKERS: [&str; 6] = [
+    "Follow",
+    "Subscribe",
+    "LIVE",
+    "STREAM CHAT",
+    "Send",
+    "viewers",
+];
+
+/// Check the `tesseract` command is there, it does the reading
+pub fn check() -> io::Result<()> {
+    run(Command::new("tesseract").arg("--version")).map(|_| ())
+}
+
+/// The channel of a capture of a twitch page that's not named like a screenshot ( PrintScreen,
+/// snipping tools .. ), read off the image: the twitch.tv/<channel> in the address bar, or the
+/// channel name next to the Follow button or one that has a folder already
+pub fn channel_of(path: &Path) -> io::Result<Option<String>> {
+    let output = run(Command::new("tesseract").arg(path).arg("stdout"))?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let channel = from_url(&text).or_else(|| from_page(&text));
+    match &channel {
+        Some(channel) => log::debug!("Read {channel} off {}", path.display()),
+        None => log::debug!("No twitch channel in {}", path.display()),
+    }
+    Ok(channel)
+}
+
+fn from_url(text: &str) -> Option<String> {
+    text.match_indices("twitch.tv/").find_map(|(at, url)| {
+        let login = text[at + url.len()..]
+            .split(|c: char| !is_login_char(c))
+            .next()?;
+        twitch::is_login(login).then(|| login.to_lowercase())
+    })
+}
+
+fn from_page(text: &str) -> Option<String> {
+    let markers = MARKERS
+        .iter()
+        .filter(|marker| text.contains(*marker))
+        .count();
+    if markers < 2 {
+        return None;
+    }
+    // a channel that has a folder already is the safest bet
+    let known = text
+        .split(|c: char| !is_login_char(c))
+        .find(|word| twitch::is_login(word) && confidence::is_known(word));
+    if let Some(known) = known {
+        return Some(known.to_lowercase());
+    }
+    // the name above the stream title sits right before the Follow button
+    text.lines().find_map(|line| {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let follow = words.iter().position(|word| *word == "Follow")?;
+        // OCR makes something of the verified badge between them at times
+        words[..follow]
+            .iter()
+            .rev()
+            .take(2)
+            .find(|word| twitch::is_login(word))
+            .map(|login| login.to_lowercase())
+    })
+}
+
+fn is_login_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn run(command: &mut Command) -> io::Result<Output> {
+    let output = command
+        .output()
+        .map_err(|error| io::Error::new(error.kind(), format!("can't run tesseract: {error}")))?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "tesseract failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output)
+}
@@ -0,0 +1,141 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::{channel_name, is_screenshot, Options};
+
+/// what happened to the files of each channel since the last [take_tally]
+static TALLY: Mutex<BTreeMap<String, Tally>> = Mutex::new(BTreeMap::new());
+
+/// How what happened to each file is reported on stdout
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Only the logs
+    #[default]
+    Text,
+    /// One json object per file with source, destination, channel, action and error
+    Json,
+}
+
+/// What was done with a file
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Moved,
+    Linked,
+    Copied,
+    /// left where it is, already organized or not a screenshot of a twitch channel
+    Skipped,
+    Failed,
+    /// put aside in the quarantine folder
+    Quarantined,
+    /// what a dry run would do
+    WouldMove,
+}
+
+/// One line of `--output json`
+#[derive(Serialize, Debug)]
+pub struct Outcome<'a> {
+    pub source: &'a Path,
+    pub destination: Option<&'a Path>,
+    pub channel: Option<&'a str>,
+    pub action: Action,
+    pub error: Option<String>,
+}
+
+impl<'a> Outcome<'a> {
+    pub fn new(source: &'a Path, action: Action) -> Self {
+        Self {
+            source,
+            destination: None,
+            channel: None,
+            action,
+            error: None,
+        }
+    }
+
+    pub fn to(mut self, destination: &'a Path) -> Self {
+        self.destination = Some(destination);
+        self
+    }
+
+    pub fn channel(mut self, channel: &'a str) -> Self {
+        self.channel = Some(channel);
+        self
+    }
+
+    pub fn error(mut self, error: impl ToString) -> Self {
+        self.error = Some(error.to_string());
+        self
+    }
+}
+
+/// Files of one channel per kind of outcome
+#[derive(Debug, Default)]
+pub struct Tally {
+    pub moved: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+/// Count `outcome` and print it if --output json was asked for
+pub fn report(options: &Options, outcome: Outcome) {
+    count(&outcome);
+    if options.output != Format::Json {
+        return;
+    }
+    let line = serde_json::to_string(&outcome).expect("outcome serializes");
+    // one write per line so lines from the watcher's threads don't interleave
+    let _ = writeln!(io::stdout().lock(), "{line}");
+}
+
+/// Whether the human readable summaries should stay off stdout
+pub fn is_json(options: &Options) -> bool {
+    options.output == Format::Json
+}
+
+/// What happened per channel since the last call
+pub fn take_tally() -> BTreeMap<String, Tally> {
+    std::mem::take(&mut *TALLY.lock().unwrap())
+}
+
+/// Print what a batch run of `total` files did per channel
+pub fn print_summary(total: usize, took: Duration, tally: &BTreeMap<String, Tally>) {
+    println!(
+        "Went through {total} screenshot(s) in {:.1}s",
+        took.as_secs_f64()
+    );
+    let width = tally.keys().map(String::len).max().unwrap_or(0).max(7);
+    println!(
+        "{:<width$}  {:>7}  {:>7}  {:>7}",
+        "channel", "moved", "skipped", "failed"
+    );
+    for (channel, counts) in tally {
+        println!(
+            "{channel:<width$}  {:>7}  {:>7}  {:>7}",
+            counts.moved, counts.skipped, counts.failed
+        );
+    }
+}
+
+fn count(outcome: &Outcome) {
+    // failed moves aren't planned far enough to know the channel
+    let channel = match outcome.channel {
+        Some(channel) => channel.to_string(),
+        None => match outcome.source.file_name() {
+            Some(name) if is_screenshot(outcome.source) => channel_name(&name.to_string_lossy()),
+            _ => "(not a screenshot)".to_string(),
+        },
+    };
+    let mut tally = TALLY.lock().unwrap();
+    let counts = tally.entry(channel).or_default();
+    match outcome.action {
+        Action::Moved | Action::Linked | Action::Copied => counts.moved += 1,
+        Action::Skipped | Action::Quarantined => counts.skipped += 1,
+        Action::Failed => counts.failed += 1,
+        Action::WouldMove => {}
+    }
+}
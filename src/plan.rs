@@ -0,0 +1,526 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::config::FanoutRule;
+use crate::storage::StorageBackend;
+use crate::{
+    capture_time, channel_name, glob, index, iso_name, lock, sharding, sync_tools, unnumbered,
+    Options,
+};
+
+/// folder next to the screenshots everything is organized into
+pub const SAVE_TO: &str = "twitch-screenshots";
+
+/// targets of moves that are planned but not done yet, so two screenshots can't be numbered
+/// into the same spot
+static CLAIMS: Mutex<Claims> = Mutex::new(Claims {
+    held: BTreeMap::new(),
+    released: BTreeMap::new(),
+    releases: 0,
+    checking: 0,
+});
+
+struct Claims {
+    /// target to the source it's held for
+    held: BTreeMap<PathBuf, PathBuf>,
+    /// targets let go of while a claim was looking at storage, by when, the move may have
+    /// landed there after it looked
+    released: BTreeMap<PathBuf, u64>,
+    releases: u64,
+    /// claims looking at storage right now, without holding the lock
+    checking: usize,
+}
+
+/// What happens to one screenshot: where it's moved and which other folders get a link to it
+#[derive(Debug, Clone)]
+pub struct Plan {
+    pub channel: String,
+    /// what the channel was playing, only known for fresh screenshots with twitch set up
+    pub game: Option<String>,
+    pub channel_dir: PathBuf,
+    pub target: PathBuf,
+    /// extra destinations from fan-out rules, linked to `target` so nothing is stored twice
+    pub links: Vec<PathBuf>,
+}
+
+/// A target [claimed](Plan::claim) for a move, let go of when it's dropped: the move is done,
+/// it failed or it never got to run
+#[derive(Debug)]
+pub struct Claim {
+    target: PathBuf,
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        let mut claims = CLAIMS.lock().unwrap();
+        claims.held.remove(&self.target);
+        claims.releases += 1;
+        if claims.checking > 0 {
+            let releases = claims.releases;
+            claims.released.insert(self.target.clone(), releases);
+        }
+    }
+}
+
+/// How a screenshot got to its target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transfer {
+    Moved,
+    Linked,
+    Copied,
+    /// the source had to stay and an identical copy was already at the target
+    AlreadyThere,
+}
+
+impl fmt::Display for Transfer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Transfer::Moved => "moved",
+            Transfer::Linked => "linked",
+            Transfer::Copied => "copied",
+            Transfer::AlreadyThere => "already at",
+        })
+    }
+}
+
+impl Plan {
+    /// [SAVE_TO]/[channel_name]/[filename] plus whatever the fan-out rules add, the filename
+    /// as --rename-to-iso has it if given. `channel` is where it goes instead of the one in the
+    /// name.
+    pub fn new(file_path: &Path, channel: Option<&str>, options: &Options) -> Plan {
+        let parent_dir = file_path.parent().expect("File has no parent directory");
+        let file_name = file_path.file_name().unwrap();
+        let channel = channel.map_or_else(
+            || channel_name(&file_name.to_string_lossy()),
+            str::to_string,
+        );
+        let renamed = options
+            .rename_to_iso
+            .then(|| iso_name(&file_name.to_string_lossy()))
+            .flatten()
+            // the (1) the browser added, the name is only numbered if it's taken where it goes
+            .or_else(|| unnumbered(&file_name.to_string_lossy()))
+            .map(OsString::from)
+            // the index and the journal only take unicode paths, what isn't valid becomes �
+            .or_else(|| {
+                file_name
+                    .to_str()
+                    .is_none()
+                    .then(|| file_name.to_string_lossy().into_owned().into())
+            });
+        let file_name = renamed.as_deref().unwrap_or(file_name);
+        let channel = options
+            .twitch
+            .as_ref()
+            .and_then(|twitch| twitch.display_name(&channel))
+            .unwrap_or(channel);
+        let game = options
+            .twitch
+            .as_ref()
+            .and_then(|twitch| twitch.game(&channel, capture_time(&file_name.to_string_lossy())?));
+
+        let save_to = options.save_dir(parent_dir);
+        let channel_dir = save_to.join(&channel);
+        let target = if sharding::is_sharded(&channel_dir) {
+            sharding::shard_path(&channel_dir, file_name)
+        } else {
+            None
+        }
+        .unwrap_or_else(|| channel_dir.join(file_name));
+
+        let links = options
+            .config
+            .fanout
+            .iter()
+            .filter(|rule| rule.applies_to(&channel, &file_name.to_string_lossy()))
+            .flat_map(|rule| &rule.to)
+            .filter_map(|dir| {
+                let dir = dir.replace("{channel}", &channel);
+                let dir = match &game {
+                    Some(game) => dir.replace("{game}", &folder_name(game)),
+                    None if dir.contains("{game}") => return None,
+                    None => dir,
+                };
+                Some(save_to.join(dir).join(file_name))
+            })
+            .filter(|link| *link != target)
+            .collect();
+
+        Plan {
+            channel,
+            game,
+            channel_dir,
+            target,
+            links,
+        }
+    }
+
+    /// The same plan for the screenshot once it was converted to a file with `extension`
+    pub fn converted(&self, extension: &str) -> Plan {
+        Plan {
+            target: self.target.with_extension(extension),
+            links: self
+                .links
+                .iter()
+                .map(|link| link.with_extension(extension))
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Number the target `-2`, `-3` .. when a different screenshot has its name already, or is
+    /// about to get it from another move. It's left as it is when it's `source` that's there (
+    /// organized before, or copied out of a read-only folder ). The target is held for `source`
+    /// as long as the returned [Claim] is around.
+    pub fn claim(
+        mut self,
+        source: &Path,
+        storage: &dyn StorageBackend,
+    ) -> io::Result<(Plan, Claim)> {
+        let file_name = self
+            .target
+            .file_name()
+            .expect("target has a name")
+            .to_string_lossy()
+            .into_owned();
+        let (stem, extension) = file_name.rsplit_once('.').unwrap_or((&file_name, ""));
+        let mut number = 1;
+        loop {
+            let looked_at = {
+                let mut claims = CLAIMS.lock().unwrap();
+                claims.checking += 1;
+                claims.releases
+            };
+            // a remote or a big file to hash takes a while, other moves aren't held up by it
+            let differs = match storage.stat(&self.target) {
+                Ok(Some(existing)) => differs(&self.target, existing.len, source, storage),
+                Ok(None) => Ok(false),
+                Err(error) => Err(error),
+            };
+            let mut claims = CLAIMS.lock().unwrap();
+            claims.checking -= 1;
+            let landed = claims
+                .released
+                .get(&self.target)
+                .is_some_and(|released| *released > looked_at);
+            if claims.checking == 0 {
+                claims.released.clear();
+            }
+            let differs = differs?;
+            let taken = match claims.held.get(&self.target) {
+                Some(other) => other != source,
+                // another move put a screenshot there since it was looked at
+                None if landed => continue,
+                None => differs,
+            };
+            if !taken {
+                claims
+                    .held
+                    .insert(self.target.clone(), source.to_path_buf());
+                break;
+            }
+            drop(claims);
+            number += 1;
+            let numbered = format!("{stem}-{number}.{extension}");
+            self.target.set_file_name(&numbered);
+            for link in &mut self.links {
+                link.set_file_name(&numbered);
+            }
+        }
+        if number > 1 {
+            log::debug!("{file_name} is taken, numbering it {number}");
+        }
+        let claim = Claim {
+            target: self.target.clone(),
+        };
+        Ok((self, claim))
+    }
+
+    /// Put `source` at the target in `storage`. Normally it's moved, but when the source has to
+    /// be left alone ( `keep_source`, or the move turns out to be refused ) it's linked or copied.
+    pub fn execute(
+        &self,
+        source: &Path,
+        keep_source: bool,
+        storage: &dyn StorageBackend,
+    ) -> io::Result<Transfer> {
+        // whatever the sync tool is bringing in would end up on top of the screenshot, or the
+        // other way around. It's left where it is and tried again next time.
+        sync_tools::check_incoming(&self.target)?;
+
+        // another machine organizing into the same place could be putting a screenshot with the
+        // same name there right now, one of them would be lost
+        let save_dir = self
+            .channel_dir
+            .parent()
+            .expect("channel dir is in the save dir");
+        let lock = lock::acquire(save_dir)?;
+        let shared = lock.is_some();
+        if shared && !keep_source && storage.stat(&self.target)?.is_some() {
+            return Err(self.taken());
+        }
+
+        if !keep_source {
+            match storage.move_in(source, &self.target) {
+                Err(error) if is_read_only(&error) => log::warn!(
+                    "Can't move {} out of its folder ( {error} ), copying it instead",
+                    source.display()
+                ),
+                result => return result.map(|()| Transfer::Moved),
+            }
+        }
+
+        // the source stays around, so the same file shows up again on every run
+        let size = source.metadata()?.len();
+        let existing = storage.stat(&self.target)?;
+        if existing.is_some_and(|target| target.len == size) {
+            return Ok(Transfer::AlreadyThere);
+        }
+        if shared && existing.is_some() {
+            return Err(self.taken());
+        }
+        storage.copy_in(source, &self.target)
+    }
+
+    fn taken(&self) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!(
+                "{} is already there, another instance may have put it there",
+                self.target.display()
+            ),
+        )
+    }
+
+    /// Link every fan-out destination to the moved file, each one on its own so one bad
+    /// destination doesn't take the others down
+    pub fn create_links(&self) {
+        for link in &self.links {
+            match link_file(&self.target, link) {
+                Ok(()) => log::info!("Linked to: {}", link.display()),
+                Err(error) => log::error!("Failed to link {}: {error:?}", link.display()),
+            }
+        }
+    }
+}
+
+impl FanoutRule {
+    fn applies_to(&self, channel: &str, file_name: &str) -> bool {
+        glob::matches(&self.channel, channel) && glob::matches(&self.name, file_name)
+    }
+}
+
+/// hard link if possible, symlink when the destination is on another filesystem, copy as the
+/// last resort
+fn link_file(original: &Path, link: &Path) -> io::Result<()> {
+    fs::create_dir_all(link.parent().expect("link has a parent directory"))?;
+    if fs::hard_link(original, link).is_ok() {
+        return Ok(());
+    }
+    #[cfg(unix)]
+    if std::os::unix::fs::symlink(original, link).is_ok() {
+        return Ok(());
+    }
+    #[cfg(windows)]
+    if std::os::windows::fs::symlink_file(original, link).is_ok() {
+        return Ok(());
+    }
+    sync_tools::copy(original, link)
+}
+
+/// whether the file of `len` bytes at `target` in `storage` isn't the same as `source`
+fn differs(
+    target: &Path,
+    len: u64,
+    source: &Path,
+    storage: &dyn StorageBackend,
+) -> io::Result<bool> {
+    if source.metadata()?.len() != len {
+        return Ok(true);
+    }
+    // a remote without hashes can't tell, it's numbered rather than overwritten
+    match storage.hash(target)? {
+        Some(hash) => Ok(hash != index::hash_file(source)?),
+        None => Ok(true),
+    }
+}
+
+/// errors a rename gets when the source folder can't be changed
+pub fn is_read_only(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem
+    )
+}
+
+/// errors a move gets while something else ( the browser, an antivirus scan ) still has the file
+/// open, they go away on their own
+pub fn is_locked(error: &io::Error) -> bool {
+    // ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION
+    (cfg!(windows) && matches!(error.raw_os_error(), Some(32) | Some(33)))
+        || error.kind() == io::ErrorKind::ResourceBusy
+}
+
+/// Can files be removed from `dir`, checked by actually creating and deleting one since
+/// permission bits don't tell about read-only mounts or ACLs
+pub fn is_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!(
+        ".twitch-screenshot-organizer-{}",
+        std::process::id()
+    ));
+    match fs::File::create(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            true
+        }
+        Err(error) => !is_read_only(&error),
+    }
+}
+
+/// `game` with what can't be in a file name replaced, "Warhammer 40,000: Darktide" and such
+fn folder_name(game: &str) -> String {
+    game.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c => c,
+        })
+        .collect::<String>()
+        .trim_end_matches(['.', ' '])
+        .to_string()
+}
+
+/// whether `name` can be a folder as it is on every system, without [folder_name] changing it (
+/// `.` and `..` can't either )
+pub fn is_folder_name(name: &str) -> bool {
+    !name.is_empty() && folder_name(name) == name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Memory;
+    use tempfile::TempDir;
+
+    /// a local screenshot to organize in a folder that's removed with the guard, the
+    /// destination is only in memory
+    fn source(contents: &[u8]) -> (TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chan_Sat-Jan-18-2025_1_06_05-PM.png");
+        fs::write(&path, contents).unwrap();
+        (dir, path)
+    }
+
+    fn plan(test: &str) -> Plan {
+        let channel_dir = Path::new("/memory").join(test).join(SAVE_TO).join("chan");
+        Plan {
+            channel: "chan".to_string(),
+            game: None,
+            target: channel_dir.join("chan_Sat-Jan-18-2025_1_06_05-PM.png"),
+            channel_dir,
+            links: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn taken_names_are_numbered() {
+        let taken = plan("numbered").target;
+        let storage = Memory::default().with_file(&taken, b"another screenshot");
+        let (_first_dir, first) = source(b"first");
+        let (_second_dir, second) = source(b"second");
+
+        let (first_plan, _first_claim) = plan("numbered").claim(&first, &storage).unwrap();
+        assert_eq!(
+            first_plan.target,
+            taken.with_file_name("chan_Sat-Jan-18-2025_1_06_05-PM-2.png")
+        );
+        // -2 is held for the first one until it's moved
+        let (second_plan, _second_claim) = plan("numbered").claim(&second, &storage).unwrap();
+        assert_eq!(
+            second_plan.target,
+            taken.with_file_name("chan_Sat-Jan-18-2025_1_06_05-PM-3.png")
+        );
+
+        assert_eq!(
+            first_plan.execute(&first, false, &storage).unwrap(),
+            Transfer::Moved
+        );
+        assert!(!first.exists());
+        assert_eq!(storage.contents(&first_plan.target).unwrap(), b"first");
+        assert_eq!(storage.contents(&taken).unwrap(), b"another screenshot");
+    }
+
+    #[test]
+    fn same_size_screenshots_are_told_apart() {
+        let taken = plan("same-size").target;
+        let storage = Memory::default().with_file(&taken, b"one");
+        let (_source_dir, source) = source(b"two");
+
+        let (plan, _claim) = plan("same-size").claim(&source, &storage).unwrap();
+        assert_eq!(
+            plan.target,
+            taken.with_file_name("chan_Sat-Jan-18-2025_1_06_05-PM-2.png")
+        );
+    }
+
+    #[test]
+    fn released_claims_are_free_again() {
+        let (_source_dir, source) = source(b"screenshot");
+        let storage = Memory::default();
+        let (first, claim) = plan("released").claim(&source, &storage).unwrap();
+        drop(claim);
+        let other = source.with_file_name("other.png");
+        fs::write(&other, b"other").unwrap();
+        let (second, _claim) = plan("released").claim(&other, &storage).unwrap();
+        assert_eq!(first.target, second.target);
+    }
+
+    #[test]
+    fn the_same_screenshot_is_already_there() {
+        let (_source_dir, source) = source(b"screenshot");
+        let storage = Memory::default().with_file(&plan("already-there").target, b"screenshot");
+
+        let (plan, _claim) = plan("already-there").claim(&source, &storage).unwrap();
+        assert_eq!(
+            plan.target.file_name().unwrap(),
+            source.file_name().unwrap()
+        );
+        assert_eq!(
+            plan.execute(&source, true, &storage).unwrap(),
+            Transfer::AlreadyThere
+        );
+        assert!(source.exists());
+    }
+
+    #[test]
+    fn read_only_sources_are_copied() {
+        let (_source_dir, source) = source(b"screenshot");
+        let storage = Memory {
+            read_only_sources: true,
+            ..Memory::default()
+        };
+
+        let (plan, _claim) = plan("read-only").claim(&source, &storage).unwrap();
+        assert_eq!(
+            plan.execute(&source, false, &storage).unwrap(),
+            Transfer::Copied
+        );
+        assert!(source.exists());
+        assert_eq!(storage.contents(&plan.target).unwrap(), b"screenshot");
+    }
+
+    #[test]
+    fn folder_names() {
+        assert!(is_folder_name("chan"));
+        assert!(is_folder_name(".chan"));
+        for name in [
+            "", ".", "..", "a/b", "a\\b", "con:", "what?", "chan.", "chan ",
+        ] {
+            assert!(!is_folder_name(name), "{name:?}");
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use indicatif::{ProgressBar, ProgressState, ProgressStyle};
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+
+/// batches smaller than this are over before a bar would help
+pub const THRESHOLD: usize = 100;
+
+/// the bar on screen, log lines are written above it
+static BAR: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+/// A progress bar on stderr, cleared when dropped
+pub struct Progress(ProgressBar);
+
+impl Progress {
+    /// A bar for `total` files, none if stderr isn't a terminal anyone is looking at
+    pub fn start(total: usize) -> Option<Self> {
+        if !io::stderr().is_terminal() {
+            return None;
+        }
+        let bar = ProgressBar::new(total as u64).with_style(
+            ProgressStyle::with_template(
+                "{bar:40.cyan/blue} {pos}/{len} screenshots, {rate}/s, {eta} left",
+            )
+            .expect("valid template")
+            .with_key("rate", |state: &ProgressState, w: &mut dyn fmt::Write| {
+                let _ = write!(w, "{:.0}", state.per_sec());
+            })
+            .progress_chars("=> "),
+        );
+        *BAR.lock().unwrap() = Some(bar.clone());
+        Some(Self(bar))
+    }
+
+    pub fn inc(&self) {
+        self.0.inc(1);
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        BAR.lock().unwrap().take();
+        self.0.finish_and_clear();
+    }
+}
+
+/// Stderr for the logger, moving the bar out of the way of every line
+pub struct Stderr;
+
+impl Write for Stderr {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let bar = BAR.lock().unwrap().clone();
+        match bar {
+            Some(bar) => bar.suspend(|| io::stderr().write(buf)),
+            None => io::stderr().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
@@ -0,0 +1,200 @@
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::config::Quarantine;
+use crate::output::{self, Action, Outcome};
+use crate::{journal, plan, strip_extension, sync_tools, Options};
+
+/// default [Quarantine::dir], sorts before the channel folders
+pub const UNSORTED: &str = "_unsorted";
+
+/// failed moves per file so far, loaded from [failures_path] the first time it's needed
+static FAILURES: Mutex<Option<BTreeMap<PathBuf, u32>>> = Mutex::new(None);
+
+/// Put `path` aside if it's named almost like a twitch screenshot ( cut off by the browser,
+/// renamed by hand .. ) and the config doesn't say otherwise
+pub fn near_miss(path: &Path, options: &Options) {
+    if options.config.quarantine.near_misses && is_near_miss(path) {
+        put_aside(path, "it's named almost like a twitch screenshot", options);
+    }
+}
+
+/// Count a failed move of `path`, putting it aside once it failed too often
+pub fn failed(path: &Path, error: &io::Error, options: &Options) {
+    let after_failures = options.config.quarantine.after_failures;
+    // gone already, nothing to put aside
+    if after_failures == 0 || error.kind() == io::ErrorKind::NotFound {
+        return;
+    }
+    // it was tried again and again already
+    if plan::is_locked(error) {
+        put_aside(
+            path,
+            &format!("it was still in use after every attempt: {error}"),
+            options,
+        );
+        return;
+    }
+    let failures = match count(path) {
+        Ok(failures) => failures,
+        Err(error) => {
+            log::error!("Failed to keep count of failed moves: {error:?}");
+            return;
+        }
+    };
+    if failures >= after_failures {
+        put_aside(
+            path,
+            &format!("moving it failed {failures} times, the last time with: {error}"),
+            options,
+        );
+    }
+}
+
+/// `path` was organized or put aside, stop counting its failures
+pub fn forget(path: &Path) {
+    let mut failures = FAILURES.lock().unwrap();
+    let failures = match failures.as_mut() {
+        Some(failures) => failures,
+        None => match load() {
+            Ok(loaded) => failures.insert(loaded),
+            Err(_) => return,
+        },
+    };
+    if failures.remove(&key(path)).is_some() {
+        if let Err(error) = save(failures) {
+            log::error!("Failed to keep count of failed moves: {error:?}");
+        }
+    }
+}
+
+/// Move `path` into the quarantine folder with a `<name>.reason.txt` next to it
+pub fn put_aside(path: &Path, reason: &str, options: &Options) {
+    if options.read_only_source {
+        log::warn!("Leaving {}, {reason}", path.display());
+        return;
+    }
+    match move_aside(path, reason, options) {
+        Ok(target) => {
+            log::warn!(
+                "Put {} aside in {}, {reason}",
+                path.display(),
+                target.display()
+            );
+            forget(path);
+            if options.storage.is_none() {
+                journal::moved(path, &target);
+            }
+            output::report(
+                options,
+                Outcome::new(path, Action::Quarantined)
+                    .to(&target)
+                    .error(reason),
+            );
+        }
+        Err(error) => log::error!(
+            "Failed to put {} aside ( {reason} ): {error:?}",
+            path.display()
+        ),
+    }
+}
+
+/// Whether `path` has a twitch date like Sat-Jan-18-2025 in its name, but not the rest of a
+/// screenshot name
+pub fn is_near_miss(path: &Path) -> bool {
+    let Some(file_name) = path.file_name().map(|name| name.to_string_lossy()) else {
+        return false;
+    };
+    if sync_tools::is_temp(&file_name) {
+        return false;
+    }
+    let Some(stem) = strip_extension(&file_name) else {
+        return false;
+    };
+    stem.split(['_', ' '])
+        .any(|part| NaiveDate::parse_from_str(part, "%a-%b-%d-%Y").is_ok())
+}
+
+fn move_aside(path: &Path, reason: &str, options: &Options) -> io::Result<PathBuf> {
+    let parent = path.parent().expect("file has a parent directory");
+    let dir = dir(&options.save_dir(parent), &options.config.quarantine);
+    let storage = options.storage();
+    storage.create_dir_all(&dir)?;
+
+    // never on top of something put aside before
+    let file_name = path.file_name().expect("file has a name");
+    let mut target = dir.join(file_name);
+    let mut n = 1;
+    while storage.stat(&target)?.is_some() {
+        let (stem, extension) = file_name
+            .to_string_lossy()
+            .rsplit_once('.')
+            .map(|(stem, extension)| (stem.to_string(), format!(".{extension}")))
+            .unwrap_or_else(|| (file_name.to_string_lossy().into_owned(), String::new()));
+        target = dir.join(format!("{stem} ({n}){extension}"));
+        n += 1;
+    }
+
+    storage.move_in(path, &target)?;
+    let mut reason_file = target.clone().into_os_string();
+    reason_file.push(".reason.txt");
+    let reason = format!("{}: {reason}\n", path.display());
+    if let Err(error) = storage.write(Path::new(&reason_file), reason.as_bytes()) {
+        log::error!(
+            "Failed to write why {} was put aside: {error:?}",
+            target.display()
+        );
+    }
+    Ok(target)
+}
+
+fn dir(save_dir: &Path, quarantine: &Quarantine) -> PathBuf {
+    match &quarantine.dir {
+        Some(dir) => save_dir.join(dir),
+        None => save_dir.join(UNSORTED),
+    }
+}
+
+/// one more failure of `path`, returns how many there were
+fn count(path: &Path) -> io::Result<u32> {
+    let mut failures = FAILURES.lock().unwrap();
+    let failures = match failures.as_mut() {
+        Some(failures) => failures,
+        None => failures.insert(load()?),
+    };
+    let count = failures.entry(key(path)).or_default();
+    *count += 1;
+    let count = *count;
+    save(failures)?;
+    Ok(count)
+}
+
+/// the file may be gone already, only its folder is resolved
+fn key(path: &Path) -> PathBuf {
+    match (path.parent().map(Path::canonicalize), path.file_name()) {
+        (Some(Ok(parent)), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn load() -> io::Result<BTreeMap<PathBuf, u32>> {
+    match fs::read(failures_path()) {
+        Ok(json) => Ok(serde_json::from_slice(&json).unwrap_or_default()),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(error) => Err(error),
+    }
+}
+
+fn save(failures: &BTreeMap<PathBuf, u32>) -> io::Result<()> {
+    let path = failures_path();
+    fs::create_dir_all(path.parent().expect("state dir"))?;
+    fs::write(path, serde_json::to_vec(failures)?)
+}
+
+fn failures_path() -> PathBuf {
+    crate::state_dir().join("failures.json")
+}
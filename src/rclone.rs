@@ -0,0 +1,203 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use crate::plan::Transfer;
+use crate::storage::{Stat, StorageBackend};
+
+/// `--dest rclone:<remote>:<path>` puts screenshots on an rclone remote
+pub const PREFIX: &str = "rclone:";
+
+/// rclone's exit codes for a directory or file that isn't there
+const NOT_FOUND: [i32; 2] = [3, 4];
+
+/// A remote set up in rclone, everything goes through the `rclone` command so its config and
+/// credentials are used as they are
+#[derive(Debug)]
+pub struct Rclone {
+    /// the --dest this stands for, paths below it are paths on the remote
+    dest: PathBuf,
+    /// `remote:path` as rclone takes it
+    remote: String,
+}
+
+/// What `rclone lsjson --stat` says about a file
+#[derive(Deserialize)]
+struct Entry {
+    #[serde(rename = "Size")]
+    size: i64,
+    /// with --hash, the ones the remote keeps
+    #[serde(rename = "Hashes", default)]
+    hashes: HashMap<String, String>,
+}
+
+impl Rclone {
+    /// The remote for `dest` if it's an rclone one, checking rclone is there and knows it
+    pub fn for_dest(dest: &Path) -> io::Result<Option<Rclone>> {
+        let Some(remote) = dest.to_str().and_then(|dest| dest.strip_prefix(PREFIX)) else {
+            return Ok(None);
+        };
+        let Some((name, _)) = remote.split_once(':') else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} isn't {PREFIX}<remote>:<path>", dest.display()),
+            ));
+        };
+
+        // a remote like :s3,provider=..: is set up right there, not in the config
+        let remotes = run(Command::new("rclone").arg("listremotes"))?;
+        let known = String::from_utf8_lossy(&remotes.stdout)
+            .lines()
+            .any(|line| line.trim().strip_suffix(':') == Some(name));
+        if !name.is_empty() && !known {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("rclone has no remote named {name}, set it up with `rclone config`"),
+            ));
+        }
+
+        Ok(Some(Rclone {
+            dest: dest.to_path_buf(),
+            remote: remote.to_string(),
+        }))
+    }
+
+    /// `remote:path` as rclone takes it
+    pub fn remote(&self) -> &str {
+        &self.remote
+    }
+
+    /// `remote:path/...` for a path under the dest
+    fn remote_path(&self, path: &Path) -> io::Result<String> {
+        let relative = path.strip_prefix(&self.dest).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not in {}", path.display(), self.dest.display()),
+            )
+        })?;
+        let mut remote = self.remote.clone();
+        for component in relative.components() {
+            if !remote.ends_with([':', '/']) {
+                remote.push('/');
+            }
+            remote.push_str(&component.as_os_str().to_string_lossy());
+        }
+        Ok(remote)
+    }
+
+    /// `rclone lsjson --stat` of the file at `path`, none when there's nothing there
+    fn entry(&self, path: &Path, args: &[&str]) -> io::Result<Option<Entry>> {
+        let output = Command::new("rclone")
+            .args(["lsjson", "--stat", "--files-only"])
+            .args(args)
+            .arg(self.remote_path(path)?)
+            .output()
+            .map_err(cant_run)?;
+        if output
+            .status
+            .code()
+            .is_some_and(|code| NOT_FOUND.contains(&code))
+        {
+            return Ok(None);
+        }
+        let output = check(output)?;
+        Ok(Some(serde_json::from_slice(&output.stdout)?))
+    }
+
+    fn rclone(&self, args: &[&str], paths: &[&Path]) -> io::Result<Output> {
+        let mut command = Command::new("rclone");
+        command.args(args);
+        for path in paths {
+            if path.starts_with(&self.dest) {
+                command.arg(self.remote_path(path)?);
+            } else {
+                command.arg(path);
+            }
+        }
+        run(&mut command)
+    }
+}
+
+impl StorageBackend for Rclone {
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()> {
+        self.rclone(&["mkdir"], &[dir]).map(drop)
+    }
+
+    /// rcat only makes the file show up once it's all there
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let mut child = Command::new("rclone")
+            .arg("rcat")
+            .arg(self.remote_path(path)?)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(cant_run)?;
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(contents)?;
+        check(child.wait_with_output()?).map(drop)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.rclone(&["moveto"], &[from, to]).map(drop)
+    }
+
+    fn delete(&self, path: &Path) -> io::Result<()> {
+        self.rclone(&["deletefile"], &[path]).map(drop)
+    }
+
+    fn stat(&self, path: &Path) -> io::Result<Option<Stat>> {
+        Ok(self.entry(path, &[])?.map(|entry| Stat {
+            len: entry.size.max(0) as u64,
+        }))
+    }
+
+    /// from the remote when it keeps sha256 hashes, otherwise the file is downloaded to hash it,
+    /// screenshots are small
+    fn hash(&self, path: &Path) -> io::Result<Option<String>> {
+        let Some(mut entry) = self.entry(path, &["--hash", "--hash-type", "sha256"])? else {
+            return Ok(None);
+        };
+        if let Some(hash) = entry.hashes.remove("sha256") {
+            return Ok(Some(hash));
+        }
+        let output = self.rclone(&["hashsum", "sha256", "--download"], &[path])?;
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .next()
+            .map(str::to_string))
+    }
+
+    fn move_in(&self, source: &Path, target: &Path) -> io::Result<()> {
+        self.rclone(&["moveto"], &[source, target]).map(drop)
+    }
+
+    /// nothing can be linked across to a remote
+    fn copy_in(&self, source: &Path, target: &Path) -> io::Result<Transfer> {
+        self.rclone(&["copyto"], &[source, target])
+            .map(|_| Transfer::Copied)
+    }
+}
+
+fn run(command: &mut Command) -> io::Result<Output> {
+    check(command.output().map_err(cant_run)?)
+}
+
+fn check(output: Output) -> io::Result<Output> {
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "rclone failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output)
+}
+
+fn cant_run(error: io::Error) -> io::Error {
+    io::Error::new(error.kind(), format!("can't run rclone: {error}"))
+}
@@ -0,0 +1,160 @@
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::animated;
+
+/// how often to check again while the file is still open
+const POLL: Duration = Duration::from_millis(500);
+
+/// give up waiting after this and try to move anyway, something may just keep it open forever
+const MAX_WAIT: Duration = Duration::from_secs(5 * 60);
+
+/// files modified longer ago than this are assumed to be done, checking every file of a big
+/// folder for open handles would be way too slow
+const RECENT: Duration = Duration::from_secs(60);
+
+/// how long a file has to stay the same size to count as written, when open handles can't be
+/// checked. 2s is usually enough for a screenshot.
+const SETTLE: Duration = Duration::from_secs(2);
+
+/// animated captures are bigger and encoders write them out in bursts
+const SETTLE_ANIMATED: Duration = Duration::from_secs(5);
+
+/// Where waiting for a file to be written out is at, checked a step at a time so nothing has to
+/// block while it waits
+pub struct Wait {
+    started: Instant,
+    /// the size at the last check, once it's clear open handles can't be checked here
+    size: Option<Option<u64>>,
+}
+
+impl Wait {
+    pub fn start() -> Self {
+        Self {
+            started: Instant::now(),
+            size: None,
+        }
+    }
+
+    /// None once `path` is done being written, otherwise how long until it should be checked
+    /// again. Once nothing holds it open anymore, or it stopped growing on platforms that can't
+    /// tell.
+    pub fn check(&mut self, path: &Path) -> Option<Duration> {
+        if self.started.elapsed() >= MAX_WAIT {
+            log::warn!(
+                "{} is still being written after {:?}, moving it anyway",
+                path.display(),
+                MAX_WAIT
+            );
+            return None;
+        }
+        if self.size.is_none() {
+            match in_use(path) {
+                Some(false) => return None,
+                Some(true) => {
+                    log::debug!("{} is still open, deferring", path.display());
+                    return Some(POLL);
+                }
+                None => {}
+            }
+        }
+        let quiet = if animated::is_animated(path) {
+            SETTLE_ANIMATED
+        } else {
+            SETTLE
+        };
+        let now = len(path);
+        match self.size.replace(now) {
+            Some(size) if size == now => None,
+            Some(_) => {
+                log::debug!("{} is still growing, deferring", path.display());
+                Some(quiet)
+            }
+            // can't tell on this platform, it's done once it stops growing
+            None => Some(quiet),
+        }
+    }
+}
+
+/// Block until `path` is done being written, see [Wait::check]
+pub fn wait(path: &Path) {
+    let mut wait = Wait::start();
+    while let Some(again) = wait.check(path) {
+        thread::sleep(again);
+    }
+}
+
+fn len(path: &Path) -> Option<u64> {
+    path.metadata().map(|metadata| metadata.len()).ok()
+}
+
+/// was the file written to recently enough that something might still hold it
+pub fn recently_modified(path: &Path) -> bool {
+    age(path).is_none_or(|age| age < RECENT)
+}
+
+/// How long ago a file or folder was last changed, none if that can't be told
+pub fn age(path: &Path) -> Option<Duration> {
+    path.metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+}
+
+/// any process other than us with an open fd pointing at the file
+#[cfg(target_os = "linux")]
+fn in_use(path: &Path) -> Option<bool> {
+    let target = path.canonicalize().ok()?;
+    let own_pid = std::process::id().to_string();
+
+    let procs = std::fs::read_dir("/proc").ok()?;
+    let open = procs
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.bytes().all(|b| b.is_ascii_digit()) && name != own_pid
+        })
+        // processes of other users aren't readable, those are not writing our downloads anyway
+        .filter_map(|entry| std::fs::read_dir(entry.path().join("fd")).ok())
+        .flat_map(|fds| fds.filter_map(Result::ok))
+        .any(|fd| std::fs::read_link(fd.path()).is_ok_and(|link| link == target));
+
+    Some(open)
+}
+
+/// lsof exits with 0 when some process has the file open
+#[cfg(all(unix, not(target_os = "linux")))]
+fn in_use(path: &Path) -> Option<bool> {
+    let status = std::process::Command::new("lsof")
+        .arg("-t")
+        .arg("--")
+        .arg(path)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .ok()?;
+    Some(status.success())
+}
+
+/// an open without any sharing fails with a sharing violation while someone else has it open
+#[cfg(windows)]
+fn in_use(path: &Path) -> Option<bool> {
+    use std::os::windows::fs::OpenOptionsExt;
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+
+    match std::fs::OpenOptions::new()
+        .read(true)
+        .share_mode(0)
+        .open(path)
+    {
+        Ok(_) => Some(false),
+        Err(error) => Some(error.raw_os_error() == Some(ERROR_SHARING_VIOLATION)),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn in_use(_path: &Path) -> Option<bool> {
+    None
+}
@@ -0,0 +1,171 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::index::Index;
+use crate::progress::{self, Progress};
+use crate::{
+    channel_name, collection, is_screenshot, journal, same_channel, sharding, sidecar,
+    strip_extension, sync_tools, thumbnails,
+};
+
+/// Bring the index of the tree organized into `dest` up to date with what's in it: screenshots
+/// that are new or changed are indexed and hashed again, the ones that are gone are forgotten.
+/// Screenshots in the wrong channel folder are listed, with `fix` they're moved to their own.
+pub fn run(dest: &Path, fix: bool) -> io::Result<()> {
+    let save_dir = collection::save_dir(dest).canonicalize()?;
+    let index = Index::open(&save_dir)?;
+    let indexed = index.sizes()?;
+    let (placed, misplaced) = scan(&save_dir);
+
+    let (mut added, mut changed) = (0, 0);
+    let progress = (placed.len() >= progress::THRESHOLD)
+        .then(|| Progress::start(placed.len()))
+        .flatten();
+    for (path, channel) in &placed {
+        let size = path.metadata().map(|metadata| metadata.len()).ok();
+        let known = indexed.get(path);
+        if known.is_none() || known.copied() != size {
+            match index.record(channel, path) {
+                Ok(()) if known.is_none() => added += 1,
+                Ok(()) => changed += 1,
+                Err(error) => log::error!("Failed to index {}: {error:?}", path.display()),
+            }
+        }
+        if let Some(progress) = &progress {
+            progress.inc();
+        }
+    }
+    drop(progress);
+
+    let present = placed.iter().map(|(path, _)| path).collect::<HashSet<_>>();
+    let mut forgotten = 0;
+    for path in indexed.keys().filter(|path| !present.contains(path)) {
+        match index.remove(path) {
+            Ok(()) => forgotten += 1,
+            Err(error) => log::error!("Failed to forget {}: {error:?}", path.display()),
+        }
+    }
+    println!(
+        "{}: indexed {added} new and {changed} changed screenshot(s), forgot {forgotten} that are gone",
+        save_dir.display()
+    );
+
+    if misplaced.is_empty() {
+        return Ok(());
+    }
+    if !fix {
+        for (path, channel) in &misplaced {
+            println!("{} belongs in the folder of {channel}", path.display());
+        }
+        println!(
+            "{} screenshot(s) are in the wrong place, run again with --fix to move them to their channel's folder",
+            misplaced.len()
+        );
+        return Ok(());
+    }
+    let mut moved = 0;
+    for (path, channel) in &misplaced {
+        match put_back(&save_dir, path, channel, &index) {
+            Ok(target) => {
+                log::info!("Moved {} to {}", path.display(), target.display());
+                moved += 1;
+            }
+            Err(error) => log::error!("Failed to move {}: {error}", path.display()),
+        }
+    }
+    println!(
+        "Moved {moved} of {} misplaced screenshot(s) to their channel's folder",
+        misplaced.len()
+    );
+    Ok(())
+}
+
+/// a screenshot in the organized tree and the channel it's of
+type Found = (PathBuf, String);
+
+/// the screenshots under `save_dir` in their channel's folder and the ones elsewhere
+fn scan(save_dir: &Path) -> (Vec<Found>, Vec<Found>) {
+    let (placed, misplaced): (Vec<_>, Vec<_>) = WalkDir::new(save_dir)
+        .min_depth(1)
+        .into_iter()
+        // the index, thumbnails and the like
+        .filter_entry(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy();
+            let relative = entry.path().strip_prefix(save_dir).ok()?;
+            let mut components = relative.components();
+            let folder = match (components.next(), components.next()) {
+                (Some(Component::Normal(top)), Some(_)) => Some(top.to_string_lossy()),
+                _ => None,
+            };
+            if !is_screenshot(entry.path()) {
+                // organized by OCR or a decision, it's the folder that's named after the channel
+                if strip_extension(&file_name).is_none() || sync_tools::is_temp(&file_name) {
+                    return None;
+                }
+                let channel = folder?.into_owned();
+                return Some((true, (entry.into_path(), channel)));
+            }
+            let channel = channel_name(&file_name);
+            // the folder may be named the way the streamer capitalizes their name
+            let in_place = folder.is_some_and(|top| same_channel(&top, &channel));
+            Some((in_place, (entry.into_path(), channel)))
+        })
+        .partition(|(in_place, _)| *in_place);
+    let placed = placed
+        .into_iter()
+        .map(|(_, found)| found)
+        .collect::<Vec<_>>();
+
+    // fan-out links of a screenshot in its own folder, not misplaced copies
+    let names = placed
+        .iter()
+        .filter_map(|(path, _)| path.file_name())
+        .collect::<HashSet<_>>();
+    let misplaced = misplaced
+        .into_iter()
+        .map(|(_, found)| found)
+        .filter(|(path, _)| path.file_name().is_some_and(|name| !names.contains(name)))
+        .collect();
+    (placed, misplaced)
+}
+
+/// move a misplaced screenshot into the folder of `channel`, returns where it ended up
+fn put_back(save_dir: &Path, path: &Path, channel: &str, index: &Index) -> io::Result<PathBuf> {
+    let channel_dir = fs::read_dir(save_dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .find(|dir| {
+            dir.is_dir()
+                && dir
+                    .file_name()
+                    .is_some_and(|name| same_channel(&name.to_string_lossy(), channel))
+        })
+        .unwrap_or_else(|| save_dir.join(channel));
+    let file_name = path.file_name().expect("screenshot has a name");
+    let target = if sharding::is_sharded(&channel_dir) {
+        sharding::shard_path(&channel_dir, file_name)
+    } else {
+        None
+    }
+    .unwrap_or_else(|| channel_dir.join(file_name));
+    if target.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} is already there", target.display()),
+        ));
+    }
+
+    fs::create_dir_all(target.parent().expect("target has a parent directory"))?;
+    fs::rename(path, &target)?;
+    journal::moved(path, &target);
+    thumbnails::relocate(save_dir, path, &target);
+    sidecar::relocate(path, &target);
+    index.record(channel, &target)?;
+    Ok(target)
+}
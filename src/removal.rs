@@ -0,0 +1,86 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::index::Index;
+use crate::sidecar;
+use crate::sync_tools;
+use crate::thumbnails;
+
+/// set by --use-trash, nothing is deleted for good then
+static USE_TRASH: AtomicBool = AtomicBool::new(false);
+
+/// Send everything that would be deleted or overwritten to the trash from now on
+pub fn use_trash() {
+    USE_TRASH.store(true, Ordering::Relaxed);
+}
+
+/// Whether --use-trash was given
+pub fn uses_trash() -> bool {
+    USE_TRASH.load(Ordering::Relaxed)
+}
+
+/// Move `path` to the trash of the desktop
+pub fn trash(path: &Path) -> io::Result<()> {
+    ::trash::delete(path).map_err(io::Error::other)
+}
+
+/// Get rid of an organized screenshot along with its index row and thumbnails. It goes to the
+/// trash of the desktop when `to_trash` or with --use-trash, so a wrong keystroke or a
+/// retention rule that's off can still be taken back.
+pub fn discard(
+    save_dir: &Path,
+    screenshot: &Path,
+    index: Option<&Index>,
+    to_trash: bool,
+) -> io::Result<()> {
+    let to_trash = to_trash || uses_trash();
+    if to_trash {
+        trash(screenshot)?;
+    } else {
+        fs::remove_file(screenshot)?;
+    }
+    if let Some(index) = index {
+        index.remove(screenshot)?;
+    }
+    thumbnails::remove(save_dir, screenshot);
+    sidecar::remove(screenshot);
+    log::info!(
+        "{} {}",
+        if to_trash { "Trashed" } else { "Deleted" },
+        screenshot.display()
+    );
+    Ok(())
+}
+
+/// Move an organized screenshot out of the save dir into `archive_dir`, keeping where it was
+/// relative to the save dir. It leaves the index and its thumbnails behind.
+pub fn archive(
+    save_dir: &Path,
+    screenshot: &Path,
+    archive_dir: &Path,
+    index: Option<&Index>,
+) -> io::Result<()> {
+    let relative = screenshot
+        .strip_prefix(save_dir)
+        .map_err(|_| io::Error::other(format!("{} isn't organized", screenshot.display())))?;
+    let target = archive_dir.join(relative);
+    fs::create_dir_all(
+        target
+            .parent()
+            .expect("archived path has a parent directory"),
+    )?;
+    // the archive is usually on another disk, where renames don't work
+    if fs::rename(screenshot, &target).is_err() {
+        sync_tools::copy(screenshot, &target)?;
+        fs::remove_file(screenshot)?;
+    }
+    if let Some(index) = index {
+        index.remove(screenshot)?;
+    }
+    thumbnails::remove(save_dir, screenshot);
+    sidecar::relocate(screenshot, &target);
+    log::info!("Archived {} to {}", screenshot.display(), target.display());
+    Ok(())
+}
@@ -0,0 +1,218 @@
+use notify::event::{CreateKind, ModifyKind, RenameMode};
+use notify::{Event, EventKind};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+use crate::{delayed, journal, watch, Options, EXTENSIONS};
+
+/// moves wait up to 2s for a file to be written on platforms that can't tell when it's closed
+const SETTLE: Duration = Duration::from_secs(3);
+
+/// One line of a recording. Paths in events are relative to the watched folder, so recordings
+/// can be shared without giving away where things live.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Recorded {
+    Started {
+        platform: String,
+        version: String,
+    },
+    /// `ms` is the time since recording started
+    Event {
+        ms: u64,
+        event: Event,
+    },
+    Error {
+        ms: u64,
+        error: String,
+    },
+}
+
+/// Writes every raw watcher event to a file as json lines, for --record-events
+#[derive(Debug)]
+pub struct Recorder {
+    root: PathBuf,
+    started: Instant,
+    file: Mutex<File>,
+}
+
+impl Recorder {
+    /// Start a new recording of events under `root` in `file`, replacing an earlier one
+    pub fn create(file: &Path, root: &Path) -> io::Result<Recorder> {
+        let recorder = Recorder {
+            root: root.canonicalize()?,
+            started: Instant::now(),
+            file: Mutex::new(File::create(file)?),
+        };
+        recorder.write(&Recorded::Started {
+            platform: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        })?;
+        log::info!("Recording watcher events to {}", file.display());
+        Ok(recorder)
+    }
+
+    pub fn record(&self, event: &notify::Result<Event>) {
+        let ms = self.started.elapsed().as_millis() as u64;
+        let recorded = match event {
+            Ok(event) => {
+                let mut event = event.clone();
+                for path in &mut event.paths {
+                    if let Ok(relative) = path.strip_prefix(&self.root) {
+                        *path = relative.to_path_buf();
+                    }
+                }
+                Recorded::Event { ms, event }
+            }
+            Err(error) => Recorded::Error {
+                ms,
+                error: error.to_string(),
+            },
+        };
+        if let Err(error) = self.write(&recorded) {
+            log::warn!("Failed to record watcher event: {error}");
+        }
+    }
+
+    fn write(&self, recorded: &Recorded) -> io::Result<()> {
+        let mut line = serde_json::to_string(recorded)?;
+        line.push('\n');
+        self.file.lock().unwrap().write_all(line.as_bytes())
+    }
+}
+
+/// Feed the events recorded in `file` through the watcher pipeline against a fresh temp folder,
+/// with the same gaps between them unless `fast`. Files show up as events say they did ( created
+/// or renamed into place ), getting them out again is up to the pipeline.
+pub fn run(file: &Path, fast: bool, mut options: Options) -> io::Result<()> {
+    let dir = std::env::temp_dir().join(format!(
+        "twitch-screenshot-organizer-replay-{}",
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir)?;
+    let dir = dir.canonicalize()?;
+    options.collapse_into(&dir);
+    options.open_index(&dir)?;
+    options.connect_twitch();
+    delayed::set_workers(options.max_concurrent_moves);
+    journal::disable();
+    log::info!("Replaying {} in {}", file.display(), dir.display());
+
+    let started = Instant::now();
+    let mut debouncer = watch::Debouncer::default();
+    let mut renamed_from: Option<PathBuf> = None;
+    let mut events = 0;
+    for (number, line) in BufReader::new(File::open(file)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let recorded: Recorded = serde_json::from_str(&line).map_err(|error| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {error}", number + 1),
+            )
+        })?;
+
+        let (ms, event) = match recorded {
+            Recorded::Started { platform, version } => {
+                log::info!("Recorded on {platform} by version {version}");
+                continue;
+            }
+            Recorded::Event { ms, event } => (ms, Ok(event)),
+            Recorded::Error { ms, error } => (ms, Err(notify::Error::generic(&error))),
+        };
+        if !fast {
+            let at = started + Duration::from_millis(ms);
+            thread::sleep(at.saturating_duration_since(Instant::now()));
+        }
+
+        let event = match event {
+            Ok(mut event) => {
+                if event.paths.iter().any(|path| !is_relative(path)) {
+                    log::warn!("Skipping event outside the watched folder: {event:?}");
+                    continue;
+                }
+                event.paths = event.paths.iter().map(|path| dir.join(path)).collect();
+                log::debug!("Replaying {event:?}");
+                materialize(&event, &mut renamed_from)?;
+                Ok(event)
+            }
+            Err(error) => Err(error),
+        };
+        debouncer.add(&dir, event, &options);
+        debouncer.organize_due(&options, false);
+        events += 1;
+    }
+    // whatever is still waiting would go quiet now
+    debouncer.organize_due(&options, true);
+
+    // moves in watch mode happen on their own threads once the file is ready
+    thread::sleep(SETTLE);
+    log::info!("Replayed {events} events, {} now has:", dir.display());
+    for entry in WalkDir::new(&dir)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+    {
+        println!("{}", entry.path().strip_prefix(&dir).unwrap().display());
+    }
+    Ok(())
+}
+
+/// no .. either, a recording shouldn't be able to point anywhere but into the temp folder
+fn is_relative(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, std::path::Component::Normal(_)))
+}
+
+/// do to the temp folder what the event says happened to the watched one
+fn materialize(event: &Event, renamed_from: &mut Option<PathBuf>) -> io::Result<()> {
+    match (&event.kind, event.paths.as_slice()) {
+        (EventKind::Create(CreateKind::Folder), [path]) => fs::create_dir_all(path),
+        (EventKind::Create(_), [path]) => create(path),
+        (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [from, to]) => rename(from, to),
+        (EventKind::Modify(ModifyKind::Name(RenameMode::From)), [from]) => {
+            *renamed_from = Some(from.clone());
+            Ok(())
+        }
+        (EventKind::Modify(ModifyKind::Name(RenameMode::To)), [to]) => match renamed_from.take() {
+            Some(from) => rename(&from, to),
+            None => create(to),
+        },
+        _ => Ok(()),
+    }
+}
+
+fn rename(from: &Path, to: &Path) -> io::Result<()> {
+    if from.exists() {
+        fs::rename(from, to)
+    } else {
+        create(to)
+    }
+}
+
+/// a tiny image for anything with an image extension, so converting and thumbnails work too
+fn create(path: &Path) -> io::Result<()> {
+    if path.exists() {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    match extension.filter(|extension| EXTENSIONS.contains(extension)) {
+        Some("avif") | Some("webm") | None => File::create(path).map(drop),
+        Some(_) => image::RgbImage::new(16, 9)
+            .save(path)
+            .map_err(io::Error::other),
+    }
+}
@@ -0,0 +1,181 @@
+use chrono::{Local, TimeDelta};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::collection::{self, Screenshot};
+use crate::config::{Limits, Retention, RetentionAction};
+use crate::glob;
+use crate::index::Index;
+use crate::removal;
+use crate::stats::human_size;
+
+/// default [Retention::archive_dir], a dot folder so it's left out of everything else
+pub const ARCHIVE: &str = ".archive";
+
+/// What pruning did, or would do
+#[derive(Debug, Default)]
+struct Pruned {
+    screenshots: usize,
+    freed: u64,
+}
+
+/// Bring every channel under `path` ( only those matching `channel` if given ) within the
+/// [retention] limits, for the `prune` command. With `clean_empty` channel folders left empty
+/// are removed.
+pub fn run(
+    path: &Path,
+    retention: &Retention,
+    channel: Option<&str>,
+    dry_run: bool,
+    clean_empty: bool,
+) -> io::Result<()> {
+    let save_dir = collection::save_dir(path);
+    let index = Index::open_existing(&save_dir)?;
+
+    let mut channels: BTreeMap<String, Vec<Screenshot>> = BTreeMap::new();
+    for screenshot in collection::walk(&save_dir) {
+        if channel.is_none_or(|pattern| glob::matches(pattern, &screenshot.channel)) {
+            channels
+                .entry(screenshot.channel.clone())
+                .or_default()
+                .push(screenshot);
+        }
+    }
+
+    let mut pruned = Pruned::default();
+    for (channel, screenshots) in channels {
+        let limits = retention.limits_for(&channel);
+        for (screenshot, size) in over_limits(&screenshots, &limits) {
+            if dry_run {
+                println!("{}", screenshot.path.display());
+            } else if let Err(error) = apply(&save_dir, screenshot, retention, index.as_ref()) {
+                log::error!("Failed to prune {}: {error:?}", screenshot.path.display());
+                continue;
+            }
+            pruned.screenshots += 1;
+            pruned.freed += size;
+        }
+    }
+
+    println!(
+        "{} {} screenshot(s) ( {} )",
+        if dry_run { "Would prune" } else { "Pruned" },
+        pruned.screenshots,
+        human_size(pruned.freed)
+    );
+    if clean_empty && !dry_run {
+        let removed = collection::remove_empty_channels(&save_dir);
+        println!("Removed {removed} empty channel folder(s)");
+    }
+    Ok(())
+}
+
+/// Keep every channel in `save_dir` within its limits, for channels that don't get new
+/// screenshots to check them
+pub fn enforce_all(save_dir: &Path, retention: &Retention, index: Option<&Index>) {
+    let Ok(entries) = fs::read_dir(save_dir) else {
+        return;
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let is_channel = entry.file_type().is_ok_and(|ft| ft.is_dir())
+            && !entry.file_name().to_string_lossy().starts_with('.');
+        if !is_channel {
+            continue;
+        }
+        if let Err(error) = enforce(&entry.path(), retention, index) {
+            log::error!("Failed to prune {}: {error:?}", entry.path().display());
+        }
+    }
+}
+
+/// Keep the channel in `channel_dir` within its limits, after a screenshot was organized into it
+pub fn enforce(channel_dir: &Path, retention: &Retention, index: Option<&Index>) -> io::Result<()> {
+    let save_dir = channel_dir
+        .parent()
+        .expect("channel dir is in the save dir");
+    let screenshots = collection::channel(save_dir, channel_dir);
+    let Some(channel) = screenshots
+        .first()
+        .map(|screenshot| screenshot.channel.clone())
+    else {
+        return Ok(());
+    };
+
+    let mut pruned = Pruned::default();
+    for (screenshot, size) in over_limits(&screenshots, &retention.limits_for(&channel)) {
+        apply(save_dir, screenshot, retention, index)?;
+        pruned.screenshots += 1;
+        pruned.freed += size;
+    }
+    if pruned.screenshots > 0 {
+        log::info!(
+            "Pruned {} screenshot(s) of {channel} to stay within its limits, {} freed",
+            pruned.screenshots,
+            human_size(pruned.freed)
+        );
+    }
+    Ok(())
+}
+
+/// The screenshots of one channel ( sorted oldest first ) that don't fit into `limits`, with
+/// their sizes. Going from the newest back, everything past the first one that doesn't fit goes.
+/// Screenshots without a capture time are never pruned, there's no telling how old they are.
+fn over_limits<'a>(screenshots: &'a [Screenshot], limits: &Limits) -> Vec<(&'a Screenshot, u64)> {
+    let oldest_kept = limits
+        .max_age
+        .map(|days| Local::now().naive_local() - TimeDelta::days(days.into()));
+
+    let mut kept = 0;
+    let mut total = 0;
+    let mut over = false;
+    let mut pruned = Vec::new();
+    for screenshot in screenshots.iter().rev() {
+        let Some(captured_at) = screenshot.captured_at else {
+            continue;
+        };
+        let size = screenshot
+            .path
+            .metadata()
+            .map_or(0, |metadata| metadata.len());
+        over = over
+            || oldest_kept.is_some_and(|oldest| captured_at < oldest)
+            || limits.max_per_channel.is_some_and(|max| kept + 1 > max)
+            || limits
+                .max_total_size
+                .is_some_and(|max| total + size > max.0);
+        if over {
+            pruned.push((screenshot, size));
+        } else {
+            kept += 1;
+            total += size;
+        }
+    }
+    pruned
+}
+
+fn apply(
+    save_dir: &Path,
+    screenshot: &Screenshot,
+    retention: &Retention,
+    index: Option<&Index>,
+) -> io::Result<()> {
+    match retention.action {
+        RetentionAction::Delete => removal::discard(save_dir, &screenshot.path, index, false),
+        RetentionAction::Trash => removal::discard(save_dir, &screenshot.path, index, true),
+        RetentionAction::Archive => removal::archive(
+            save_dir,
+            &screenshot.path,
+            &archive_dir(save_dir, retention),
+            index,
+        ),
+    }
+}
+
+fn archive_dir(save_dir: &Path, retention: &Retention) -> PathBuf {
+    match &retention.archive_dir {
+        Some(dir) => save_dir.join(dir),
+        None => save_dir.join(ARCHIVE),
+    }
+}
@@ -0,0 +1,275 @@
+use image::RgbImage;
+use ratatui::buffer::Buffer;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Margin, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Paragraph, Widget};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::animated;
+use crate::collection::{self, Screenshot};
+use crate::dupes;
+use crate::index::Index;
+use crate::removal;
+use crate::search::{self, Query};
+use crate::stats::human_size;
+
+/// one key per screenshot, anything past this in a group is always kept
+const MAX_GROUP: usize = 9;
+
+const HELP: &str =
+    "1-9: keep/delete  enter: apply and next  a: keep all  n/p: next/previous group  q: quit";
+
+/// Go through groups of near duplicate screenshots under `path` and pick which ones to keep,
+/// the rest go to the trash ( or are deleted for good with `permanent` )
+pub fn run(path: &Path, query: &Query, threshold: u32, permanent: bool) -> io::Result<()> {
+    let save_dir = collection::save_dir(path);
+    let index = Index::open_existing(&save_dir)?;
+
+    log::info!("Looking for near duplicates, new screenshots have to be hashed first...");
+    let groups = dupes::find(search::find(path, query)?, index.as_ref(), threshold);
+    if groups.is_empty() {
+        println!("No near duplicates found");
+        return Ok(());
+    }
+
+    let mut review = Review {
+        save_dir,
+        index,
+        keep: Vec::new(),
+        groups,
+        current: 0,
+        pictures: HashMap::new(),
+        status: HELP.to_string(),
+        to_trash: !permanent,
+        removed: 0,
+        freed: 0,
+    };
+    review.select(0);
+
+    let mut terminal = ratatui::init();
+    let result = review.run(&mut terminal);
+    ratatui::restore();
+
+    println!(
+        "Removed {} screenshot(s), {} freed",
+        review.removed,
+        human_size(review.freed)
+    );
+    result
+}
+
+struct Review {
+    save_dir: PathBuf,
+    index: Option<Index>,
+    groups: Vec<Vec<Screenshot>>,
+    current: usize,
+    /// what's kept in the current group
+    keep: Vec<bool>,
+    /// decoded for the size they were last drawn at, none if they can't be decoded
+    pictures: HashMap<(PathBuf, u16, u16), Option<RgbImage>>,
+    status: String,
+    to_trash: bool,
+    removed: usize,
+    freed: u64,
+}
+
+impl Review {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char(c @ '1'..='9') => {
+                    let i = c as usize - '1' as usize;
+                    if let Some(keep) = self.keep.get_mut(i) {
+                        *keep = !*keep;
+                    }
+                }
+                KeyCode::Char('a') => {
+                    self.keep.iter_mut().for_each(|keep| *keep = true);
+                    self.next();
+                }
+                KeyCode::Enter => {
+                    if !self.keep.contains(&true) {
+                        self.status = "Keep at least one of them".to_string();
+                        continue;
+                    }
+                    self.apply();
+                    self.next();
+                }
+                KeyCode::Char('n') | KeyCode::Right => self.next(),
+                KeyCode::Char('p') | KeyCode::Left => {
+                    self.select(self.current.saturating_sub(1));
+                }
+                _ => {}
+            }
+            if self.current >= self.groups.len() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// start reviewing a group, the earliest screenshot is kept unless told otherwise
+    fn select(&mut self, current: usize) {
+        self.current = current;
+        self.pictures.clear();
+        let len = self.groups.get(current).map_or(0, Vec::len);
+        self.keep = (0..len).map(|i| i == 0 || i >= MAX_GROUP).collect();
+    }
+
+    fn next(&mut self) {
+        self.select(self.current + 1);
+    }
+
+    fn apply(&mut self) {
+        let group = &self.groups[self.current];
+        let mut failed = 0;
+        for (screenshot, _) in group.iter().zip(&self.keep).filter(|(_, keep)| !**keep) {
+            let size = screenshot.path.metadata().map_or(0, |m| m.len());
+            match removal::discard(
+                &self.save_dir,
+                &screenshot.path,
+                self.index.as_ref(),
+                self.to_trash,
+            ) {
+                Ok(()) => {
+                    self.removed += 1;
+                    self.freed += size;
+                }
+                Err(error) => {
+                    log::error!("Failed to remove {}: {error}", screenshot.path.display());
+                    failed += 1;
+                }
+            }
+        }
+        let kept = self.keep.iter().filter(|keep| **keep).count();
+        self.status = if failed > 0 {
+            format!("Kept {kept}, {failed} could not be removed")
+        } else {
+            format!("Kept {kept} of the last group")
+        };
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let Some(group) = self.groups.get(self.current).cloned() else {
+            return;
+        };
+        let [header, pictures, info, status] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(4),
+            Constraint::Length(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let day = group[0]
+            .captured_at
+            .map(|time| time.date().to_string())
+            .unwrap_or_else(|| "unknown date".to_string());
+        frame.render_widget(
+            Line::from(format!(
+                "Group {} of {}  {}  {day}  {} screenshots",
+                self.current + 1,
+                self.groups.len(),
+                group[0].channel,
+                group.len()
+            ))
+            .bold(),
+            header,
+        );
+
+        let shown = group.len().min(MAX_GROUP);
+        let columns = Layout::horizontal(vec![Constraint::Ratio(1, shown as u32); shown]);
+        for (i, (picture_area, info_area)) in columns
+            .split(pictures)
+            .iter()
+            .zip(columns.split(info).iter())
+            .enumerate()
+        {
+            let screenshot = &group[i];
+            let area = picture_area.inner(Margin::new(1, 0));
+            if let Some(picture) = self.picture(&screenshot.path, area) {
+                frame.render_widget(Picture(picture), area);
+            }
+
+            let keep = self.keep[i];
+            let size = screenshot
+                .path
+                .metadata()
+                .map(|metadata| human_size(metadata.len()))
+                .unwrap_or_else(|_| "-".to_string());
+            let time = screenshot
+                .captured_at
+                .map(|time| time.format("%H:%M:%S").to_string())
+                .unwrap_or_default();
+            frame.render_widget(
+                Paragraph::new(vec![
+                    Line::from(format!(
+                        "[{}] {}",
+                        i + 1,
+                        if keep { "keep" } else { "delete" }
+                    ))
+                    .style(if keep {
+                        Style::new().green().bold()
+                    } else {
+                        Style::new().red().bold()
+                    }),
+                    Line::from(format!("{time}  {size}")),
+                    Line::from(screenshot.file_name()),
+                ]),
+                *info_area,
+            );
+        }
+
+        frame.render_widget(Line::from(self.status.as_str()), status);
+    }
+
+    fn picture(&mut self, path: &Path, area: Rect) -> Option<&RgbImage> {
+        self.pictures
+            .entry((path.to_path_buf(), area.width, area.height))
+            .or_insert_with(|| {
+                let image = animated::open(path).ok()?;
+                // two pixels per cell, one in each half
+                Some(
+                    image
+                        .thumbnail(u32::from(area.width), u32::from(area.height) * 2)
+                        .to_rgb8(),
+                )
+            })
+            .as_ref()
+    }
+}
+
+/// An image drawn with half blocks, the foreground colour is the top pixel and the background
+/// the bottom one
+struct Picture<'a>(&'a RgbImage);
+
+impl Widget for Picture<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let image = self.0;
+        let left = area.x + area.width.saturating_sub(image.width() as u16) / 2;
+        for y in 0..image.height().div_ceil(2) {
+            for x in 0..image.width() {
+                let top = image.get_pixel(x, y * 2);
+                let bottom = image.get_pixel(x, (y * 2 + 1).min(image.height() - 1));
+                if let Some(cell) = buf.cell_mut((left + x as u16, area.y + y as u16)) {
+                    cell.set_char('▀')
+                        .set_fg(Color::Rgb(top[0], top[1], top[2]))
+                        .set_bg(Color::Rgb(bottom[0], bottom[1], bottom[2]));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use chrono::NaiveDate;
+use std::io;
+use std::path::Path;
+
+use crate::collection::{self, Screenshot};
+use crate::glob;
+use crate::index::Index;
+
+/// more than this and --open would bury the desktop in image viewers
+const MAX_OPEN: usize = 20;
+
+/// Which organized screenshots a command should look at
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct Query {
+    /// Only screenshots of this channel, `*` and `?` wildcards work
+    #[clap(long)]
+    pub channel: Option<String>,
+
+    /// Only screenshots captured on or after this day ( YYYY-MM-DD )
+    #[clap(long)]
+    pub from: Option<NaiveDate>,
+
+    /// Only screenshots captured on or before this day ( YYYY-MM-DD )
+    #[clap(long)]
+    pub to: Option<NaiveDate>,
+
+    /// Only screenshots whose file name contains this, case insensitive
+    #[clap(long)]
+    pub name: Option<String>,
+}
+
+impl Query {
+    pub fn matches(&self, screenshot: &Screenshot) -> bool {
+        if let Some(channel) = &self.channel {
+            if !glob::matches(channel, &screenshot.channel) {
+                return false;
+            }
+        }
+        if self.from.is_some() || self.to.is_some() {
+            let Some(day) = screenshot.captured_at.map(|time| time.date()) else {
+                return false;
+            };
+            if self.from.is_some_and(|from| day < from) || self.to.is_some_and(|to| day > to) {
+                return false;
+            }
+        }
+        if let Some(name) = &self.name {
+            if !screenshot
+                .file_name()
+                .to_lowercase()
+                .contains(&name.to_lowercase())
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Organized screenshots under `path` matching `query`, from the index when there is one so
+/// nothing has to be walked
+pub fn find(path: &Path, query: &Query) -> io::Result<Vec<Screenshot>> {
+    let save_dir = collection::save_dir(path);
+    let screenshots = match Index::open_existing(&save_dir)? {
+        Some(index) => index
+            .screenshots()?
+            .into_iter()
+            .filter(|screenshot| screenshot.path.exists())
+            .collect(),
+        None => {
+            log::debug!("No index in {}, walking it", save_dir.display());
+            collection::walk(&save_dir)
+        }
+    };
+    Ok(screenshots
+        .into_iter()
+        .filter(|screenshot| query.matches(screenshot))
+        .collect())
+}
+
+/// Print the matches one per line, optionally opening them in the default viewer
+pub fn run(path: &Path, query: &Query, open: bool) -> io::Result<()> {
+    let matches = find(path, query)?;
+    for screenshot in &matches {
+        println!("{}", screenshot.path.display());
+    }
+    log::info!("{} screenshot(s) found", matches.len());
+
+    if open {
+        if matches.len() > MAX_OPEN {
+            log::warn!("Only opening the first {MAX_OPEN} matches");
+        }
+        for screenshot in matches.iter().take(MAX_OPEN) {
+            if let Err(error) = opener::open(&screenshot.path) {
+                log::error!("Failed to open {}: {error}", screenshot.path.display());
+            }
+        }
+    }
+    Ok(())
+}
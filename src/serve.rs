@@ -0,0 +1,439 @@
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tiny_http::{Header, Request, Response, Server};
+
+use crate::animated;
+use crate::collection::{self, Screenshot};
+use crate::config::{Labels, Thumbnails};
+use crate::index::{self, Index};
+use crate::search::{self, Query};
+use crate::thumbnails;
+
+/// requests are handled by this many threads, thumbnails take a moment to render
+const WORKERS: usize = 4;
+
+/// Serve a gallery of the screenshots organized under `path` to anything on the network. With
+/// `thumbnails` set missing thumbnails are written to .thumbs/ as they're asked for, otherwise
+/// they're rendered on the fly when there's none.
+pub fn run(
+    path: &Path,
+    port: u16,
+    thumbnails: Option<Thumbnails>,
+    labels: &Labels,
+) -> io::Result<()> {
+    let server = Server::http(("0.0.0.0", port)).map_err(io::Error::other)?;
+    let save_dir = collection::save_dir(path).canonicalize()?;
+    let gallery = Arc::new(Gallery {
+        path: path.to_path_buf(),
+        labels: index::labels(&save_dir, labels),
+        index: Index::open_existing(&save_dir)?,
+        save_dir,
+        thumbnails,
+        pages: Mutex::new(Pages::default()),
+    });
+    log::info!(
+        "Serving {} on http://0.0.0.0:{port}/",
+        gallery.save_dir.display()
+    );
+
+    let server = Arc::new(server);
+    let workers = (0..WORKERS)
+        .map(|_| {
+            let server = server.clone();
+            let gallery = gallery.clone();
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    gallery.handle(request);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for worker in workers {
+        worker.join().expect("Gallery worker panicked");
+    }
+    Ok(())
+}
+
+struct Gallery {
+    path: PathBuf,
+    save_dir: PathBuf,
+    thumbnails: Option<Thumbnails>,
+    labels: Labels,
+    /// with an index pages are kept and only built again for what changed in it
+    index: Option<Index>,
+    pages: Mutex<Pages>,
+}
+
+/// a channel's screenshots by day, sorted by capture time
+type Days = BTreeMap<Option<NaiveDate>, Vec<Screenshot>>;
+
+/// What the gallery showed as of a change of the index, and the html built from it so far
+#[derive(Default)]
+struct Pages {
+    /// none until the first request loads everything
+    version: Option<i64>,
+    channels: BTreeMap<String, Days>,
+    front: Option<String>,
+    channel_pages: HashMap<String, String>,
+    day_sections: HashMap<(String, Option<NaiveDate>), String>,
+}
+
+impl Gallery {
+    fn handle(&self, request: Request) {
+        let url = request.url().split('?').next().unwrap_or("/").to_string();
+        log::debug!("{} {url}", request.method());
+
+        let response = match url.split_once('/').map(|(_, rest)| rest) {
+            Some("") => self.channels().map(html),
+            Some(rest) => match rest.split_once('/') {
+                Some(("channel", channel)) => self.channel(&decode(channel)).map(html),
+                Some(("image", file)) => self.file(&decode(file)).and_then(|file| {
+                    Ok(Response::from_data(fs::read(&file)?)
+                        .with_header(header("Content-Type", image_type(&file))))
+                }),
+                Some(("thumb", file)) => self.file(&decode(file)).and_then(|file| {
+                    let (data, content_type) = self.thumbnail(&file)?;
+                    Ok(Response::from_data(data)
+                        .with_header(header("Content-Type", content_type))
+                        .with_header(header("Cache-Control", "max-age=86400")))
+                }),
+                _ => Err(io::ErrorKind::NotFound.into()),
+            },
+            None => Err(io::ErrorKind::NotFound.into()),
+        };
+
+        let response = response.unwrap_or_else(|error| {
+            let status = match error.kind() {
+                io::ErrorKind::NotFound => 404,
+                io::ErrorKind::PermissionDenied => 403,
+                _ => {
+                    log::error!("Failed to serve {url}: {error:?}");
+                    500
+                }
+            };
+            Response::from_string(error.to_string()).with_status_code(status)
+        });
+        if let Err(error) = request.respond(response) {
+            log::debug!("Failed to respond to {url}: {error}");
+        }
+    }
+
+    /// front page, every channel with how many screenshots it has
+    fn channels(&self) -> io::Result<String> {
+        let Some(index) = &self.index else {
+            let screenshots = search::find(&self.path, &Query::default())?;
+            return Ok(self.front_page(&by_channel(screenshots)));
+        };
+        let mut pages = self.pages.lock().unwrap();
+        self.refresh(index, &mut pages)?;
+        if pages.front.is_none() {
+            pages.front = Some(self.front_page(&pages.channels));
+        }
+        Ok(pages.front.clone().unwrap_or_default())
+    }
+
+    /// one channel, newest day first
+    fn channel(&self, channel: &str) -> io::Result<String> {
+        let not_found = || {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No screenshots of {channel}"),
+            )
+        };
+        let Some(index) = &self.index else {
+            let query = Query {
+                channel: Some(channel.to_string()),
+                ..Query::default()
+            };
+            let channels = by_channel(search::find(&self.path, &query)?);
+            let days = channels.get(channel).ok_or_else(not_found)?;
+            let sections = days
+                .iter()
+                .rev()
+                .map(|(day, screenshots)| self.day_section(*day, screenshots))
+                .collect::<Vec<_>>();
+            return Ok(channel_page(self.labels.of(channel), &sections));
+        };
+
+        let mut pages = self.pages.lock().unwrap();
+        self.refresh(index, &mut pages)?;
+        if let Some(page) = pages.channel_pages.get(channel) {
+            return Ok(page.clone());
+        }
+        let Pages {
+            channels,
+            channel_pages,
+            day_sections,
+            ..
+        } = &mut *pages;
+        let days = channels.get(channel).ok_or_else(not_found)?;
+        let sections = days
+            .iter()
+            .rev()
+            .map(|(day, screenshots)| {
+                day_sections
+                    .entry((channel.to_string(), *day))
+                    .or_insert_with(|| self.day_section(*day, screenshots))
+                    .clone()
+            })
+            .collect::<Vec<_>>();
+        let page = channel_page(self.labels.of(channel), &sections);
+        channel_pages.insert(channel.to_string(), page.clone());
+        Ok(page)
+    }
+
+    /// Catch `pages` up with the index. The first time everything is loaded, after that only
+    /// the days that changed since are loaded again and the pages showing them dropped.
+    fn refresh(&self, index: &Index, pages: &mut Pages) -> io::Result<()> {
+        let latest = index.version()?;
+        let Some(version) = pages.version else {
+            pages.channels = by_channel(search::find(&self.path, &Query::default())?);
+            pages.version = Some(latest);
+            return Ok(());
+        };
+        if latest == version {
+            return Ok(());
+        }
+
+        for (channel, day) in index.changed_since(version)? {
+            log::debug!("Rebuilding {channel} on {day:?}");
+            let screenshots = index
+                .screenshots_on(&channel, day)?
+                .into_iter()
+                .filter(|screenshot| screenshot.path.exists())
+                .collect::<Vec<_>>();
+            pages.day_sections.remove(&(channel.clone(), day));
+            pages.channel_pages.remove(&channel);
+
+            let days = pages.channels.entry(channel.clone()).or_default();
+            if screenshots.is_empty() {
+                days.remove(&day);
+            } else {
+                days.insert(day, screenshots);
+            }
+            if days.is_empty() {
+                pages.channels.remove(&channel);
+            }
+        }
+        pages.front = None;
+        pages.version = Some(latest);
+        Ok(())
+    }
+
+    fn front_page(&self, channels: &BTreeMap<String, Days>) -> String {
+        let mut body = String::from("<h1>Twitch screenshots</h1><div class=\"grid\">");
+        for (channel, days) in channels {
+            let count = days.values().map(Vec::len).sum::<usize>();
+            // sorted by capture time, so the last one is the newest
+            let latest = days.values().flatten().last();
+            let _ = write!(
+                body,
+                "<a class=\"card\" href=\"/channel/{}\">{}<span>{} ({count})</span></a>",
+                encode(channel),
+                latest.map(|s| self.thumb(s)).unwrap_or_default(),
+                escape(self.labels.of(channel))
+            );
+        }
+        body.push_str("</div>");
+        page("Twitch screenshots", &body)
+    }
+
+    fn day_section(&self, day: Option<NaiveDate>, screenshots: &[Screenshot]) -> String {
+        let mut section = format!(
+            "<h2>{}</h2><div class=\"grid\">",
+            day.map(|day| day.format("%A %Y-%m-%d").to_string())
+                .unwrap_or_else(|| "unknown date".to_string())
+        );
+        for screenshot in screenshots {
+            let _ = write!(
+                section,
+                "<a class=\"card\" href=\"/image/{}\">{}<span>{}</span></a>",
+                self.url_path(screenshot),
+                self.thumb(screenshot),
+                screenshot
+                    .captured_at
+                    .map(|time| time.format("%H:%M:%S").to_string())
+                    .unwrap_or_else(|| escape(&screenshot.file_name()))
+            );
+        }
+        section.push_str("</div>");
+        section
+    }
+
+    /// clips show their first frame themselves when there's no thumbnail
+    fn thumb(&self, screenshot: &Screenshot) -> String {
+        let url_path = self.url_path(screenshot);
+        if animated::is_video(&screenshot.path) {
+            format!(
+                "<video muted preload=\"metadata\" poster=\"/thumb/{url_path}\" \
+                 src=\"/image/{url_path}#t=0.1\"></video>"
+            )
+        } else {
+            format!(
+                "<img loading=\"lazy\" src=\"/thumb/{url_path}\" alt=\"{}\">",
+                escape(&screenshot.file_name())
+            )
+        }
+    }
+
+    /// path of a screenshot relative to the save dir, encoded for a url
+    fn url_path(&self, screenshot: &Screenshot) -> String {
+        let path = screenshot
+            .path
+            .canonicalize()
+            .unwrap_or_else(|_| screenshot.path.clone());
+        path.strip_prefix(&self.save_dir)
+            .unwrap_or(&path)
+            .components()
+            .map(|component| encode(&component.as_os_str().to_string_lossy()))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// the stored thumbnail when there is one, written or rendered now otherwise
+    /// ( avif can't be decoded here, those get the full image, clips without ffmpeg none )
+    fn thumbnail(&self, file: &Path) -> io::Result<(Vec<u8>, &'static str)> {
+        if let Some((thumbnail, format)) = thumbnails::existing(&self.save_dir, file) {
+            return Ok((fs::read(thumbnail)?, format.content_type()));
+        }
+        let result = match &self.thumbnails {
+            Some(settings) => thumbnails::update(&self.save_dir, file, settings)
+                .and_then(fs::read)
+                .map(|data| (data, settings.format.content_type())),
+            None => {
+                let settings = Thumbnails::default();
+                thumbnails::render(file, &settings)
+                    .map(|data| (data, settings.format.content_type()))
+            }
+        };
+        result.or_else(|error| {
+            log::debug!("No thumbnail for {}: {error}", file.display());
+            if animated::is_video(file) {
+                return Err(io::ErrorKind::NotFound.into());
+            }
+            Ok((fs::read(file)?, image_type(file)))
+        })
+    }
+
+    /// a screenshot file from a url path, nothing outside the save dir is handed out
+    fn file(&self, relative: &str) -> io::Result<PathBuf> {
+        let relative = Path::new(relative);
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(io::ErrorKind::PermissionDenied.into());
+        }
+        let file = self.save_dir.join(relative).canonicalize()?;
+        if !file.starts_with(&self.save_dir) || !crate::is_screenshot(&file) {
+            return Err(io::ErrorKind::PermissionDenied.into());
+        }
+        Ok(file)
+    }
+}
+
+fn by_channel(screenshots: Vec<Screenshot>) -> BTreeMap<String, Days> {
+    let mut channels: BTreeMap<String, Days> = BTreeMap::new();
+    for screenshot in screenshots {
+        channels
+            .entry(screenshot.channel.clone())
+            .or_default()
+            .entry(screenshot.captured_at.map(|time| time.date()))
+            .or_default()
+            .push(screenshot);
+    }
+    channels
+}
+
+/// `label` is how the channel is shown
+fn channel_page(label: &str, sections: &[String]) -> String {
+    let body = format!(
+        "<p><a href=\"/\">all channels</a></p><h1>{}</h1>{}",
+        escape(label),
+        sections.concat()
+    );
+    page(label, &body)
+}
+
+fn image_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("webp") => "image/webp",
+        Some("avif") => "image/avif",
+        Some("jpg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webm") => "video/webm",
+        _ => "image/png",
+    }
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!doctype html><html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <title>{}</title><style>\
+         body {{ font-family: sans-serif; background: #18181b; color: #efeff1; margin: 1em; }}\
+         a {{ color: #bf94ff; }}\
+         .grid {{ display: grid; grid-template-columns: repeat(auto-fill, minmax(160px, 1fr)); gap: 8px; }}\
+         .card {{ display: flex; flex-direction: column; text-decoration: none; }}\
+         .card img, .card video {{ width: 100%; aspect-ratio: 16 / 9; object-fit: cover; background: #26262c; }}\
+         </style></head><body>{body}</body></html>",
+        escape(title)
+    )
+}
+
+fn html(body: String) -> Response<Cursor<Vec<u8>>> {
+    Response::from_string(body).with_header(header("Content-Type", "text/html; charset=utf-8"))
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).expect("header is valid ascii")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// percent encode everything but unreserved characters
+fn encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+    encoded
+}
+
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,409 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// name used for the unit file / launchd label / windows service
+const SERVICE_NAME: &str = "twitch-screenshot-organizer";
+
+/// Register the current binary as a per-user service running in watch mode on `path` with
+/// `config` so the watcher comes back after a reboot. `extra_args` are passed along to the
+/// organizer as is.
+pub fn install(path: &Path, config: Option<&Path>, extra_args: &[String]) -> io::Result<()> {
+    let exe = env::current_exe()?;
+    let path = fs::canonicalize(path)?;
+
+    let mut args = Vec::new();
+    if let Some(config) = config {
+        args.push("--config".to_string());
+        args.push(fs::canonicalize(config)?.to_string_lossy().into_owned());
+    }
+    args.extend_from_slice(extra_args);
+
+    platform::install(&exe, &path, &args)
+}
+
+/// Stop the service and remove its definition
+pub fn uninstall() -> io::Result<()> {
+    platform::uninstall()
+}
+
+/// Start the installed service right away instead of waiting for the next login / boot
+pub fn start() -> io::Result<()> {
+    platform::start()
+}
+
+#[cfg(windows)]
+pub use platform::run;
+
+/// systemd user unit in ~/.config/systemd/user
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use super::*;
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    pub fn install(exe: &Path, path: &Path, extra_args: &[String]) -> io::Result<()> {
+        let target = unit_file()?;
+
+        let exec_start = [exe.to_string_lossy(), path.to_string_lossy()]
+            .into_iter()
+            .map(|arg| arg.into_owned())
+            .chain(["--watch".to_string()])
+            .chain(extra_args.iter().cloned())
+            .map(|arg| systemd_quote(&arg))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        // the watcher doesn't handle SIGHUP, it's asked over the control socket instead
+        let exec_reload = format!(
+            "{} ctl reload-config",
+            systemd_quote(&exe.to_string_lossy())
+        );
+
+        let contents = format!(
+            "[Unit]\n\
+             Description=Daemon to run twitch-screenshot-organizer\n\
+             \n\
+             [Service]\n\
+             ExecStart={exec_start}\n\
+             ExecReload={exec_reload}\n\
+             Restart=on-failure\n\
+             RestartSec=5\n\
+             \n\
+             [Install]\n\
+             WantedBy=default.target\n"
+        );
+
+        fs::create_dir_all(target.parent().expect("unit file has a parent directory"))?;
+        fs::write(&target, contents)?;
+        log::info!("Service definition written to: {}", target.display());
+        log::info!(
+            "Enable it with: systemctl --user daemon-reload && systemctl --user enable --now {SERVICE_NAME}"
+        );
+        Ok(())
+    }
+
+    pub fn uninstall() -> io::Result<()> {
+        if let Err(error) = systemctl(&["disable", "--now", SERVICE_NAME]) {
+            log::warn!("Could not disable service, removing it anyway: {error}");
+        }
+        let target = unit_file()?;
+        fs::remove_file(&target)?;
+        log::info!("Removed: {}", target.display());
+        systemctl(&["daemon-reload"])
+    }
+
+    pub fn start() -> io::Result<()> {
+        systemctl(&["daemon-reload"])?;
+        systemctl(&["enable", "--now", SERVICE_NAME])?;
+        log::info!("Service started");
+        Ok(())
+    }
+
+    fn unit_file() -> io::Result<PathBuf> {
+        let config_dir = dirs::config_dir().ok_or_else(no_home)?;
+        Ok(config_dir
+            .join("systemd")
+            .join("user")
+            .join(format!("{SERVICE_NAME}.service")))
+    }
+
+    fn systemctl(args: &[&str]) -> io::Result<()> {
+        let status = Command::new("systemctl")
+            .arg("--user")
+            .args(args)
+            .status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "systemctl --user {} failed with {status}",
+                args.join(" ")
+            )));
+        }
+        Ok(())
+    }
+
+    /// quote an ExecStart argument so spaces in paths survive
+    fn systemd_quote(arg: &str) -> String {
+        // % starts a specifier in unit files
+        let arg = arg.replace('%', "%%");
+        if arg.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+            format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+        } else {
+            arg
+        }
+    }
+}
+
+/// launchd agent in ~/Library/LaunchAgents
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::*;
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    pub fn install(exe: &Path, path: &Path, extra_args: &[String]) -> io::Result<()> {
+        let target = plist_file()?;
+
+        let program_arguments = [exe.to_string_lossy(), path.to_string_lossy()]
+            .into_iter()
+            .map(|arg| arg.into_owned())
+            .chain(["--watch".to_string()])
+            .chain(extra_args.iter().cloned())
+            .map(|arg| format!("        <string>{}</string>\n", xml_escape(&arg)))
+            .collect::<String>();
+
+        let contents = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+             <plist version=\"1.0\">\n\
+             <dict>\n\
+             \x20   <key>Label</key>\n\
+             \x20   <string>{}</string>\n\
+             \x20   <key>ProgramArguments</key>\n\
+             \x20   <array>\n\
+             {program_arguments}\
+             \x20   </array>\n\
+             \x20   <key>RunAtLoad</key>\n\
+             \x20   <true/>\n\
+             \x20   <key>KeepAlive</key>\n\
+             \x20   <true/>\n\
+             </dict>\n\
+             </plist>\n",
+            label()
+        );
+
+        fs::create_dir_all(target.parent().expect("plist has a parent directory"))?;
+        fs::write(&target, contents)?;
+        log::info!("Service definition written to: {}", target.display());
+        log::info!("Enable it with: launchctl load -w {}", target.display());
+        Ok(())
+    }
+
+    pub fn uninstall() -> io::Result<()> {
+        let target = plist_file()?;
+        if let Err(error) = launchctl("unload", &target) {
+            log::warn!("Could not unload service, removing it anyway: {error}");
+        }
+        fs::remove_file(&target)?;
+        log::info!("Removed: {}", target.display());
+        Ok(())
+    }
+
+    pub fn start() -> io::Result<()> {
+        launchctl("load", &plist_file()?)?;
+        log::info!("Service started");
+        Ok(())
+    }
+
+    fn label() -> String {
+        format!("com.github.ruinivist.{SERVICE_NAME}")
+    }
+
+    fn plist_file() -> io::Result<PathBuf> {
+        let home = dirs::home_dir().ok_or_else(no_home)?;
+        Ok(home
+            .join("Library")
+            .join("LaunchAgents")
+            .join(format!("{}.plist", label())))
+    }
+
+    fn launchctl(action: &str, plist: &Path) -> io::Result<()> {
+        let status = Command::new("launchctl")
+            .args([action, "-w"])
+            .arg(plist)
+            .status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "launchctl {action} failed with {status}"
+            )));
+        }
+        Ok(())
+    }
+
+    fn xml_escape(value: &str) -> String {
+        value
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    }
+}
+
+/// windows service registered with the service control manager, the SCM launches
+/// `<exe> service run <path> [options]` which hands control to [run]
+#[cfg(windows)]
+mod platform {
+    use super::*;
+    use std::ffi::{OsStr, OsString};
+    use std::path::PathBuf;
+    use std::sync::{mpsc, OnceLock};
+    use std::thread;
+    use std::time::Duration;
+    use windows_service::service::{
+        ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
+        ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+    };
+    use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+    use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+    use windows_service::{define_windows_service, service_dispatcher};
+
+    use crate::error::Error;
+    use crate::Options;
+
+    const SERVICE_TYPE: ServiceType = ServiceType::OWN_PROCESS;
+
+    /// what the service should watch, the dispatcher entry point can't take arguments
+    static WATCH: OnceLock<(PathBuf, Options)> = OnceLock::new();
+
+    pub fn install(exe: &Path, path: &Path, extra_args: &[String]) -> io::Result<()> {
+        let manager = ServiceManager::local_computer(
+            None::<&str>,
+            ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+        )
+        .map_err(io::Error::other)?;
+
+        let launch_arguments = ["service", "run"]
+            .into_iter()
+            .map(OsString::from)
+            .chain([path.as_os_str().to_owned()])
+            .chain(extra_args.iter().map(OsString::from))
+            .collect();
+
+        let info = ServiceInfo {
+            name: SERVICE_NAME.into(),
+            display_name: "Twitch Screenshot Organizer".into(),
+            service_type: SERVICE_TYPE,
+            start_type: ServiceStartType::AutoStart,
+            error_control: ServiceErrorControl::Normal,
+            executable_path: exe.to_path_buf(),
+            launch_arguments,
+            dependencies: vec![],
+            account_name: None,
+            account_password: None,
+        };
+        let service = manager
+            .create_service(&info, ServiceAccess::CHANGE_CONFIG)
+            .map_err(io::Error::other)?;
+        service
+            .set_description("Moves twitch screenshots into per channel folders")
+            .map_err(io::Error::other)?;
+
+        log::info!("Service {SERVICE_NAME} installed");
+        log::info!("Start it with: twitch-screenshot-organizer service start");
+        Ok(())
+    }
+
+    pub fn uninstall() -> io::Result<()> {
+        let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+            .map_err(io::Error::other)?;
+        let service = manager
+            .open_service(
+                SERVICE_NAME,
+                ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+            )
+            .map_err(io::Error::other)?;
+
+        // marks it for deletion, it goes away once stopped
+        service.delete().map_err(io::Error::other)?;
+        let status = service.query_status().map_err(io::Error::other)?;
+        if status.current_state != ServiceState::Stopped {
+            service.stop().map_err(io::Error::other)?;
+        }
+
+        log::info!("Service {SERVICE_NAME} removed");
+        Ok(())
+    }
+
+    pub fn start() -> io::Result<()> {
+        let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+            .map_err(io::Error::other)?;
+        let service = manager
+            .open_service(SERVICE_NAME, ServiceAccess::START)
+            .map_err(io::Error::other)?;
+        service.start(&[] as &[&OsStr]).map_err(io::Error::other)?;
+        log::info!("Service started");
+        Ok(())
+    }
+
+    /// Entry point when launched by the SCM, blocks until the service is stopped
+    pub fn run(path: PathBuf, options: Options) -> io::Result<()> {
+        WATCH
+            .set((path, options))
+            .expect("service is only run once per process");
+        service_dispatcher::start(SERVICE_NAME, ffi_service_main).map_err(io::Error::other)
+    }
+
+    define_windows_service!(ffi_service_main, service_main);
+
+    fn service_main(_arguments: Vec<OsString>) {
+        if let Err(error) = run_service() {
+            log::error!("Service failed: {error:?}");
+        }
+    }
+
+    fn run_service() -> windows_service::Result<()> {
+        // none when asked to stop, the error when the watcher couldn't be started
+        let (stop_tx, stop_rx) = mpsc::channel::<Option<Error>>();
+
+        let stop = stop_tx.clone();
+        let event_handler = move |control| match control {
+            ServiceControl::Stop => {
+                let _ = stop.send(None);
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        };
+        let status_handle = service_control_handler::register(SERVICE_NAME, event_handler)?;
+        status_handle.set_service_status(status(
+            ServiceState::Running,
+            ServiceControlAccept::STOP,
+            ServiceExitCode::Win32(0),
+        ))?;
+
+        // the watcher never returns on its own unless it can't start ( bad path, config .. )
+        let (path, options) = WATCH.get().expect("run sets what to watch").clone();
+        thread::spawn(move || {
+            if let Err(error) = crate::run(path, true, options) {
+                log::error!("{error}");
+                let _ = stop_tx.send(Some(error));
+            }
+        });
+
+        // the SCM shows the exit code of a service that stopped by itself as failed
+        let exit_code = match stop_rx.recv() {
+            Ok(Some(error)) => ServiceExitCode::ServiceSpecific(error.exit_code() as u32),
+            _ => ServiceExitCode::Win32(0),
+        };
+        status_handle.set_service_status(status(
+            ServiceState::Stopped,
+            ServiceControlAccept::empty(),
+            exit_code,
+        ))?;
+        Ok(())
+    }
+
+    fn status(
+        current_state: ServiceState,
+        controls_accepted: ServiceControlAccept,
+        exit_code: ServiceExitCode,
+    ) -> ServiceStatus {
+        ServiceStatus {
+            service_type: SERVICE_TYPE,
+            current_state,
+            controls_accepted,
+            exit_code,
+            checkpoint: 0,
+            wait_hint: Duration::default(),
+            process_id: None,
+        }
+    }
+}
+
+#[cfg(not(windows))]
+fn no_home() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        "Could not determine home directory",
+    )
+}
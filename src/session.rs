@@ -0,0 +1,119 @@
+use chrono::{NaiveDateTime, TimeDelta};
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::{capture_time, is_screenshot, sync_tools};
+
+/// screenshots further apart than this are considered to be from different streams
+const SESSION_GAP: TimeDelta = TimeDelta::hours(3);
+
+const PREFIX: &str = "screenshots_";
+const NAME_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+
+/// Rewrite the screenshots_<session start>.txt next to `screenshot` for the session it belongs to.
+/// Sessions are rebuilt from the folder contents so files arriving out of order still end up
+/// in the right list.
+pub fn update(screenshot: &Path) -> io::Result<()> {
+    let channel_dir = screenshot
+        .parent()
+        .expect("Screenshot has no parent directory");
+    let Some(captured_at) = screenshot
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(capture_time)
+    else {
+        log::debug!(
+            "No capture time in {}, skipping session file",
+            screenshot.display()
+        );
+        return Ok(());
+    };
+
+    let mut shots = fs::read_dir(channel_dir)?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().map(|ft| ft.is_file()).unwrap_or(false))
+        .filter(|entry| is_screenshot(&entry.path()))
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            Some((capture_time(&name)?, name))
+        })
+        .collect::<Vec<_>>();
+    shots.sort();
+
+    // organized by its decision or OCR, it doesn't look like a screenshot in the folder
+    let Some(session) = session_around(&shots, captured_at) else {
+        log::debug!(
+            "{} isn't named like a screenshot, skipping session file",
+            screenshot.display()
+        );
+        return Ok(());
+    };
+    let (start, end) = (session[0].0, session[session.len() - 1].0);
+
+    let mut contents = format!("# Screenshots from the session starting {start}\n");
+    for (time, name) in session {
+        let offset = (*time - start).num_seconds();
+        let _ = writeln!(
+            contents,
+            "{}:{:02}:{:02} {name}",
+            offset / 3600,
+            offset / 60 % 60,
+            offset % 60
+        );
+    }
+
+    let file_name = format!("{PREFIX}{}.txt", start.format(NAME_FORMAT));
+    sync_tools::write(&channel_dir.join(&file_name), contents.as_bytes())?;
+
+    remove_stale(channel_dir, &file_name, start, end)
+}
+
+/// the run of screenshots without a SESSION_GAP break that contains `captured_at`, none when
+/// there's no screenshot from then
+fn session_around(
+    shots: &[(NaiveDateTime, String)],
+    captured_at: NaiveDateTime,
+) -> Option<&[(NaiveDateTime, String)]> {
+    let index = shots.iter().position(|(time, _)| *time == captured_at)?;
+
+    let mut first = index;
+    while first > 0 && shots[first].0 - shots[first - 1].0 <= SESSION_GAP {
+        first -= 1;
+    }
+    let mut last = index;
+    while last + 1 < shots.len() && shots[last + 1].0 - shots[last].0 <= SESSION_GAP {
+        last += 1;
+    }
+
+    Some(&shots[first..=last])
+}
+
+/// a session can grow backwards when an earlier screenshot shows up later, drop the file
+/// that was written for the old start
+fn remove_stale(
+    channel_dir: &Path,
+    current: &str,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+) -> io::Result<()> {
+    for entry in fs::read_dir(channel_dir)?.filter_map(Result::ok) {
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        if name == current {
+            continue;
+        }
+        let stale = name
+            .strip_prefix(PREFIX)
+            .and_then(|rest| rest.strip_suffix(".txt"))
+            .and_then(|time| NaiveDateTime::parse_from_str(time, NAME_FORMAT).ok())
+            .is_some_and(|time| start <= time && time <= end);
+        if stale {
+            log::debug!("Removing stale session file {name}");
+            fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use std::ffi::OsStr;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::Sharding;
+use crate::{capture_time, is_screenshot, journal, sidecar, thumbnails, Options};
+
+/// left in a channel folder once it's split, new screenshots go straight into the shards
+pub const MARKER: &str = ".sharded";
+
+pub fn is_sharded(channel_dir: &Path) -> bool {
+    channel_dir.join(MARKER).exists()
+}
+
+/// <channel>/<year>/<month>/<file>, none if the capture time can't be parsed from the name
+pub fn shard_path(channel_dir: &Path, file_name: &OsStr) -> Option<PathBuf> {
+    let captured_at = capture_time(&file_name.to_string_lossy())?;
+    Some(
+        channel_dir
+            .join(captured_at.format("%Y").to_string())
+            .join(captured_at.format("%m").to_string())
+            .join(file_name),
+    )
+}
+
+/// Split `channel_dir` into year/month subfolders if it grew past the configured size,
+/// keeping the index in sync and optionally leaving symlinks at the old locations
+pub fn maybe_shard(channel_dir: &Path, options: &Options) -> io::Result<()> {
+    let Some(sharding) = &options.config.sharding else {
+        return Ok(());
+    };
+    if is_sharded(channel_dir) {
+        return Ok(());
+    }
+
+    let count = fs::read_dir(channel_dir)?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|ft| ft.is_file()))
+        .count();
+    if count <= sharding.max_files {
+        return Ok(());
+    }
+
+    // whoever creates the marker does the split, concurrent moves just see it's taken care of
+    match OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(channel_dir.join(MARKER))
+    {
+        Ok(_) => {}
+        Err(error) if error.kind() == io::ErrorKind::AlreadyExists => return Ok(()),
+        Err(error) => return Err(error),
+    }
+
+    log::info!(
+        "{} has {count} files, splitting it into year/month folders",
+        channel_dir.display()
+    );
+    shard(channel_dir, sharding, options)
+}
+
+fn shard(channel_dir: &Path, sharding: &Sharding, options: &Options) -> io::Result<()> {
+    let mut moved = 0;
+    for entry in fs::read_dir(channel_dir)?.filter_map(Result::ok) {
+        let path = entry.path();
+        if !entry.file_type().is_ok_and(|ft| ft.is_file()) || !is_screenshot(&path) {
+            continue;
+        }
+        let Some(target) = shard_path(channel_dir, &entry.file_name()) else {
+            continue;
+        };
+
+        let storage = options.storage();
+        storage.create_dir_all(target.parent().expect("shard has a parent directory"))?;
+        storage.rename(&path, &target)?;
+        journal::moved(&path, &target);
+        if let Some(save_dir) = channel_dir.parent() {
+            thumbnails::relocate(save_dir, &path, &target);
+        }
+        sidecar::relocate(&path, &target);
+        moved += 1;
+
+        if let Some(index) = &options.index {
+            if let Err(error) = index.relocate(&path, &target) {
+                log::error!("Failed to update index for {}: {error:?}", target.display());
+            }
+        }
+        if sharding.symlinks {
+            let relative = target
+                .strip_prefix(channel_dir)
+                .expect("shard is in the channel dir");
+            if let Err(error) = symlink(relative, &path) {
+                log::warn!("Failed to leave a symlink at {}: {error}", path.display());
+            }
+        }
+    }
+
+    log::info!(
+        "Moved {moved} screenshots of {} into year/month folders",
+        channel_dir.display()
+    );
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink(original: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(original, link)
+}
+
+#[cfg(windows)]
+fn symlink(original: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(original, link)
+}
@@ -0,0 +1,47 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::storage::StorageBackend;
+use crate::twitch::Vod;
+
+/// What's known about a screenshot beyond its name, in <screenshot>.json next to it
+#[derive(Serialize, Debug)]
+pub struct Sidecar<'a> {
+    pub channel: &'a str,
+    pub captured_at: NaiveDateTime,
+    pub vod: &'a Vod,
+}
+
+/// <file name>.json, the image extension stays in so it's clear which file it belongs to
+pub fn path_for(screenshot: &Path) -> PathBuf {
+    let mut name = screenshot
+        .file_name()
+        .expect("screenshot has a file name")
+        .to_os_string();
+    name.push(".json");
+    screenshot.with_file_name(name)
+}
+
+pub fn write(storage: &dyn StorageBackend, screenshot: &Path, sidecar: &Sidecar) -> io::Result<()> {
+    let json = serde_json::to_vec_pretty(sidecar)?;
+    storage.write(&path_for(screenshot), &json)
+}
+
+/// Take the sidecar along when a screenshot is moved, if it has one
+pub fn relocate(from: &Path, to: &Path) {
+    let old = path_for(from);
+    if !old.exists() {
+        return;
+    }
+    if let Err(error) = fs::rename(&old, path_for(to)) {
+        log::warn!("Failed to move {}: {error}", old.display());
+    }
+}
+
+/// Drop the sidecar of a screenshot that was deleted, or that it doesn't describe anymore
+pub fn remove(screenshot: &Path) {
+    let _ = fs::remove_file(path_for(screenshot));
+}
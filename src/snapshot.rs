@@ -0,0 +1,245 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::index::{self, Index};
+use crate::plan::SAVE_TO;
+use crate::{activity, collection, journal, lock, s3, sharding, sync_tools};
+
+/// bumped when what's in a snapshot changes in a way older versions can't restore
+const VERSION: u32 = 1;
+
+const MANIFEST: &str = "snapshot.json";
+const CONFIG: &str = "config.toml";
+const INDEX: &str = "index.sqlite";
+/// moves with paths relative to the organized folder, like `journal export` writes them
+const JOURNAL: &str = "journal.jsonl";
+const ACTIVITY: &str = "activity.jsonl";
+/// marker files in the save dir, by their path relative to it
+const LAYOUT: &str = "layout/";
+
+/// What a snapshot is
+#[derive(Serialize, Deserialize, Debug)]
+struct Manifest {
+    version: u32,
+    created_at: DateTime<Local>,
+    /// version of the organizer that made it
+    organizer: String,
+}
+
+/// Write the config, index, journals and layout markers of the folder organized in `path` to the
+/// zip `file`
+pub fn create(path: &Path, file: &Path, config: Option<&Path>) -> io::Result<()> {
+    let save_dir = collection::save_dir(path);
+    let temp = sync_tools::temp_path(file);
+    let result = write(path, &save_dir, &temp, config);
+    if let Err(error) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(error);
+    }
+    fs::rename(&temp, file)?;
+    println!(
+        "Saved a snapshot of {} to {}",
+        path.display(),
+        file.display()
+    );
+    Ok(())
+}
+
+/// Put what's in the snapshot `file` back for the folder organized in `path` ( with its channel
+/// folders in `dest` if given ) and the config at `config`. Files that are replaced are kept
+/// as <name>.bak, journal entries are added to the ones that are there.
+pub fn restore(file: &Path, path: &Path, dest: Option<&Path>, config: &Path) -> io::Result<()> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(file)?))?;
+    let manifest: Manifest = serde_json::from_reader(archive.by_name(MANIFEST)?)?;
+    if manifest.version > VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} was made by version {} of the organizer, update to restore it",
+                file.display(),
+                manifest.organizer
+            ),
+        ));
+    }
+    log::info!(
+        "Restoring a snapshot from {} made by version {}",
+        manifest.created_at,
+        manifest.organizer
+    );
+    let save_dir = dest.map_or_else(|| save_dir(path), Path::to_path_buf);
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let name = entry.name()?.to_string();
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+
+        match name.as_str() {
+            MANIFEST => {}
+            CONFIG => replace(config, &contents)?,
+            INDEX => {
+                // leftovers of the old database would be taken for part of this one
+                for suffix in ["-wal", "-shm"] {
+                    let _ = fs::remove_file(save_dir.join(format!("{}{suffix}", index::FILE_NAME)));
+                }
+                replace(&save_dir.join(index::FILE_NAME), &contents)?;
+            }
+            JOURNAL => {
+                let imported = journal::import_from(contents.as_slice(), path, dest)?;
+                println!("Imported {imported} journal entries");
+            }
+            ACTIVITY => append_new(&activity::journal_path(), &contents)?,
+            _ => match name.strip_prefix(LAYOUT).and_then(relative) {
+                Some(relative) => replace(&save_dir.join(relative), &contents)?,
+                None => log::warn!("Skipping {name}, it isn't part of a snapshot"),
+            },
+        }
+    }
+
+    println!("Restored {} into {}", file.display(), save_dir.display());
+    Ok(())
+}
+
+fn write(path: &Path, save_dir: &Path, temp: &Path, config: Option<&Path>) -> io::Result<()> {
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(temp)?));
+    let options = SimpleFileOptions::default();
+
+    zip.start_file(MANIFEST, options)?;
+    serde_json::to_writer_pretty(
+        &mut zip,
+        &Manifest {
+            version: VERSION,
+            created_at: Local::now(),
+            organizer: env!("CARGO_PKG_VERSION").to_string(),
+        },
+    )?;
+
+    if let Some(config) = config {
+        add_file(&mut zip, CONFIG, config)?;
+    }
+
+    if let Some(index) = Index::open_existing(save_dir)? {
+        let backup = sync_tools::temp_path(&save_dir.join(INDEX));
+        let _ = fs::remove_file(&backup);
+        let added = index
+            .back_up(&backup)
+            .and_then(|()| add_file(&mut zip, INDEX, &backup));
+        let _ = fs::remove_file(&backup);
+        added?;
+    }
+
+    let (moves, _) = journal::exported(path)?;
+    zip.start_file(JOURNAL, options)?;
+    zip.write_all(moves.as_bytes())?;
+
+    let activity = activity::journal_path();
+    if activity.is_file() {
+        add_file(&mut zip, ACTIVITY, &activity)?;
+    }
+
+    for marker in markers(save_dir) {
+        let relative = marker
+            .strip_prefix(save_dir)
+            .expect("marker is in the save dir")
+            .to_string_lossy()
+            .replace('\\', "/");
+        add_file(&mut zip, &format!("{LAYOUT}{relative}"), &marker)?;
+    }
+
+    let mut file = zip.finish()?;
+    file.flush()?;
+    file.get_ref().sync_all()
+}
+
+fn add_file<W: Write + io::Seek>(
+    zip: &mut ZipWriter<W>,
+    name: &str,
+    file: &Path,
+) -> io::Result<()> {
+    zip.start_file(name, SimpleFileOptions::default())?;
+    io::copy(&mut File::open(file)?, zip)?;
+    Ok(())
+}
+
+/// the save dir for `path` on a machine where nothing was organized into it yet
+fn save_dir(path: &Path) -> PathBuf {
+    if path.file_name().is_some_and(|name| name == SAVE_TO) {
+        path.to_path_buf()
+    } else {
+        path.join(SAVE_TO)
+    }
+}
+
+/// files in the save dir that say how it's laid out and what happened to it: shared with other
+/// machines, what's uploaded and which channel folders are split
+fn markers(save_dir: &Path) -> Vec<PathBuf> {
+    let mut markers = [lock::FILE_NAME, s3::STATE_FILE]
+        .iter()
+        .map(|name| save_dir.join(name))
+        .collect::<Vec<_>>();
+    if let Ok(entries) = fs::read_dir(save_dir) {
+        markers.extend(
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path().join(sharding::MARKER)),
+        );
+    }
+    markers.retain(|marker| marker.is_file());
+    markers
+}
+
+/// a path in the snapshot as one in the save dir, none if it would point outside of it
+fn relative(name: &str) -> Option<PathBuf> {
+    let parts = name.split('/').collect::<Vec<_>>();
+    if parts
+        .iter()
+        .any(|part| part.is_empty() || *part == "." || *part == ".." || part.contains('\\'))
+    {
+        return None;
+    }
+    Some(parts.iter().collect())
+}
+
+/// add the lines of `contents` that aren't in `path` yet, restoring on the same machine twice
+/// shouldn't count sessions twice
+fn append_new(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let existing = match fs::read_to_string(path) {
+        Ok(existing) => existing,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(error),
+    };
+    let existing = existing.lines().collect::<HashSet<_>>();
+    let mut new = String::new();
+    for line in String::from_utf8_lossy(contents).lines() {
+        if !line.trim().is_empty() && !existing.contains(line) {
+            new.push_str(line);
+            new.push('\n');
+        }
+    }
+    if new.is_empty() {
+        return Ok(());
+    }
+    fs::create_dir_all(path.parent().expect("journal is in the state dir"))?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(new.as_bytes())
+}
+
+/// write `contents` to `path`, keeping what was there as <name>.bak
+fn replace(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if path.exists() {
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(".bak");
+        fs::rename(path, backup)?;
+    }
+    fs::create_dir_all(path.parent().expect("restored file has a parent directory"))?;
+    sync_tools::write(path, contents)
+}
@@ -0,0 +1,211 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+use crate::config::Labels;
+use crate::search::{self, Query};
+use crate::{collection, index};
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default)]
+pub enum Format {
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(Serialize, Debug, Default)]
+struct ChannelStats {
+    channel: String,
+    /// from [labels] in the config
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    count: u64,
+    bytes: u64,
+    first: Option<NaiveDate>,
+    last: Option<NaiveDate>,
+    /// YYYY-MM to count and size, screenshots without a parseable date are left out
+    per_month: BTreeMap<String, MonthStats>,
+}
+
+#[derive(Serialize, Debug, Default, Clone, Copy)]
+struct MonthStats {
+    count: u64,
+    bytes: u64,
+}
+
+#[derive(Serialize, Debug)]
+struct Report {
+    channels: Vec<ChannelStats>,
+    total: ChannelStats,
+}
+
+/// Print counts, sizes, date ranges and per month activity for every channel under `path`
+pub fn run(path: &Path, format: Format, labels: &Labels) -> io::Result<()> {
+    let labels = index::labels(&collection::save_dir(path), labels);
+    let mut channels: BTreeMap<String, ChannelStats> = BTreeMap::new();
+    let mut total = ChannelStats {
+        channel: "total".to_string(),
+        ..ChannelStats::default()
+    };
+
+    for screenshot in search::find(path, &Query::default())? {
+        let bytes = match screenshot.path.metadata() {
+            Ok(metadata) => metadata.len(),
+            Err(error) => {
+                log::warn!("Skipping {}: {error}", screenshot.path.display());
+                continue;
+            }
+        };
+        let date = screenshot.captured_at.map(|time| time.date());
+
+        let stats = channels
+            .entry(screenshot.channel.clone())
+            .or_insert_with(|| ChannelStats {
+                channel: screenshot.channel.clone(),
+                label: Some(labels.of(&screenshot.channel))
+                    .filter(|label| *label != screenshot.channel)
+                    .map(str::to_string),
+                ..ChannelStats::default()
+            });
+        stats.add(date, bytes);
+        total.add(date, bytes);
+    }
+
+    let report = Report {
+        channels: channels.into_values().collect(),
+        total,
+    };
+    match format {
+        Format::Table => print_table(&report),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        Format::Csv => print_csv(&report),
+    }
+    Ok(())
+}
+
+impl ChannelStats {
+    /// how it's shown in the table
+    fn name(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.channel)
+    }
+
+    fn add(&mut self, date: Option<NaiveDate>, bytes: u64) {
+        self.count += 1;
+        self.bytes += bytes;
+        if let Some(date) = date {
+            self.first = Some(self.first.map_or(date, |first| first.min(date)));
+            self.last = Some(self.last.map_or(date, |last| last.max(date)));
+            let month = self
+                .per_month
+                .entry(date.format("%Y-%m").to_string())
+                .or_default();
+            month.count += 1;
+            month.bytes += bytes;
+        }
+    }
+}
+
+fn print_table(report: &Report) {
+    let width = report
+        .channels
+        .iter()
+        .map(|stats| stats.name().chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("channel".len());
+
+    println!(
+        "{:<width$}  {:>7}  {:>10}  {:<10}  {:<10}",
+        "channel", "count", "size", "first", "last"
+    );
+    for stats in report.channels.iter().chain([&report.total]) {
+        println!(
+            "{:<width$}  {:>7}  {:>10}  {:<10}  {:<10}",
+            stats.name(),
+            stats.count,
+            human_size(stats.bytes),
+            date_or_dash(stats.first),
+            date_or_dash(stats.last),
+        );
+    }
+
+    println!(
+        "\n{:<width$}  {:<7}  {:>7}  {:>10}",
+        "channel", "month", "count", "size"
+    );
+    for stats in &report.channels {
+        for (month, month_stats) in &stats.per_month {
+            println!(
+                "{:<width$}  {:<7}  {:>7}  {:>10}",
+                stats.name(),
+                month,
+                month_stats.count,
+                human_size(month_stats.bytes)
+            );
+        }
+    }
+}
+
+/// one row per channel and month, the format spreadsheets deal with best. Every channel ends
+/// with a row for all of it without a month, and the total comes last, those have the dates of
+/// the first and last screenshot.
+fn print_csv(report: &Report) {
+    println!("channel,month,count,bytes,first,last");
+    for stats in &report.channels {
+        for (month, month_stats) in &stats.per_month {
+            println!(
+                "{},{month},{},{},,",
+                csv_field(&stats.channel),
+                month_stats.count,
+                month_stats.bytes
+            );
+        }
+        print_csv_summary(stats);
+    }
+    print_csv_summary(&report.total);
+}
+
+fn print_csv_summary(stats: &ChannelStats) {
+    let date = |date: Option<NaiveDate>| date.map(|date| date.to_string()).unwrap_or_default();
+    println!(
+        "{},,{},{},{},{}",
+        csv_field(&stats.channel),
+        stats.count,
+        stats.bytes,
+        date(stats.first),
+        date(stats.last)
+    );
+}
+
+fn date_or_dash(date: Option<NaiveDate>) -> String {
+    date.map(|date| date.to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// 1234567 -> 1.2 MB
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// quote a field if it would break the row
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
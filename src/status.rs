@@ -0,0 +1,113 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::{activity, control, delayed};
+
+static LAST_EVENT: Mutex<Option<DateTime<Local>>> = Mutex::new(None);
+static LAST_ERROR: Mutex<Option<Problem>> = Mutex::new(None);
+
+/// How the running watcher is doing, what it answers `status` with
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Health {
+    pub path: PathBuf,
+    pub paused: bool,
+    pub last_event: Option<DateTime<Local>>,
+    /// screenshots waiting to be written out or being moved
+    pub pending: usize,
+    pub last_error: Option<Problem>,
+    pub organized: u64,
+    pub failed: u64,
+    pub restarts: u32,
+}
+
+/// Something that went wrong in the watcher
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Problem {
+    pub at: DateTime<Local>,
+    pub message: String,
+}
+
+/// A watcher event came in
+pub fn event() {
+    *LAST_EVENT.lock().unwrap() = Some(Local::now());
+}
+
+/// Remember `message` as the last thing that went wrong
+pub fn error(message: impl ToString) {
+    *LAST_ERROR.lock().unwrap() = Some(Problem {
+        at: Local::now(),
+        message: message.to_string(),
+    });
+}
+
+/// How the watcher of `path` is doing right now
+pub fn health(path: &Path, restarts: u32) -> Health {
+    let (organized, failed) = activity::counts();
+    Health {
+        path: path.canonicalize().unwrap_or_else(|_| path.to_path_buf()),
+        paused: control::is_paused(),
+        last_event: *LAST_EVENT.lock().unwrap(),
+        pending: delayed::pending(),
+        last_error: LAST_ERROR.lock().unwrap().clone(),
+        organized,
+        failed,
+        restarts,
+    }
+}
+
+/// Print how the watcher of `path` is doing, fails if none is running on it so cron can tell
+/// when it silently stopped
+pub fn run(path: &Path) -> io::Result<()> {
+    let answer = control::send(control::Request::Health)?;
+    let health: Health = serde_json::from_str(&answer).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("the watcher is from before `status`: {}", answer.trim()),
+        )
+    })?;
+    let path = path.canonicalize()?;
+    if health.path != path {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "the watcher is running on {}, not {}",
+                health.path.display(),
+                path.display()
+            ),
+        ));
+    }
+
+    println!(
+        "A watcher is running on {}{}",
+        path.display(),
+        if health.paused { ", paused" } else { "" }
+    );
+    match health.last_event {
+        Some(at) => println!("Last event: {}", ago(at)),
+        None => println!("Last event: none yet"),
+    }
+    println!("Waiting to be moved: {}", health.pending);
+    println!(
+        "Organized {} screenshot(s), {} failed, the watcher was restarted {} time(s)",
+        health.organized, health.failed, health.restarts
+    );
+    match health.last_error {
+        Some(problem) => println!("Last error: {} ( {} )", problem.message, ago(problem.at)),
+        None => println!("Last error: none"),
+    }
+    Ok(())
+}
+
+fn ago(at: DateTime<Local>) -> String {
+    let seconds = (Local::now() - at).num_seconds().max(0);
+    let ago = match seconds {
+        0..60 => format!("{seconds}s"),
+        60..3600 => format!("{}m", seconds / 60),
+        3600..86400 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    };
+    format!("{} ( {ago} ago )", at.format("%Y-%m-%d %H:%M:%S"))
+}
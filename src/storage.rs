@@ -0,0 +1,175 @@
+#[cfg(test)]
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+#[cfg(test)]
+use std::{collections::BTreeMap, path::PathBuf, sync::Mutex};
+
+use crate::plan::Transfer;
+use crate::{index, removal, sync_tools};
+
+/// What's known about a file in storage
+#[derive(Debug, Clone, Copy)]
+pub struct Stat {
+    pub len: u64,
+}
+
+/// Where organized screenshots are put. Everything the organizer does to the destination goes
+/// through one of these, screenshots always come from local files.
+pub trait StorageBackend: fmt::Debug + Send + Sync {
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()>;
+
+    /// Put `contents` at `path` so it shows up complete or not at all
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// Move a file within the destination
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    fn delete(&self, path: &Path) -> io::Result<()>;
+
+    /// Size of `path`, none when there's nothing there
+    fn stat(&self, path: &Path) -> io::Result<Option<Stat>>;
+
+    /// Hex sha256 of the file at `path`, none when the backend can't tell without downloading it
+    fn hash(&self, path: &Path) -> io::Result<Option<String>>;
+
+    /// Move the local file `source` to `target`
+    fn move_in(&self, source: &Path, target: &Path) -> io::Result<()>;
+
+    /// Put the contents of the local file `source` at `target` and leave the source alone,
+    /// returns whether it's linked to the source or a copy
+    fn copy_in(&self, source: &Path, target: &Path) -> io::Result<Transfer>;
+}
+
+/// The local filesystem, what's used unless something else is set up
+#[derive(Debug, Default)]
+pub struct LocalFs;
+
+impl StorageBackend for LocalFs {
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        sync_tools::write(path, contents)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn delete(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn stat(&self, path: &Path) -> io::Result<Option<Stat>> {
+        match path.metadata() {
+            Ok(metadata) => Ok(Some(Stat {
+                len: metadata.len(),
+            })),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn hash(&self, path: &Path) -> io::Result<Option<String>> {
+        index::hash_file(path).map(Some)
+    }
+
+    fn move_in(&self, source: &Path, target: &Path) -> io::Result<()> {
+        // the rename would replace it for good
+        if removal::uses_trash() && target.is_file() {
+            log::info!("Moving {} out of the way to the trash", target.display());
+            removal::trash(target)?;
+        }
+        fs::rename(source, target)
+    }
+
+    /// hard linked when it's on the same filesystem, nothing is stored twice then
+    fn copy_in(&self, source: &Path, target: &Path) -> io::Result<Transfer> {
+        if fs::hard_link(source, target).is_ok() {
+            return Ok(Transfer::Linked);
+        }
+        sync_tools::copy(source, target).map(|()| Transfer::Copied)
+    }
+}
+
+/// Files kept in memory, for tests that shouldn't depend on what's on disk
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct Memory {
+    pub files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+    /// moving a source fails like it does out of a read-only folder
+    pub read_only_sources: bool,
+}
+
+#[cfg(test)]
+impl Memory {
+    pub fn with_file(self, path: &Path, contents: &[u8]) -> Self {
+        self.files
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), contents.to_vec());
+        self
+    }
+
+    pub fn contents(&self, path: &Path) -> Option<Vec<u8>> {
+        self.files.lock().unwrap().get(path).cloned()
+    }
+}
+
+#[cfg(test)]
+impl StorageBackend for Memory {
+    fn create_dir_all(&self, _dir: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.files
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), contents.to_vec());
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut files = self.files.lock().unwrap();
+        let contents = files.remove(from).ok_or(io::ErrorKind::NotFound)?;
+        files.insert(to.to_path_buf(), contents);
+        Ok(())
+    }
+
+    fn delete(&self, path: &Path) -> io::Result<()> {
+        let removed = self.files.lock().unwrap().remove(path);
+        removed.map(drop).ok_or(io::ErrorKind::NotFound.into())
+    }
+
+    fn stat(&self, path: &Path) -> io::Result<Option<Stat>> {
+        let files = self.files.lock().unwrap();
+        Ok(files.get(path).map(|contents| Stat {
+            len: contents.len() as u64,
+        }))
+    }
+
+    fn hash(&self, path: &Path) -> io::Result<Option<String>> {
+        let files = self.files.lock().unwrap();
+        Ok(files
+            .get(path)
+            .map(|contents| hex::encode(Sha256::digest(contents))))
+    }
+
+    fn move_in(&self, source: &Path, target: &Path) -> io::Result<()> {
+        if self.read_only_sources {
+            return Err(io::ErrorKind::PermissionDenied.into());
+        }
+        self.write(target, &fs::read(source)?)?;
+        fs::remove_file(source)
+    }
+
+    fn copy_in(&self, source: &Path, target: &Path) -> io::Result<Transfer> {
+        self.write(target, &fs::read(source)?)
+            .map(|()| Transfer::Copied)
+    }
+}
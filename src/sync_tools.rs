@@ -0,0 +1,90 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// suffix of files this program is still writing, renamed to their real name once complete
+const TEMP_SUFFIX: &str = ".tso-tmp";
+
+/// Files sync tools ( and browsers ) write while something is still on its way. They're never
+/// screenshots and never touched, the tool renames or removes them itself.
+pub fn is_temp(file_name: &str) -> bool {
+    let lower = file_name.to_lowercase();
+    // syncthing, .syncthing.<name>.tmp and ~syncthing~<name>.tmp on windows
+    (lower.starts_with(".syncthing.") || lower.starts_with("~syncthing~")) && lower.ends_with(".tmp")
+        // onedrive and office lock files
+        || lower.starts_with("~$")
+        // nextcloud / owncloud, .<name>.~<random>
+        || (lower.starts_with('.') && lower.contains(".~"))
+        // libreoffice / dropbox style lock files
+        || lower.starts_with(".~lock.")
+        // resilio sync
+        || lower.ends_with(".!sync")
+        || [".tmp", ".part", ".partial", ".crdownload", ".download", TEMP_SUFFIX]
+            .iter()
+            .any(|suffix| lower.ends_with(suffix))
+}
+
+/// The sync tool that is bringing in a file at `target` right now, going by its temp files
+pub fn incoming(target: &Path) -> Option<&'static str> {
+    let dir = target.parent()?;
+    let name = target.file_name()?.to_string_lossy();
+    [
+        (format!(".syncthing.{name}.tmp"), "Syncthing"),
+        (format!("~syncthing~{name}.tmp"), "Syncthing"),
+        (format!("{name}.!sync"), "Resilio Sync"),
+        (format!("{name}.part"), "a download"),
+    ]
+    .into_iter()
+    .find(|(temp, _)| dir.join(temp).exists())
+    .map(|(_, tool)| tool)
+    .or_else(|| {
+        // nextcloud adds a random suffix, have to look
+        let prefix = format!(".{name}.~");
+        fs::read_dir(dir)
+            .ok()?
+            .filter_map(Result::ok)
+            .any(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+            .then_some("Nextcloud")
+    })
+}
+
+/// Error for writing to `target` while a sync tool is still bringing a file in there
+pub fn check_incoming(target: &Path) -> io::Result<()> {
+    match incoming(target) {
+        Some(tool) => Err(io::Error::new(
+            io::ErrorKind::WouldBlock,
+            format!("{tool} is still writing {}", target.display()),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// hidden temp file next to `target`, sync tools leave dotfiles that are still changing alone
+/// and [is_temp] keeps this program off it
+pub fn temp_path(target: &Path) -> PathBuf {
+    let name = target
+        .file_name()
+        .expect("target has a file name")
+        .to_string_lossy();
+    target.with_file_name(format!(".{name}{TEMP_SUFFIX}"))
+}
+
+/// Write `contents` to `target` in one go as far as anyone watching the folder can tell
+pub fn write(target: &Path, contents: &[u8]) -> io::Result<()> {
+    let temp = temp_path(target);
+    fs::write(&temp, contents)
+        .and_then(|()| fs::rename(&temp, target))
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&temp);
+        })
+}
+
+/// Copy `source` to `target` through a temp file, so a half copied screenshot never shows up
+pub fn copy(source: &Path, target: &Path) -> io::Result<()> {
+    let temp = temp_path(target);
+    fs::copy(source, &temp)
+        .and_then(|_| fs::rename(&temp, target))
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&temp);
+        })
+}
@@ -0,0 +1,203 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::ImageFormat;
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::animated;
+use crate::collection;
+use crate::config::{ThumbnailFormat, Thumbnails};
+use crate::sync_tools;
+
+/// lives in the save dir, mirrors the channel folders
+const DIR: &str = ".thumbs";
+
+const FORMATS: [ThumbnailFormat; 2] = [ThumbnailFormat::Jpeg, ThumbnailFormat::Webp];
+
+impl ThumbnailFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "jpg",
+            ThumbnailFormat::Webp => "webp",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "image/jpeg",
+            ThumbnailFormat::Webp => "image/webp",
+        }
+    }
+}
+
+/// Make sure the thumbnail of `screenshot` is there and newer than the screenshot
+pub fn update(save_dir: &Path, screenshot: &Path, settings: &Thumbnails) -> io::Result<PathBuf> {
+    let thumbnail = path_for(save_dir, screenshot, settings.format)?;
+    if is_fresh(&thumbnail, screenshot) {
+        return Ok(thumbnail);
+    }
+
+    fs::create_dir_all(
+        thumbnail
+            .parent()
+            .expect("thumbnail has a parent directory"),
+    )?;
+    sync_tools::write(&thumbnail, &render(screenshot, settings)?)?;
+    log::debug!("Thumbnail written to {}", thumbnail.display());
+    Ok(thumbnail)
+}
+
+/// An up to date thumbnail of `screenshot` in any format, if one was generated
+pub fn existing(save_dir: &Path, screenshot: &Path) -> Option<(PathBuf, ThumbnailFormat)> {
+    FORMATS.into_iter().find_map(|format| {
+        let thumbnail = path_for(save_dir, screenshot, format).ok()?;
+        is_fresh(&thumbnail, screenshot).then_some((thumbnail, format))
+    })
+}
+
+/// Downscaled and encoded `screenshot`, the first frame of animated ones
+pub fn render(screenshot: &Path, settings: &Thumbnails) -> io::Result<Vec<u8>> {
+    let image = animated::open(screenshot)?;
+    let thumbnail = image.thumbnail(settings.width, settings.height);
+    let mut encoded = Cursor::new(Vec::new());
+    let result = match settings.format {
+        ThumbnailFormat::Jpeg => {
+            thumbnail
+                .into_rgb8()
+                .write_with_encoder(JpegEncoder::new_with_quality(
+                    &mut encoded,
+                    settings.quality.clamp(1, 100),
+                ))
+        }
+        ThumbnailFormat::Webp => thumbnail
+            .into_rgba8()
+            .write_to(&mut encoded, ImageFormat::WebP),
+    };
+    result.map_err(io::Error::other)?;
+    Ok(encoded.into_inner())
+}
+
+/// Take the thumbnails along when a screenshot moves within the save dir
+pub fn relocate(save_dir: &Path, from: &Path, to: &Path) {
+    for format in FORMATS {
+        let (Ok(old), Ok(new)) = (
+            path_for(save_dir, from, format),
+            path_for(save_dir, to, format),
+        ) else {
+            continue;
+        };
+        if !old.exists() {
+            continue;
+        }
+        let result = fs::create_dir_all(new.parent().expect("thumbnail has a parent directory"))
+            .and_then(|()| fs::rename(&old, &new));
+        if let Err(error) = result {
+            log::warn!("Failed to move thumbnail {}: {error}", old.display());
+        }
+    }
+}
+
+/// Drop the thumbnails of a screenshot that was deleted
+pub fn remove(save_dir: &Path, screenshot: &Path) {
+    for format in FORMATS {
+        if let Ok(thumbnail) = path_for(save_dir, screenshot, format) {
+            let _ = fs::remove_file(thumbnail);
+        }
+    }
+}
+
+/// Generate every missing or outdated thumbnail under `path` and remove the ones whose
+/// screenshot is gone
+pub fn sync(path: &Path, settings: &Thumbnails) -> io::Result<()> {
+    let save_dir = collection::save_dir(path);
+    let mut generated = 0;
+    let mut failed = 0;
+    for screenshot in collection::walk(&save_dir) {
+        let fresh = path_for(&save_dir, &screenshot.path, settings.format)
+            .is_ok_and(|thumbnail| is_fresh(&thumbnail, &screenshot.path));
+        if fresh {
+            continue;
+        }
+        match update(&save_dir, &screenshot.path, settings) {
+            Ok(_) => generated += 1,
+            Err(error) => {
+                log::error!(
+                    "Failed to make a thumbnail of {}: {error}",
+                    screenshot.path.display()
+                );
+                failed += 1;
+            }
+        }
+    }
+
+    let thumbs = save_dir.join(DIR);
+    let mut removed = 0;
+    for entry in WalkDir::new(&thumbs)
+        .contents_first(true)
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        if entry.file_type().is_dir() {
+            // only goes through when it's empty
+            let _ = fs::remove_dir(entry.path());
+            continue;
+        }
+        if sync_tools::is_temp(&entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(&thumbs)
+            .expect("walked path is under .thumbs");
+        let orphaned = entry.path().extension() != Some(settings.format.extension().as_ref())
+            || !crate::EXTENSIONS
+                .iter()
+                .any(|extension| save_dir.join(relative).with_extension(extension).is_file());
+        if orphaned && fs::remove_file(entry.path()).is_ok() {
+            removed += 1;
+        }
+    }
+
+    println!("Generated {generated} thumbnail(s), removed {removed} stale one(s), {failed} failed");
+    Ok(())
+}
+
+/// .thumbs/<channel>/<file>.<jpg|webp> for the screenshot at <channel>/<file>.<png|...>
+fn path_for(save_dir: &Path, screenshot: &Path, format: ThumbnailFormat) -> io::Result<PathBuf> {
+    let relative = relative(save_dir, screenshot).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not in {}", screenshot.display(), save_dir.display()),
+        )
+    })?;
+    Ok(save_dir
+        .join(DIR)
+        .join(relative)
+        .with_extension(format.extension()))
+}
+
+/// `screenshot` relative to `save_dir`, resolving symlinks only when the paths don't line up as
+/// they are
+fn relative(save_dir: &Path, screenshot: &Path) -> Option<PathBuf> {
+    if let Ok(relative) = screenshot.strip_prefix(save_dir) {
+        return Some(relative.to_path_buf());
+    }
+    let save_dir = save_dir.canonicalize().ok()?;
+    let parent = screenshot.parent()?.canonicalize().ok()?;
+    parent
+        .join(screenshot.file_name()?)
+        .strip_prefix(save_dir)
+        .ok()
+        .map(Path::to_path_buf)
+}
+
+fn is_fresh(thumbnail: &Path, screenshot: &Path) -> bool {
+    match (
+        thumbnail.metadata().and_then(|m| m.modified()),
+        screenshot.metadata().and_then(|m| m.modified()),
+    ) {
+        (Ok(thumbnail), Ok(screenshot)) => thumbnail >= screenshot,
+        _ => false,
+    }
+}
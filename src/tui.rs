@@ -0,0 +1,403 @@
+use chrono::NaiveDate;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::collection::{self, Screenshot};
+use crate::index::Index;
+use crate::removal;
+use crate::search::{self, Query};
+use crate::sharding;
+use crate::sidecar;
+use crate::stats::human_size;
+use crate::thumbnails;
+
+const HELP: &str = "enter: open  esc: back  o: view  d: delete  t: re-tag  q: quit";
+
+/// Browse the organized screenshots under `path` channel by channel and day by day
+pub fn run(path: &Path) -> io::Result<()> {
+    let save_dir = collection::save_dir(path);
+    let mut app = App {
+        index: Index::open_existing(&save_dir)?,
+        screenshots: search::find(path, &Query::default())?,
+        save_dir,
+        level: Level::Channels,
+        channel: String::new(),
+        date: None,
+        states: Default::default(),
+        mode: Mode::Browse,
+        status: HELP.to_string(),
+    };
+    app.states[0].select_first();
+
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Level {
+    Channels,
+    Dates,
+    Files,
+}
+
+enum Mode {
+    Browse,
+    ConfirmDelete,
+    /// typing the channel the selected screenshot really belongs to
+    Retag(String),
+}
+
+struct App {
+    save_dir: PathBuf,
+    index: Option<Index>,
+    screenshots: Vec<Screenshot>,
+    level: Level,
+    /// the channel and day that were drilled into
+    channel: String,
+    date: Option<NaiveDate>,
+    /// selection of each level, kept when going back up
+    states: [ListState; 3],
+    mode: Mode,
+    status: String,
+}
+
+impl App {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match &mut self.mode {
+                Mode::Browse => match key.code {
+                    KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Down | KeyCode::Char('j') => self.state().select_next(),
+                    KeyCode::Up | KeyCode::Char('k') => self.state().select_previous(),
+                    KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => self.enter(),
+                    KeyCode::Esc | KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') => {
+                        self.back()
+                    }
+                    KeyCode::Char('o') => self.open(),
+                    KeyCode::Char('d') if self.selected_file().is_some() => {
+                        self.mode = Mode::ConfirmDelete;
+                    }
+                    KeyCode::Char('t') if self.selected_file().is_some() => {
+                        self.mode = Mode::Retag(String::new());
+                    }
+                    _ => {}
+                },
+                Mode::ConfirmDelete => {
+                    self.mode = Mode::Browse;
+                    if key.code == KeyCode::Char('y') {
+                        self.delete();
+                    } else {
+                        self.status = HELP.to_string();
+                    }
+                }
+                Mode::Retag(channel) => match key.code {
+                    KeyCode::Char(c) => channel.push(c),
+                    KeyCode::Backspace => {
+                        channel.pop();
+                    }
+                    KeyCode::Esc => {
+                        self.mode = Mode::Browse;
+                        self.status = HELP.to_string();
+                    }
+                    KeyCode::Enter => {
+                        let channel = channel.trim().to_string();
+                        self.mode = Mode::Browse;
+                        if !channel.is_empty() {
+                            self.retag(&channel);
+                        }
+                    }
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [list, preview] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(main);
+
+        let title = match self.level {
+            Level::Channels => "Channels".to_string(),
+            Level::Dates => self.channel.clone(),
+            Level::Files => format!("{} / {}", self.channel, date_label(self.date)),
+        };
+        let items = self.items();
+        let level = self.level as usize;
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title(title))
+                .highlight_style(Style::new().reversed()),
+            list,
+            &mut self.states[level],
+        );
+        frame.render_widget(
+            Paragraph::new(self.preview()).block(Block::bordered().title("Preview")),
+            preview,
+        );
+
+        let status_line = match &self.mode {
+            Mode::Browse => Line::from(self.status.as_str()),
+            Mode::ConfirmDelete => Line::from("Delete this screenshot? y/n").bold(),
+            Mode::Retag(channel) => Line::from(format!("Move to channel: {channel}_")).bold(),
+        };
+        frame.render_widget(status_line, status);
+    }
+
+    fn state(&mut self) -> &mut ListState {
+        &mut self.states[self.level as usize]
+    }
+
+    fn channels(&self) -> Vec<(String, usize)> {
+        let mut channels = BTreeMap::new();
+        for screenshot in &self.screenshots {
+            *channels.entry(screenshot.channel.clone()).or_insert(0) += 1;
+        }
+        channels.into_iter().collect()
+    }
+
+    /// days with screenshots of `channel`, unknown dates last
+    fn dates(&self, channel: &str) -> Vec<(Option<NaiveDate>, usize)> {
+        let mut dates = BTreeMap::new();
+        for screenshot in self.screenshots.iter().filter(|s| s.channel == channel) {
+            let date = screenshot.captured_at.map(|time| time.date());
+            *dates.entry((date.is_none(), date)).or_insert(0) += 1;
+        }
+        dates
+            .into_iter()
+            .map(|((_, date), count)| (date, count))
+            .collect()
+    }
+
+    fn files(&self, channel: &str, date: Option<NaiveDate>) -> Vec<&Screenshot> {
+        self.screenshots
+            .iter()
+            .filter(|s| s.channel == channel && s.captured_at.map(|time| time.date()) == date)
+            .collect()
+    }
+
+    fn items(&self) -> Vec<String> {
+        match self.level {
+            Level::Channels => self
+                .channels()
+                .into_iter()
+                .map(|(channel, count)| format!("{channel} ({count})"))
+                .collect(),
+            Level::Dates => self
+                .dates(&self.channel)
+                .into_iter()
+                .map(|(date, count)| format!("{} ({count})", date_label(date)))
+                .collect(),
+            Level::Files => self
+                .files(&self.channel, self.date)
+                .into_iter()
+                .map(Screenshot::file_name)
+                .collect(),
+        }
+    }
+
+    /// what's under the highlighted entry, one level down
+    fn preview(&self) -> Vec<Line<'_>> {
+        let selected = self.states[self.level as usize].selected();
+        match self.level {
+            Level::Channels => {
+                let Some((channel, _)) = selected.and_then(|i| self.channels().get(i).cloned())
+                else {
+                    return Vec::new();
+                };
+                self.dates(&channel)
+                    .into_iter()
+                    .map(|(date, count)| Line::from(format!("{} ({count})", date_label(date))))
+                    .collect()
+            }
+            Level::Dates => {
+                let Some((date, _)) =
+                    selected.and_then(|i| self.dates(&self.channel).get(i).cloned())
+                else {
+                    return Vec::new();
+                };
+                self.files(&self.channel, date)
+                    .into_iter()
+                    .map(|screenshot| Line::from(screenshot.file_name()))
+                    .collect()
+            }
+            Level::Files => {
+                let Some(screenshot) = self.selected_file() else {
+                    return Vec::new();
+                };
+                let size = screenshot
+                    .path
+                    .metadata()
+                    .map(|metadata| human_size(metadata.len()))
+                    .unwrap_or_else(|_| "-".to_string());
+                vec![
+                    Line::from(screenshot.path.display().to_string()),
+                    Line::from(format!("channel:  {}", screenshot.channel)),
+                    Line::from(format!(
+                        "captured: {}",
+                        screenshot
+                            .captured_at
+                            .map(|time| time.to_string())
+                            .unwrap_or_else(|| "-".to_string())
+                    )),
+                    Line::from(format!("size:     {size}")),
+                ]
+            }
+        }
+    }
+
+    fn selected_file(&self) -> Option<&Screenshot> {
+        if self.level != Level::Files {
+            return None;
+        }
+        let selected = self.states[Level::Files as usize].selected()?;
+        self.files(&self.channel, self.date).get(selected).copied()
+    }
+
+    fn enter(&mut self) {
+        let Some(selected) = self.state().selected() else {
+            return;
+        };
+        match self.level {
+            Level::Channels => {
+                let Some((channel, _)) = self.channels().get(selected).cloned() else {
+                    return;
+                };
+                self.channel = channel;
+                self.level = Level::Dates;
+            }
+            Level::Dates => {
+                let Some((date, _)) = self.dates(&self.channel).get(selected).cloned() else {
+                    return;
+                };
+                self.date = date;
+                self.level = Level::Files;
+            }
+            Level::Files => return self.open(),
+        }
+        self.state().select_first();
+    }
+
+    fn back(&mut self) {
+        self.level = match self.level {
+            Level::Channels | Level::Dates => Level::Channels,
+            Level::Files => Level::Dates,
+        };
+    }
+
+    fn open(&mut self) {
+        let Some(path) = self.selected_file().map(|s| s.path.clone()) else {
+            return;
+        };
+        self.status = match opener::open(&path) {
+            Ok(()) => format!("Opened {}", path.display()),
+            Err(error) => format!("Failed to open {}: {error}", path.display()),
+        };
+    }
+
+    fn delete(&mut self) {
+        let Some(path) = self.selected_file().map(|s| s.path.clone()) else {
+            return;
+        };
+        let result = removal::discard(&self.save_dir, &path, self.index.as_ref(), false);
+        self.status = match result {
+            Ok(()) => {
+                self.screenshots.retain(|s| s.path != path);
+                self.settle();
+                format!("Deleted {}", path.display())
+            }
+            Err(error) => format!("Failed to delete {}: {error}", path.display()),
+        };
+    }
+
+    /// move the selected screenshot into `channel`'s folder, renamed so the name still says
+    /// which channel it's from
+    fn retag(&mut self, channel: &str) {
+        let Some(screenshot) = self.selected_file().cloned() else {
+            return;
+        };
+        if channel == screenshot.channel {
+            self.status = HELP.to_string();
+            return;
+        }
+        let file_name = screenshot.file_name();
+        let file_name = format!("{channel}{}", &file_name[screenshot.channel.len()..]);
+        let channel_dir = self.save_dir.join(channel);
+        let target = sharding::is_sharded(&channel_dir)
+            .then(|| sharding::shard_path(&channel_dir, OsStr::new(&file_name)))
+            .flatten()
+            .unwrap_or_else(|| channel_dir.join(&file_name));
+
+        let result = (|| {
+            if target.exists() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} already exists", target.display()),
+                ));
+            }
+            fs::create_dir_all(target.parent().expect("target has a parent directory"))?;
+            fs::rename(&screenshot.path, &target)?;
+            thumbnails::relocate(&self.save_dir, &screenshot.path, &target);
+            // the vod it links to is the other channel's
+            sidecar::remove(&screenshot.path);
+            if let Some(index) = &self.index {
+                index.retag(&screenshot.path, &target, channel)?;
+            }
+            Ok(())
+        })();
+        self.status = match result {
+            Ok(()) => {
+                for s in &mut self.screenshots {
+                    if s.path == screenshot.path {
+                        s.path = target.clone();
+                        s.channel = channel.to_string();
+                    }
+                }
+                self.settle();
+                format!("Moved to {}", target.display())
+            }
+            Err(error) => format!("Failed to re-tag {}: {error}", file_name),
+        };
+    }
+
+    /// after a screenshot went away climb out of levels that are now empty
+    fn settle(&mut self) {
+        if self.level == Level::Files && self.files(&self.channel, self.date).is_empty() {
+            self.level = Level::Dates;
+        }
+        if self.level == Level::Dates && self.dates(&self.channel).is_empty() {
+            self.level = Level::Channels;
+        }
+        let len = self.items().len();
+        let state = self.state();
+        if state.selected().is_some_and(|selected| selected >= len) {
+            state.select(len.checked_sub(1));
+        }
+    }
+}
+
+fn date_label(date: Option<NaiveDate>) -> String {
+    date.map(|date| date.to_string())
+        .unwrap_or_else(|| "unknown date".to_string())
+}
@@ -0,0 +1,481 @@
+use chrono::{DateTime, Duration, Local, NaiveDateTime, TimeZone, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use ureq::http::HeaderMap;
+
+use crate::config;
+use crate::http;
+use crate::index::Index;
+
+const TOKEN_URL: &str = "https://id.twitch.tv/oauth2/token";
+const USERS_URL: &str = "https://api.twitch.tv/helix/users";
+const STREAMS_URL: &str = "https://api.twitch.tv/helix/streams";
+const VIDEOS_URL: &str = "https://api.twitch.tv/helix/videos";
+
+/// how long a known channel is trusted before it's looked up again, channels get renamed
+const KNOWN_FOR_DAYS: i64 = 30;
+
+/// a channel that didn't exist may be created ( or a screenshot misread ) any time
+const MISSING_FOR_DAYS: i64 = 1;
+
+/// only screenshots this fresh are looked up, twitch doesn't know what was played in the past
+const GAME_MAX_AGE_MINUTES: i64 = 10;
+
+/// a burst of screenshots shouldn't ask for the same stream again and again
+const LIVE_FOR_SECONDS: i64 = 60;
+
+/// the duration of the vod of a live stream lags behind a bit
+const VOD_SLACK_MINUTES: i64 = 15;
+
+/// own cap, twitch allows 800 a minute but that bucket is shared with everything else using the
+/// same app
+const REQUESTS_PER_MINUTE: u32 = 120;
+
+/// left in twitch's bucket for whatever else uses the same app
+const RESERVE: u32 = 20;
+
+/// how long to leave twitch alone after it couldn't be reached, so every screenshot doesn't
+/// wait for a timeout while offline
+const OFFLINE_BACKOFF: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// an app token is replaced this long before it expires
+const TOKEN_MARGIN: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// A Twitch channel as Helix knows it
+#[derive(Deserialize, Debug, Clone)]
+pub struct User {
+    pub id: String,
+    pub login: String,
+    pub display_name: String,
+}
+
+/// A live stream, only what's needed to tell what it was about
+#[derive(Deserialize, Debug, Clone)]
+pub struct Stream {
+    pub game_id: String,
+    pub game_name: String,
+    pub started_at: DateTime<Utc>,
+}
+
+/// A past broadcast, only what's needed to find a moment in it
+#[derive(Deserialize, Debug, Clone)]
+struct Video {
+    id: String,
+    url: String,
+    created_at: DateTime<Utc>,
+    /// like 3h2m1s
+    duration: String,
+}
+
+/// The moment of a screenshot in the vod of its stream
+#[derive(Serialize, Debug, Clone)]
+pub struct Vod {
+    pub id: String,
+    /// opens the vod at that moment
+    pub url: String,
+    pub offset_seconds: i64,
+}
+
+/// A lookup and when it was made, `value` is none when twitch had nothing ( no such channel,
+/// not live )
+#[derive(Debug, Clone)]
+pub struct Cached<T> {
+    pub value: Option<T>,
+    pub checked: DateTime<Local>,
+}
+
+impl<T> Cached<T> {
+    fn now(value: Option<T>) -> Cached<T> {
+        Cached {
+            value,
+            checked: Local::now(),
+        }
+    }
+
+    fn younger_than(&self, age: Duration) -> bool {
+        Local::now() - self.checked < age
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Debug)]
+struct AppToken {
+    value: String,
+    expires: Instant,
+}
+
+/// what helix wraps every list in
+#[derive(Deserialize)]
+struct Data<T> {
+    data: Vec<T>,
+}
+
+/// How many requests can still be made
+#[derive(Debug)]
+struct Budget {
+    /// start of the current minute of our own cap and what was used of it
+    window: Instant,
+    used: u32,
+    /// twitch's bucket as of the last response
+    remaining: Option<u32>,
+    reset: Option<Instant>,
+    offline_until: Option<Instant>,
+}
+
+impl Budget {
+    fn spend(&mut self) -> io::Result<()> {
+        let now = Instant::now();
+        if let Some(until) = self.offline_until.filter(|until| now < *until) {
+            return Err(io::Error::other(format!(
+                "Twitch couldn't be reached, trying again in {}s",
+                (until - now).as_secs()
+            )));
+        }
+        if now - self.window >= std::time::Duration::from_secs(60) {
+            self.window = now;
+            self.used = 0;
+        }
+        let twitch_empty = self.remaining.is_some_and(|remaining| remaining <= RESERVE)
+            && self.reset.is_some_and(|reset| now < reset);
+        if self.used >= REQUESTS_PER_MINUTE || twitch_empty {
+            return Err(io::Error::other("Twitch rate limit budget used up for now"));
+        }
+        self.used += 1;
+        Ok(())
+    }
+
+    /// Ratelimit-Remaining and Ratelimit-Reset ( unix seconds ) come with every response
+    fn update(&mut self, headers: &HeaderMap) {
+        let number = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok())
+        };
+        if let Some(remaining) = number("Ratelimit-Remaining") {
+            self.remaining = Some(remaining as u32);
+        }
+        if let Some(reset) = number("Ratelimit-Reset") {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            self.reset =
+                Some(Instant::now() + std::time::Duration::from_secs(reset.saturating_sub(now)));
+        }
+    }
+
+    fn failed(&mut self, error: &ureq::Error) {
+        match error {
+            ureq::Error::StatusCode(429) => {
+                self.remaining = Some(0);
+                self.reset = Some(Instant::now() + std::time::Duration::from_secs(60));
+            }
+            ureq::Error::StatusCode(_) => {}
+            _ => self.offline_until = Some(Instant::now() + OFFLINE_BACKOFF),
+        }
+    }
+}
+
+/// The one client for the Twitch Helix API, with an app access token from the credentials in
+/// the config. Lookups are kept in the index when there is one, so they're there on the next
+/// run and when Twitch can't be reached.
+#[derive(Debug)]
+pub struct Helix {
+    settings: config::Twitch,
+    index: Option<Arc<Index>>,
+    token: Mutex<Option<AppToken>>,
+    budget: Mutex<Budget>,
+    users: Mutex<HashMap<String, Cached<User>>>,
+    streams: Mutex<HashMap<String, Cached<Stream>>>,
+    /// recent vods by user id, for [LIVE_FOR_SECONDS]
+    videos: Mutex<HashMap<String, Cached<Vec<Video>>>>,
+}
+
+impl Helix {
+    pub fn new(settings: config::Twitch, index: Option<Arc<Index>>) -> Helix {
+        if index.is_none() {
+            log::info!("Twitch lookups are only remembered until exit without --index");
+        }
+        Helix {
+            settings,
+            index,
+            token: Mutex::new(None),
+            budget: Mutex::new(Budget {
+                window: Instant::now(),
+                used: 0,
+                remaining: None,
+                reset: None,
+                offline_until: None,
+            }),
+            users: Mutex::new(HashMap::new()),
+            streams: Mutex::new(HashMap::new()),
+            videos: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// How the streamer capitalizes `login` when display names are turned on. Only if it's the
+    /// same name otherwise, some display names are in another script entirely.
+    pub fn display_name(&self, login: &str) -> Option<String> {
+        if !self.settings.display_names {
+            return None;
+        }
+        let user = self.user(login).ok().flatten()?;
+        user.display_name
+            .eq_ignore_ascii_case(&user.login)
+            .then_some(user.display_name)
+    }
+
+    /// The channel with this login, none if there is no such channel. When Twitch can't be
+    /// asked whatever it said before is used, errors are for when it was never asked.
+    pub fn user(&self, login: &str) -> io::Result<Option<User>> {
+        let login = login.to_lowercase();
+        let cached = self.users.lock().unwrap().get(&login).cloned().or_else(|| {
+            let index = self.index.as_ref()?;
+            index.cached_twitch_user(&login).ok().flatten()
+        });
+        if let Some(cached) = &cached {
+            let days = if cached.value.is_some() {
+                KNOWN_FOR_DAYS
+            } else {
+                MISSING_FOR_DAYS
+            };
+            if cached.younger_than(Duration::days(days)) {
+                self.users
+                    .lock()
+                    .unwrap()
+                    .insert(login.clone(), cached.clone());
+                return Ok(cached.value.clone());
+            }
+        }
+
+        match self.get::<User>(USERS_URL, &[("login", &login)]) {
+            Ok(mut users) => {
+                let fetched = Cached::now(users.pop());
+                log::debug!("Twitch says {login} is {:?}", fetched.value);
+                if let Some(index) = &self.index {
+                    if let Err(error) = index.cache_twitch_user(&login, &fetched) {
+                        log::warn!("Failed to remember twitch channel {login}: {error}");
+                    }
+                }
+                self.users.lock().unwrap().insert(login, fetched.clone());
+                Ok(fetched.value)
+            }
+            Err(error) => match cached {
+                Some(stale) => {
+                    log::debug!("Going with what Twitch said about {login} before: {error}");
+                    Ok(stale.value)
+                }
+                None => Err(error),
+            },
+        }
+    }
+
+    /// What `login` was streaming at `captured_at` when games are turned on. Twitch only knows
+    /// the category of a stream while it's live, so it's none for anything older than a few
+    /// minutes or taken before the current stream started.
+    pub fn game(&self, login: &str, captured_at: NaiveDateTime) -> Option<String> {
+        if !self.settings.games {
+            return None;
+        }
+        let captured_at = Local.from_local_datetime(&captured_at).earliest()?;
+        if Local::now() - captured_at > Duration::minutes(GAME_MAX_AGE_MINUTES) {
+            return None;
+        }
+
+        let login = login.to_lowercase();
+        let cached = self
+            .streams
+            .lock()
+            .unwrap()
+            .get(&login)
+            .cloned()
+            .or_else(|| {
+                let index = self.index.as_ref()?;
+                index.cached_twitch_stream(&login).ok().flatten()
+            })
+            .filter(|cached| cached.younger_than(Duration::seconds(LIVE_FOR_SECONDS)));
+        let stream = match cached {
+            Some(cached) => cached,
+            None => match self.get::<Stream>(STREAMS_URL, &[("user_login", &login)]) {
+                Ok(mut streams) => {
+                    let fetched = Cached::now(streams.pop());
+                    if let Some(index) = &self.index {
+                        if let Err(error) = index.cache_twitch_stream(&login, &fetched) {
+                            log::warn!("Failed to remember the stream of {login}: {error}");
+                        }
+                    }
+                    fetched
+                }
+                Err(error) => {
+                    log::warn!("Failed to ask Twitch what {login} is playing: {error}");
+                    return None;
+                }
+            },
+        };
+        self.streams.lock().unwrap().insert(login, stream.clone());
+
+        let stream = stream.value?;
+        (stream.started_at <= captured_at && !stream.game_name.is_empty())
+            .then_some(stream.game_name)
+    }
+
+    /// Where `captured_at` is in the vod of the stream `login` was doing then, when vods are
+    /// turned on. None once the vod is gone, twitch keeps them for a week or two.
+    pub fn vod(&self, login: &str, captured_at: NaiveDateTime) -> Option<Vod> {
+        if !self.settings.vods {
+            return None;
+        }
+        let user = self.user(login).ok().flatten()?;
+        let captured_at = Local
+            .from_local_datetime(&captured_at)
+            .earliest()?
+            .with_timezone(&Utc);
+
+        let cached = self
+            .videos
+            .lock()
+            .unwrap()
+            .get(&user.id)
+            .cloned()
+            .filter(|cached| cached.younger_than(Duration::seconds(LIVE_FOR_SECONDS)));
+        let videos = match cached {
+            Some(cached) => cached.value.unwrap_or_default(),
+            None => {
+                let query = [
+                    ("user_id", user.id.as_str()),
+                    ("type", "archive"),
+                    ("first", "100"),
+                ];
+                match self.get::<Video>(VIDEOS_URL, &query) {
+                    Ok(videos) => {
+                        self.videos
+                            .lock()
+                            .unwrap()
+                            .insert(user.id.clone(), Cached::now(Some(videos.clone())));
+                        videos
+                    }
+                    Err(error) => {
+                        log::warn!("Failed to ask Twitch for the vods of {login}: {error}");
+                        return None;
+                    }
+                }
+            }
+        };
+
+        let video = videos.into_iter().find(|video| {
+            let end = video.created_at
+                + parse_duration(&video.duration).unwrap_or_default()
+                + Duration::minutes(VOD_SLACK_MINUTES);
+            video.created_at <= captured_at && captured_at <= end
+        })?;
+        let offset = (captured_at - video.created_at).num_seconds();
+        Some(Vod {
+            url: format!(
+                "{}?t={}h{}m{}s",
+                video.url,
+                offset / 3600,
+                offset / 60 % 60,
+                offset % 60
+            ),
+            id: video.id,
+            offset_seconds: offset,
+        })
+    }
+
+    /// the list behind a helix endpoint filtered by `query`, within the budget
+    fn get<T: DeserializeOwned>(&self, url: &str, query: &[(&str, &str)]) -> io::Result<Vec<T>> {
+        self.budget.lock().unwrap().spend()?;
+        let request = |token: &str| {
+            http::agent()
+                .get(url)
+                .query_pairs(query.iter().copied())
+                .header("Client-Id", &self.settings.client_id)
+                .header("Authorization", format!("Bearer {token}"))
+                .call()
+        };
+        let response = match request(&self.token(false)?) {
+            // revoked or expired early, get a new one once
+            Err(ureq::Error::StatusCode(401)) => request(&self.token(true)?),
+            result => result,
+        };
+
+        let mut response = response.map_err(|error| {
+            self.budget.lock().unwrap().failed(&error);
+            io::Error::other(error)
+        })?;
+        self.budget.lock().unwrap().update(response.headers());
+        response
+            .body_mut()
+            .read_json::<Data<T>>()
+            .map(|data| data.data)
+            .map_err(io::Error::other)
+    }
+
+    /// app access token from the client credentials, a new one when it's about to expire or
+    /// when `refresh`
+    fn token(&self, refresh: bool) -> io::Result<String> {
+        let mut token = self.token.lock().unwrap();
+        if let Some(token) = token
+            .as_ref()
+            .filter(|token| !refresh && Instant::now() + TOKEN_MARGIN < token.expires)
+        {
+            return Ok(token.value.clone());
+        }
+
+        let fetched: TokenResponse = http::agent()
+            .post(TOKEN_URL)
+            .send_form([
+                ("client_id", self.settings.client_id.as_str()),
+                ("client_secret", self.settings.client_secret.as_str()),
+                ("grant_type", "client_credentials"),
+            ])
+            .and_then(|mut response| response.body_mut().read_json())
+            .map_err(|error| {
+                self.budget.lock().unwrap().failed(&error);
+                io::Error::other(error)
+            })?;
+        log::debug!("Got a twitch app token for {}s", fetched.expires_in);
+        *token = Some(AppToken {
+            value: fetched.access_token.clone(),
+            expires: Instant::now() + std::time::Duration::from_secs(fetched.expires_in),
+        });
+        Ok(fetched.access_token)
+    }
+}
+
+/// a helix duration like 3h2m1s
+/// Whether `word` could be a channel login, up to 25 letters, digits and underscores. Old ones
+/// can be 3 long.
+pub fn is_login(word: &str) -> bool {
+    (3..=25).contains(&word.len()) && word.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_duration(value: &str) -> Option<Duration> {
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let amount: i64 = number.parse().ok()?;
+        number.clear();
+        total += match c {
+            'h' => Duration::hours(amount),
+            'm' => Duration::minutes(amount),
+            's' => Duration::seconds(amount),
+            _ => return None,
+        };
+    }
+    number.is_empty().then_some(total)
+}
@@ -0,0 +1,99 @@
+use std::collections::{BTreeSet, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::collection;
+use crate::index::Index;
+use crate::plan::SAVE_TO;
+
+/// how deep under the given folder twitch-screenshots folders are looked for, they're right
+/// next to the screenshots so usually one level down
+const MAX_DEPTH: usize = 3;
+
+/// Find the twitch-screenshots folders organized before there was an index under `path` and put
+/// what's in them into one, so `--index`, `search`, `serve` and the rest pick them up without
+/// organizing everything again
+pub fn run(path: &Path, dry_run: bool) -> io::Result<()> {
+    let trees = find(path);
+    if trees.is_empty() {
+        println!("No {SAVE_TO} folders in {}", path.display());
+        return Ok(());
+    }
+
+    for tree in trees {
+        let tree = tree.canonicalize()?;
+        let screenshots = collection::walk(&tree);
+        if screenshots.is_empty() {
+            continue;
+        }
+
+        let index = if dry_run {
+            Index::open_existing(&tree)?
+        } else {
+            Some(Index::open(&tree)?)
+        };
+        let indexed = match &index {
+            Some(index) => index
+                .screenshots()?
+                .into_iter()
+                .map(|screenshot| screenshot.path)
+                .collect(),
+            None => HashSet::new(),
+        };
+
+        let mut registered = 0;
+        let mut channels = BTreeSet::new();
+        for screenshot in screenshots {
+            if indexed.contains(&screenshot.path) {
+                continue;
+            }
+            if let Some(index) = &index {
+                if !dry_run {
+                    if let Err(error) = index.record(&screenshot.channel, &screenshot.path) {
+                        log::error!("Failed to index {}: {error:?}", screenshot.path.display());
+                        continue;
+                    }
+                }
+            }
+            registered += 1;
+            channels.insert(screenshot.channel);
+        }
+
+        println!(
+            "{}: {} {registered} screenshot(s) of {} channel(s)",
+            tree.display(),
+            if dry_run {
+                "would register"
+            } else {
+                "registered"
+            },
+            channels.len()
+        );
+    }
+    Ok(())
+}
+
+/// `path` itself if it's a twitch-screenshots folder, otherwise the ones below it
+fn find(path: &Path) -> Vec<PathBuf> {
+    if path.file_name().is_some_and(|name| name == SAVE_TO) {
+        return vec![path.to_path_buf()];
+    }
+    let mut trees = Vec::new();
+    let mut walker = WalkDir::new(path)
+        .min_depth(1)
+        .max_depth(MAX_DEPTH)
+        .into_iter()
+        .filter_entry(|entry| !entry.file_name().to_string_lossy().starts_with('.'));
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if entry.file_type().is_dir() && entry.file_name() == SAVE_TO {
+            trees.push(entry.into_path());
+            // a channel could be called twitch-screenshots too
+            walker.skip_current_dir();
+        }
+    }
+    trees
+}
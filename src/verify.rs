@@ -0,0 +1,154 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::collection;
+use crate::in_parallel;
+use crate::index::{self, Index};
+use crate::progress::{self, Progress};
+
+/// Something that doesn't add up between the index and the files
+#[derive(Debug)]
+enum Problem {
+    /// indexed but not there anymore
+    Missing(PathBuf),
+    /// there but not indexed
+    Unindexed(PathBuf, String),
+    /// not the file that was indexed, it got corrupted or replaced
+    Changed(PathBuf),
+    /// a channel folder without any screenshots left
+    EmptyFolder(PathBuf),
+}
+
+/// Check the index of the tree organized into `path` against what's on disk: indexed
+/// screenshots that are gone, ones that aren't indexed, ones that changed since and channel
+/// folders that are empty. With `fix` everything but changed screenshots is repaired, there's no
+/// telling which version is the right one. Fails if problems are left.
+pub fn run(path: &Path, fix: bool) -> io::Result<()> {
+    let save_dir = collection::save_dir(path).canonicalize()?;
+    let Some(index) = Index::open_existing(&save_dir)? else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} has no index to verify", save_dir.display()),
+        ));
+    };
+    let problems = check(&save_dir, &index)?;
+    if problems.is_empty() {
+        println!("{}: everything checks out", save_dir.display());
+        return Ok(());
+    }
+
+    let mut left = 0;
+    for problem in &problems {
+        match problem {
+            Problem::Missing(path) => println!("missing    {}", path.display()),
+            Problem::Unindexed(path, _) => println!("unindexed  {}", path.display()),
+            Problem::Changed(path) => println!("changed    {}", path.display()),
+            Problem::EmptyFolder(path) => println!("empty      {}", path.display()),
+        }
+        if !fix || matches!(problem, Problem::Changed(_)) {
+            left += 1;
+            continue;
+        }
+        if let Err(error) = repair(problem, &index) {
+            log::error!("Failed to repair {problem:?}: {error}");
+            left += 1;
+        }
+    }
+    if fix {
+        println!(
+            "Repaired {} of {} problem(s)",
+            problems.len() - left,
+            problems.len()
+        );
+    }
+    if left > 0 {
+        let repairable = problems
+            .iter()
+            .any(|problem| !matches!(problem, Problem::Changed(_)));
+        let hint = if !fix && repairable {
+            ", --fix repairs what it can"
+        } else {
+            ""
+        };
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{left} problem(s) in {}{hint}", save_dir.display()),
+        ));
+    }
+    Ok(())
+}
+
+fn check(save_dir: &Path, index: &Index) -> io::Result<Vec<Problem>> {
+    let hashes = index.hashes()?;
+    let screenshots = collection::walk(save_dir);
+    let on_disk = screenshots
+        .iter()
+        .map(|screenshot| &screenshot.path)
+        .collect::<HashSet<_>>();
+
+    let mut problems = hashes
+        .keys()
+        .filter(|path| !on_disk.contains(path))
+        .map(|path| Problem::Missing(path.clone()))
+        .collect::<Vec<_>>();
+    problems.sort_by(|a, b| path_of(a).cmp(path_of(b)));
+
+    // hashing every screenshot is what takes a while
+    let found = Mutex::new(Vec::new());
+    let progress = (screenshots.len() >= progress::THRESHOLD)
+        .then(|| Progress::start(screenshots.len()))
+        .flatten();
+    let jobs = thread::available_parallelism().map_or(1, |cores| cores.get());
+    in_parallel(&screenshots, jobs, |screenshot| {
+        let problem = match hashes.get(&screenshot.path) {
+            None => Some(Problem::Unindexed(
+                screenshot.path.clone(),
+                screenshot.channel.clone(),
+            )),
+            Some(hash) => match index::hash_file(&screenshot.path) {
+                Ok(actual) if actual == *hash => None,
+                Ok(_) => Some(Problem::Changed(screenshot.path.clone())),
+                Err(error) => {
+                    log::error!("Failed to read {}: {error}", screenshot.path.display());
+                    Some(Problem::Changed(screenshot.path.clone()))
+                }
+            },
+        };
+        found.lock().unwrap().extend(problem);
+        if let Some(progress) = &progress {
+            progress.inc();
+        }
+    });
+    drop(progress);
+    let mut found = found.into_inner().unwrap();
+    found.sort_by(|a, b| path_of(a).cmp(path_of(b)));
+    problems.extend(found);
+
+    problems.extend(
+        collection::empty_channel_dirs(save_dir)
+            .into_iter()
+            .map(Problem::EmptyFolder),
+    );
+    Ok(problems)
+}
+
+fn repair(problem: &Problem, index: &Index) -> io::Result<()> {
+    match problem {
+        Problem::Missing(path) => index.remove(path),
+        Problem::Unindexed(path, channel) => index.record(channel, path),
+        Problem::EmptyFolder(dir) => collection::remove_channel_dir(dir),
+        Problem::Changed(_) => Ok(()),
+    }
+}
+
+fn path_of(problem: &Problem) -> &Path {
+    match problem {
+        Problem::Missing(path)
+        | Problem::Unindexed(path, _)
+        | Problem::Changed(path)
+        | Problem::EmptyFolder(path) => path,
+    }
+}
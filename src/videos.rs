@@ -0,0 +1,115 @@
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::output::{self, Action, Outcome};
+use crate::plan::{Plan, Transfer};
+use crate::{delayed, execute, journal, quarantine, readiness, report_failure, twitch, Options};
+
+/// subfolder of a channel folder clips and vods go into, apart from the screenshots
+pub const CLIPS: &str = "clips";
+
+/// what clip and vod downloaders save
+const EXTENSION: &str = ".mp4";
+
+/// The channel of a clip or vod download, going by the names downloaders give them:
+/// `[2025-01-18] channel - title.mp4` ( TwitchDownloader ) or `channel - title [id].mp4` ( yt-dlp
+/// with `%(uploader_id)s - %(title)s [%(id)s]` )
+pub fn channel_of(file_name: &str) -> Option<String> {
+    let stem = file_name
+        .len()
+        .checked_sub(EXTENSION.len())
+        .filter(|&at| file_name.is_char_boundary(at))
+        .filter(|&at| file_name[at..].eq_ignore_ascii_case(EXTENSION))
+        .map(|at| &file_name[..at])?;
+    let stem = match stem.strip_prefix('[') {
+        Some(rest) => {
+            let (date, rest) = rest.split_once(']')?;
+            if !date.starts_with(|c: char| c.is_ascii_digit()) {
+                return None;
+            }
+            rest.trim_start()
+        }
+        None => {
+            // a vod id like v2345678901 or a clip slug like FunnyClipName-AbCd1234
+            let (_, id) = stem.strip_suffix(']')?.rsplit_once(" [")?;
+            let id_like = !id.is_empty()
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !id_like {
+                return None;
+            }
+            stem
+        }
+    };
+    let (channel, _title) = stem.split_once(" - ")?;
+    twitch::is_login(channel).then(|| channel.to_lowercase())
+}
+
+/// Move the clip or vod at `path` into the clips folder of `channel`. Like screenshots, in
+/// `daemon_mode` that's done once the download is finished.
+pub fn organize(
+    path: &Path,
+    channel: &str,
+    daemon_mode: bool,
+    options: &Options,
+) -> io::Result<()> {
+    let parent = path.parent().expect("file has a parent directory");
+    let channel_dir = options.save_dir(parent).join(channel);
+    let plan = Plan {
+        channel: channel.to_string(),
+        game: None,
+        target: channel_dir
+            .join(CLIPS)
+            .join(path.file_name().expect("file has a name")),
+        channel_dir,
+        links: Vec::new(),
+    };
+    options.create_dir_all(plan.target.parent().expect("target has a parent directory"))?;
+
+    if !daemon_mode {
+        if readiness::recently_modified(path) {
+            readiness::wait(path);
+        }
+        let transfer = execute(&plan, path, options)?;
+        land(path, &plan, transfer, options);
+        return Ok(());
+    }
+    let options = options.clone();
+    let source = path.to_path_buf();
+    let mut wait = readiness::Wait::start();
+    delayed::schedule(
+        Box::new(move || {
+            if let Some(again) = wait.check(&source) {
+                return Some(again);
+            }
+            match execute(&plan, &source, &options) {
+                Ok(transfer) => land(&source, &plan, transfer, &options),
+                Err(error) => report_failure(&source, &error, &options),
+            }
+            None
+        }),
+        Duration::ZERO,
+    )
+}
+
+fn land(source: &Path, plan: &Plan, transfer: Transfer, options: &Options) {
+    log::info!("Video {transfer} to: {}", plan.target.display());
+    quarantine::forget(source);
+    if transfer == Transfer::Moved && options.storage.is_none() {
+        journal::moved(source, &plan.target);
+    }
+    let action = match transfer {
+        Transfer::Linked => Action::Linked,
+        Transfer::Copied => Action::Copied,
+        Transfer::Moved => Action::Moved,
+        Transfer::AlreadyThere => Action::Skipped,
+    };
+    output::report(
+        options,
+        Outcome::new(source, action)
+            .to(&plan.target)
+            .channel(&plan.channel),
+    );
+}
@@ -0,0 +1,701 @@
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Config, Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use walkdir::WalkDir;
+
+use crate::output::{self, Tally};
+use crate::{
+    activity, capture_time, classify, control, in_parallel, metrics, move_all, move_file,
+    quarantine, report_failure, status, sync_tools, videos, Kind, Options, Scope,
+};
+
+/// how often the supervisor stats the watched directory
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(15);
+
+/// how often the event loop wakes up to check if it should stop
+const STOP_POLL: Duration = Duration::from_secs(1);
+
+/// first wait before watching again when the directory is gone or the watcher broke, doubled up
+/// to [MAX_RESTART_BACKOFF] while it keeps failing
+const RESTART_BACKOFF: Duration = Duration::from_secs(1);
+
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+/// how long a file has to go without events before it's organized
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Watch for new screenshots in the directory and move them to appropriate folder.
+///
+/// The event loop runs on its own thread while this one supervises it: if the directory changed
+/// but the loop hasn't seen a single event for two watchdog intervals, it's considered stalled,
+/// replaced by a fresh watcher and a catch up pass is run for whatever was missed. The same
+/// happens when the watcher reports errors or the directory is deleted, it's watched again with
+/// backoff once it's back. In between this thread answers `ctl` and starts --rescan-interval
+/// passes, those and the catch up pass run on their own thread, one at a time.
+pub fn run_as_daemon<P: AsRef<Path>>(path: P, options: &Options) -> notify::Result<()> {
+    let path = path.as_ref().to_path_buf();
+    let mut options = options.clone();
+    let heartbeat = Arc::new(AtomicU64::new(0));
+
+    let mut event_loop = EventLoop::start(&path, &options, &heartbeat)?;
+    let (requests_tx, requests) = mpsc::channel();
+    // kept for as long as it should be watched
+    let _config_watcher = match &options.config_path {
+        Some(config) => watch_config(config, requests_tx.clone())
+            .inspect_err(|error| {
+                log::warn!(
+                    "Not reloading {} when it changes: {error}",
+                    config.display()
+                )
+            })
+            .ok(),
+        None => None,
+    };
+    if let Err(error) = control::listen(requests_tx) {
+        log::warn!("Not listening for `ctl`: {error}");
+    }
+    if let Some(addr) = options.metrics_addr {
+        if let Err(error) = metrics::serve(addr) {
+            log::warn!("Not serving metrics on {addr}: {error}");
+        }
+    }
+    // a scan has to have had the chance to see a change before the watcher is taken for stalled
+    let watchdog_interval = if options.poll {
+        WATCHDOG_INTERVAL.max(Duration::from_secs(options.poll_interval) * 2)
+    } else {
+        WATCHDOG_INTERVAL
+    };
+
+    let mut last_modified = modified(&path);
+    let mut last_events = heartbeat.load(Ordering::Relaxed);
+    // event count from before a change was noticed, events for it should show up by the next tick
+    let mut pending: Option<u64> = None;
+    let mut restarts = 0;
+    let mut next_check = Instant::now() + watchdog_interval;
+    let mut next_rescan = options
+        .rescan_interval
+        .map(|interval| Instant::now() + interval);
+    // passes over the whole folder run next to this thread so `ctl` is still answered, two at
+    // once would both try to move every screenshot
+    let mut rescan = Rescan::default();
+
+    loop {
+        rescan.reap(&path, &options);
+        let wake = next_rescan.map_or(next_check, |rescan| rescan.min(next_check));
+        match requests.recv_timeout(wake.saturating_duration_since(Instant::now())) {
+            Ok(control::Message { request, reply }) => {
+                let answer = match request {
+                    control::Request::Status => status(&path, restarts),
+                    control::Request::Health => {
+                        serde_json::to_string(&status::health(&path, restarts))
+                            .expect("health serializes")
+                    }
+                    control::Request::Rescan => {
+                        if rescan.start(&path, &options) {
+                            format!("Rescanning {}", path.display())
+                        } else {
+                            format!("Already rescanning {}", path.display())
+                        }
+                    }
+                    control::Request::ReloadConfig => match options.reload_config() {
+                        Ok(()) => {
+                            event_loop.reload(&options);
+                            "Reloaded the config".to_string()
+                        }
+                        Err(error) => {
+                            log::error!("Failed to reload the config: {error}");
+                            status::error(format!("Failed to reload the config: {error}"));
+                            format!("Failed to reload the config: {error}")
+                        }
+                    },
+                    // answered by the control thread
+                    control::Request::Pause | control::Request::Resume => continue,
+                };
+                let _ = reply.send(answer);
+                continue;
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            // not listening for `ctl`
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                thread::sleep(wake.saturating_duration_since(Instant::now()))
+            }
+        }
+
+        if next_rescan.is_some_and(|rescan| rescan <= Instant::now()) && !control::is_paused() {
+            log::debug!("Rescanning {} for missed screenshots", path.display());
+            // one that's still going is as good
+            rescan.start(&path, &options);
+            next_rescan = options
+                .rescan_interval
+                .map(|interval| Instant::now() + interval);
+        }
+        if next_check > Instant::now() {
+            continue;
+        }
+        next_check = Instant::now() + watchdog_interval;
+
+        let events = heartbeat.load(Ordering::Relaxed);
+        let now_modified = modified(&path);
+
+        let trouble = if !path.is_dir() {
+            Some("the directory is gone")
+        } else if event_loop.broken.load(Ordering::Relaxed) {
+            Some("the watcher broke")
+        } else if pending.take().is_some_and(|before| before == events) {
+            Some("it saw no events although the directory changed")
+        } else {
+            None
+        };
+
+        if let Some(trouble) = trouble {
+            log::warn!("Watching {} again, {trouble}", path.display());
+            status::error(format!("Watching it again, {trouble}"));
+            event_loop.stop();
+            event_loop = restart(&path, &options, &heartbeat);
+            restarts += 1;
+            metrics::restarted();
+            pending = None;
+            // one that's still going may have looked at the folder before the watcher broke
+            if !rescan.start(&path, &options) {
+                rescan.catch_up = true;
+            }
+        } else if now_modified != last_modified {
+            pending = Some(last_events);
+        }
+
+        last_modified = now_modified;
+        last_events = events;
+    }
+}
+
+/// The pass over the whole folder that's running, if any, see [run_as_daemon]
+#[derive(Default)]
+struct Rescan {
+    running: Option<thread::JoinHandle<()>>,
+    /// another pass is needed once the running one is done
+    catch_up: bool,
+}
+
+impl Rescan {
+    /// Start a pass unless one is running already, returns whether it was started
+    fn start(&mut self, path: &Path, options: &Options) -> bool {
+        self.reap(path, options);
+        if self.running.is_some() {
+            return false;
+        }
+        self.running = Some(move_all(path, options.clone(), true));
+        true
+    }
+
+    /// Let go of a pass that's done, starting the catch up pass that waited for it
+    fn reap(&mut self, path: &Path, options: &Options) {
+        let Some(done) = self.running.take_if(|running| running.is_finished()) else {
+            return;
+        };
+        if done.join().is_err() {
+            // the panic itself is printed by the hook, the watcher carries on
+            log::error!("Rescanning {} crashed", path.display());
+            status::error("Rescanning crashed".to_string());
+        }
+        if std::mem::take(&mut self.catch_up) {
+            self.running = Some(move_all(path, options.clone(), true));
+        }
+    }
+}
+
+/// Ask for the config to be reloaded whenever the file at `path` changes, once it went quiet so
+/// a half written file isn't loaded
+fn watch_config(
+    path: &Path,
+    requests: mpsc::Sender<control::Message>,
+) -> notify::Result<Box<dyn Watcher + Send>> {
+    let path = path.canonicalize()?;
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = RecommendedWatcher::new(tx, Config::default())?;
+    // editors save by writing a new file and renaming it over the old one, that's only seen on
+    // the folder
+    let dir = path.parent().expect("config file has a parent directory");
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    thread::spawn(move || {
+        let about_config = |res: notify::Result<Event>| {
+            res.is_ok_and(|event| {
+                !matches!(event.kind, EventKind::Access(_)) && event.paths.contains(&path)
+            })
+        };
+        while let Ok(res) = rx.recv() {
+            if !about_config(res) {
+                continue;
+            }
+            while rx.recv_timeout(DEBOUNCE).is_ok() {}
+            log::debug!("{} changed, reloading it", path.display());
+            let (reply, _) = mpsc::channel();
+            let message = control::Message {
+                request: control::Request::ReloadConfig,
+                reply,
+            };
+            if requests.send(message).is_err() {
+                return;
+            }
+        }
+    });
+    Ok(Box::new(watcher))
+}
+
+/// the answer to `ctl status`
+fn status(path: &Path, restarts: u32) -> String {
+    let (organized, failed) = activity::counts();
+    format!(
+        "Watching {}{}\nOrganized {organized} screenshot(s), {failed} failed\nThe watcher was restarted {restarts} time(s)",
+        path.display(),
+        if control::is_paused() { ", paused" } else { "" },
+    )
+}
+
+/// a new event loop for `path`, waiting for it to come back if it's gone
+fn restart(path: &Path, options: &Options, heartbeat: &Arc<AtomicU64>) -> EventLoop {
+    let mut backoff = RESTART_BACKOFF;
+    loop {
+        match EventLoop::start(path, options, heartbeat) {
+            Ok(event_loop) => return event_loop,
+            Err(error) => {
+                log::warn!(
+                    "Failed to watch {}, trying again in {}s: {error}",
+                    path.display(),
+                    backoff.as_secs()
+                );
+                thread::sleep(backoff);
+                backoff = (backoff * 2).min(MAX_RESTART_BACKOFF);
+            }
+        }
+    }
+}
+
+/// one generation of watcher + the thread draining its events
+struct EventLoop {
+    stop: Arc<AtomicBool>,
+    /// set by the loop when the watcher reported an error, lost events or went away
+    broken: Arc<AtomicBool>,
+    /// options with a reloaded config for the loop, what's waiting to be organized is kept
+    reload: mpsc::Sender<Options>,
+    // dropped together with the loop so the old watch is released
+    _watcher: Box<dyn Watcher + Send>,
+}
+
+impl EventLoop {
+    fn start(path: &Path, options: &Options, heartbeat: &Arc<AtomicU64>) -> notify::Result<Self> {
+        let (tx, rx) = mpsc::channel();
+
+        // pick whatever is the best implfementation for system, network shares only work with
+        // scanning them every now and then
+        let mut watcher: Box<dyn Watcher + Send> = if options.poll {
+            let interval = Duration::from_secs(options.poll_interval.max(1));
+            Box::new(PollWatcher::new(
+                tx,
+                Config::default().with_poll_interval(interval),
+            )?)
+        } else {
+            Box::new(RecommendedWatcher::new(tx, Config::default())?)
+        };
+
+        // Add a path to be watched, with --recursive all files and directories below it are
+        // monitored for changes too.
+        let mode = if options.recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        // most watchers report absolute paths anyway, in_reach compares them against this
+        let root = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        watcher.watch(&root, mode)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let broken = Arc::new(AtomicBool::new(false));
+        let (reload, reloads) = mpsc::channel();
+        {
+            let stop = stop.clone();
+            let broken = broken.clone();
+            let heartbeat = heartbeat.clone();
+            let options = options.clone();
+            thread::spawn(move || {
+                event_loop(rx, reloads, &root, &stop, &heartbeat, options);
+                broken.store(true, Ordering::Relaxed);
+            });
+        }
+
+        Ok(Self {
+            stop,
+            broken,
+            reload,
+            _watcher: watcher,
+        })
+    }
+
+    fn reload(&self, options: &Options) {
+        let _ = self.reload.send(options.clone());
+    }
+
+    /// a stuck loop can't be joined, it's told to stop and left behind
+    fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// returns when told to stop or when the watcher can't be relied on anymore
+fn event_loop(
+    rx: mpsc::Receiver<notify::Result<Event>>,
+    reloads: mpsc::Receiver<Options>,
+    root: &Path,
+    stop: &AtomicBool,
+    heartbeat: &AtomicU64,
+    mut options: Options,
+) {
+    let options = &mut options;
+    let mut debouncer = Debouncer::default();
+    while !stop.load(Ordering::Relaxed) {
+        while let Ok(reloaded) = reloads.try_recv() {
+            *options = reloaded;
+        }
+        let timeout = debouncer
+            .next_due(options)
+            .map_or(STOP_POLL, |due| due.min(STOP_POLL));
+        match rx.recv_timeout(timeout) {
+            Ok(res) => {
+                // any event counts, our own moves out of the directory show up as well
+                heartbeat.fetch_add(1, Ordering::Relaxed);
+                metrics::event();
+                status::event();
+                if let Some(recorder) = &options.recorder {
+                    recorder.record(&res);
+                }
+                if let Some(trouble) = trouble(root, &res) {
+                    log::warn!("Watcher for {}: {trouble}", root.display());
+                    debouncer.organize_due(options, true);
+                    return;
+                }
+                debouncer.add(root, res, options);
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => return,
+        }
+        debouncer.organize_due(options, false);
+    }
+}
+
+/// Collects the files watcher events are about until they go quiet, so the burst of events a
+/// browser causes for one download ( create, write, touch .. ) organizes it once
+#[derive(Debug, Default)]
+pub struct Debouncer {
+    /// file -> when the last event about it came in
+    pending: HashMap<PathBuf, Instant>,
+    /// with --batch-window, the batch being organized, the next one waits for it
+    batch: Option<thread::JoinHandle<()>>,
+}
+
+impl Debouncer {
+    /// Take in a watcher event under `root`
+    pub fn add(&mut self, root: &Path, res: notify::Result<Event>, options: &Options) {
+        match res {
+            Ok(Event {
+                kind: EventKind::Create(_),
+                paths,
+                ..
+            }) => {
+                for path in paths {
+                    log::debug!("Processing: {}", path.display());
+                    self.wait_for(root, path, options);
+                }
+            }
+            // a download being renamed from its .crdownload / .part / .tmp name once it's done,
+            // browsers never create the final name, or a file or folder moved in from elsewhere
+            Ok(Event {
+                kind: EventKind::Modify(ModifyKind::Name(mode)),
+                paths,
+                ..
+            }) => match (mode, paths.as_slice()) {
+                (RenameMode::Both, [from, to]) => {
+                    self.pending.remove(from);
+                    log::debug!("Processing: {}", to.display());
+                    self.wait_for(root, to.clone(), options);
+                }
+                (RenameMode::From, _) => {
+                    for path in &paths {
+                        self.pending.remove(path);
+                    }
+                }
+                // the other half of the rename is its own event or can't be told apart, whatever
+                // is gone by the time it's due is skipped
+                _ => {
+                    for path in paths {
+                        log::debug!("Processing: {}", path.display());
+                        self.wait_for(root, path, options);
+                    }
+                }
+            },
+            // still being written, wait for it some more
+            Ok(Event {
+                kind: EventKind::Modify(_),
+                paths,
+                ..
+            }) => {
+                for path in paths {
+                    if let Some(at) = self.pending.get_mut(&path) {
+                        *at = Instant::now();
+                    }
+                }
+            }
+            Ok(Event {
+                kind: EventKind::Remove(_),
+                paths,
+                ..
+            }) => {
+                for path in paths {
+                    self.pending.remove(&path);
+                }
+            }
+            Ok(_) => {} // Ignore other kind of events
+            Err(error) => log::error!("Error: {error:?}"),
+        }
+    }
+
+    /// organize `path` once it goes quiet, unless it's a temp file that's renamed when done
+    fn wait_for(&mut self, root: &Path, path: PathBuf, options: &Options) {
+        // a folder dragged or moved in brings its files along without an event for each of them
+        if path.is_dir() {
+            if options.recursive && in_reach(root, &path, options) {
+                let files = WalkDir::new(&path)
+                    .min_depth(1)
+                    .into_iter()
+                    .filter_entry(|entry| in_reach(root, entry.path(), options))
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.file_type().is_file());
+                for file in files {
+                    self.wait_for(root, file.into_path(), options);
+                }
+            }
+            return;
+        }
+        let is_temp = path
+            .file_name()
+            .is_some_and(|name| sync_tools::is_temp(&name.to_string_lossy()));
+        if !is_temp && in_reach(root, &path, options) {
+            self.pending.insert(path, Instant::now());
+        }
+    }
+
+    /// How long until the next file went quiet for long enough, or with --batch-window all of
+    /// them did, none if nothing is waiting or organizing is paused
+    pub fn next_due(&self, options: &Options) -> Option<Duration> {
+        if control::is_paused() {
+            return None;
+        }
+        let now = Instant::now();
+        let wait = match options.batch_window {
+            Some(window) => self
+                .pending
+                .values()
+                .max()
+                .map(|at| (*at + window.max(DEBOUNCE)).saturating_duration_since(now)),
+            None => self
+                .pending
+                .values()
+                .map(|at| (*at + DEBOUNCE).saturating_duration_since(now))
+                .min(),
+        };
+        // the next batch can't start before the one before it is done
+        if self
+            .batch
+            .as_ref()
+            .is_some_and(|batch| !batch.is_finished())
+        {
+            return wait.map(|wait| wait.max(DEBOUNCE));
+        }
+        wait
+    }
+
+    /// Organize the files that went quiet, or all of them with `flush`, unless it's paused
+    pub fn organize_due(&mut self, options: &Options, flush: bool) {
+        if control::is_paused() {
+            return;
+        }
+        if let Some(window) = options.batch_window {
+            self.organize_batch(window, options, flush);
+            return;
+        }
+        let now = Instant::now();
+        let mut due = self
+            .pending
+            .iter()
+            .filter(|(_, at)| flush || now.duration_since(**at) >= DEBOUNCE)
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        due.sort();
+        for path in due {
+            self.pending.remove(&path);
+            organize(&path, true, options);
+        }
+    }
+
+    /// everything waiting once nothing new turned up for `window`, as one batch on its own
+    /// thread after the one before it. `flush` waits for the batch to be done.
+    fn organize_batch(&mut self, window: Duration, options: &Options, flush: bool) {
+        if let Some(batch) = self.batch.take() {
+            if !flush && !batch.is_finished() {
+                self.batch = Some(batch);
+                return;
+            }
+            // a panic was printed by the hook already
+            let _ = batch.join();
+        }
+        let now = Instant::now();
+        let quiet = self
+            .pending
+            .values()
+            .all(|at| now.duration_since(*at) >= window.max(DEBOUNCE));
+        if self.pending.is_empty() || !(flush || quiet) {
+            return;
+        }
+        let files = self.pending.drain().map(|(path, _)| path).collect();
+        let options = options.clone();
+        let batch = thread::spawn(move || organize_all(files, &options));
+        if flush {
+            let _ = batch.join();
+        } else {
+            self.batch = Some(batch);
+        }
+    }
+}
+
+/// why the watcher behind `res` has to be replaced, if it does
+fn trouble(root: &Path, res: &notify::Result<Event>) -> Option<String> {
+    match res {
+        Err(error) => Some(format!("{error}")),
+        // the kernel queue overflowed, events were dropped
+        Ok(event) if event.need_rescan() => Some("events were lost".to_string()),
+        // deleted or moved away, a new folder with the same name wouldn't be watched
+        Ok(Event {
+            kind: EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_)),
+            paths,
+            ..
+        }) if paths.iter().any(|path| path == root) => Some("the directory went away".to_string()),
+        Ok(_) => None,
+    }
+}
+
+/// organize `files` in the order the screenshots were taken, --max-concurrent-moves of them at a
+/// time, and log what came of it in one line
+fn organize_all(mut files: Vec<PathBuf>, options: &Options) {
+    // anything that isn't named with a time goes after the screenshots
+    files.sort_by_cached_key(|path| {
+        let taken = capture_time(&path.file_name().unwrap_or_default().to_string_lossy());
+        (taken.is_none(), taken, path.clone())
+    });
+    log::info!("Organizing a batch of {} file(s)", files.len());
+    let started = Instant::now();
+    output::take_tally();
+    in_parallel(&files, options.max_concurrent_moves, |path| {
+        organize(path, false, options)
+    });
+    let tally = output::take_tally()
+        .into_values()
+        .fold(Tally::default(), |total, counts| Tally {
+            moved: total.moved + counts.moved,
+            skipped: total.skipped + counts.skipped,
+            failed: total.failed + counts.failed,
+        });
+    log::info!(
+        "Organized the batch of {} file(s) in {:.1}s: {} moved, {} skipped, {} failed",
+        files.len(),
+        started.elapsed().as_secs_f64(),
+        tally.moved,
+        tally.skipped,
+        tally.failed
+    );
+}
+
+/// organize `path` if it's still there, in `daemon_mode` once it's done being written
+fn organize(path: &Path, daemon_mode: bool, options: &Options) {
+    // renamed or deleted before it went quiet
+    if !path.is_file() {
+        return;
+    }
+    match classify(path, options) {
+        Kind::Screenshot(channel) => {
+            log::info!("Moving screenshot: {}", path.display());
+            if let Err(error) = move_file(path, channel.as_deref(), daemon_mode, options) {
+                report_failure(path, &error, options);
+            }
+        }
+        Kind::Video(channel) => {
+            log::info!("Moving video: {}", path.display());
+            if let Err(error) = videos::organize(path, &channel, daemon_mode, options) {
+                report_failure(path, &error, options);
+            }
+        }
+        Kind::Skipped => log::debug!("Leaving {}, it's left out", path.display()),
+        Kind::Other => quarantine::near_miss(path, options),
+    }
+}
+
+/// with --recursive the watcher also sees our own moves into the save dir and folders deeper
+/// than --max-depth, only what a batch run would pick up is organized
+fn in_reach(root: &Path, path: &Path, options: &Options) -> bool {
+    let Ok(relative) = path.strip_prefix(root) else {
+        // reported some other way than root was given, without subfolders it's still in there
+        return !options.recursive;
+    };
+    let folders = relative.components().count().saturating_sub(1);
+    let max_depth = if options.recursive {
+        options.max_depth
+    } else {
+        0
+    };
+    folders <= max_depth && !Scope::new(root, options).excludes(path)
+}
+
+/// cheap change detection, the directory mtime moves whenever an entry is added or removed
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// A duration like 30m, 1h30m or 90s for --rescan-interval, plain numbers are seconds
+pub fn parse_interval(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("{value} isn't a duration like 30m, 1h30m or 90s");
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in value.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let amount: u64 = number.parse().map_err(|_| invalid())?;
+        number.clear();
+        let unit = match c {
+            'd' => 24 * 60 * 60,
+            'h' => 60 * 60,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+        total = amount
+            .checked_mul(unit)
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or_else(invalid)?;
+    }
+    if !number.is_empty() {
+        let seconds = number.parse::<u64>().map_err(|_| invalid())?;
+        total = total.checked_add(seconds).ok_or_else(invalid)?;
+    }
+    if total == 0 {
+        return Err("the interval has to be longer than 0s".to_string());
+    }
+    Ok(Duration::from_secs(total))
+}
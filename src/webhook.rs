@@ -0,0 +1,28 @@
+use serde_json::json;
+use std::path::Path;
+
+use crate::http;
+
+/// Post a Discord compatible message about an organized screenshot, failures are only logged
+pub fn post(url: &str, channel: &str, target: &Path) {
+    let file_name = target
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+
+    let payload = json!({
+        "username": "Twitch Screenshot Organizer",
+        "content": format!("New screenshot from **{channel}**"),
+        "embeds": [{
+            "title": file_name,
+            "fields": [
+                { "name": "Channel", "value": channel, "inline": true },
+                { "name": "Saved to", "value": target.display().to_string() },
+            ],
+        }],
+    });
+
+    if let Err(error) = http::agent().post(url).send_json(&payload) {
+        log::error!("Failed to post to webhook: {error}");
+    }
+}